@@ -0,0 +1,119 @@
+//! The crate is a binary only, so the pathfinding modules are compiled in directly. They only
+//! depend on each other and their dependencies from the manifest.
+
+use bevy::{
+    ecs::entity::Entity,
+    platform::collections::{HashMap, HashSet},
+};
+use criterion::{Criterion, criterion_group, criterion_main};
+
+#[path = "../src/grid"]
+#[allow(dead_code)]
+mod grid {
+    pub use config::GridConfig;
+    pub use grid_pos::GridPos;
+    pub use pathfinding::{FlowField, flight_path};
+
+    mod config;
+    mod grid_pos;
+    mod pathfinding;
+}
+
+use grid::{FlowField, GridConfig, GridPos, flight_path};
+
+const CONFIG: GridConfig = GridConfig::LARGE;
+/// Roughly what a tower with full health costs a walking enemy
+const TOWER_COST: usize = 60;
+const FREE_TILE_COST: usize = 4;
+
+type Towers = HashMap<GridPos, (Entity, usize)>;
+
+fn goals() -> HashMap<GridPos, Entity> {
+    HashMap::from_iter([(
+        GridPos::new(CONFIG.rows / 2, CONFIG.columns - 1),
+        Entity::PLACEHOLDER,
+    )])
+}
+
+fn spawner() -> GridPos {
+    GridPos::new(CONFIG.rows / 2, 0)
+}
+
+fn towers(tiles: impl Iterator<Item = GridPos>) -> Towers {
+    tiles
+        .map(|tile| (tile, (Entity::PLACEHOLDER, TOWER_COST)))
+        .collect()
+}
+
+/// Walls across every fourth column, with the gap alternating between top and bottom
+fn maze() -> Towers {
+    towers((4..CONFIG.columns - 4).step_by(4).flat_map(|col| {
+        let gap = match col % 8 == 0 {
+            true => 0,
+            false => CONFIG.rows - 1,
+        };
+        (0..CONFIG.rows)
+            .filter(move |row| *row != gap)
+            .map(move |row| GridPos::new(row, col))
+    }))
+}
+
+/// A single wall across the middle with one free tile in the corner
+fn nearly_sealed() -> Towers {
+    towers((1..CONFIG.rows).map(|row| GridPos::new(row, CONFIG.columns / 2)))
+}
+
+fn flow_field(towers: &Towers) -> FlowField {
+    FlowField::new(
+        towers,
+        &HashSet::new(),
+        &goals(),
+        &HashMap::new(),
+        FREE_TILE_COST,
+        &CONFIG,
+    )
+}
+
+fn bench_flow_fields(c: &mut Criterion) {
+    for (name, towers) in [
+        ("empty", Towers::new()),
+        ("maze", maze()),
+        ("nearly sealed", nearly_sealed()),
+    ] {
+        c.bench_function(&format!("flow field {name}"), |b| {
+            b.iter(|| flow_field(&towers).path_from(spawner()))
+        });
+    }
+}
+
+/// A tower placed in the middle of a wave, every enemy needs a new path from the same field
+fn bench_wave_repath(c: &mut Criterion) {
+    let towers = maze();
+    let enemies: Vec<GridPos> = (0..CONFIG.rows)
+        .flat_map(|row| [GridPos::new(row, 1), GridPos::new(row, 10)])
+        .collect();
+    c.bench_function("wave re-path", |b| {
+        b.iter(|| {
+            let field = flow_field(&towers);
+            enemies
+                .iter()
+                .filter_map(|enemy| field.path_from(*enemy))
+                .count()
+        })
+    });
+}
+
+fn bench_flight_path(c: &mut Criterion) {
+    let goals = goals();
+    c.bench_function("flight path", |b| {
+        b.iter(|| flight_path(GridPos::new(0, 0), &goals))
+    });
+}
+
+criterion_group!(
+    benches,
+    bench_flow_fields,
+    bench_wave_repath,
+    bench_flight_path
+);
+criterion_main!(benches);
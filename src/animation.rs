@@ -38,14 +38,15 @@ fn run_animations(
 ) {
     for (mut config, mut sprite) in &mut query {
         config.frame_timer.tick(time.delta());
+        if !config.frame_timer.just_finished() {
+            continue;
+        }
 
-        if config.frame_timer.just_finished() {
-            if let Some(atlas) = &mut sprite.texture_atlas {
-                if atlas.index == config.last_sprite {
-                    atlas.index = config.first_sprite;
-                } else {
-                    atlas.index += 1;
-                }
+        if let Some(atlas) = &mut sprite.texture_atlas {
+            if atlas.index == config.last_sprite {
+                atlas.index = config.first_sprite;
+            } else {
+                atlas.index += 1;
             }
         }
     }
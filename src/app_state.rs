@@ -3,6 +3,8 @@ use bevy::{
     prelude::*, window::Monitor,
 };
 
+use crate::AssetLock;
+
 pub struct AppStatePlugin;
 
 impl Plugin for AppStatePlugin {
@@ -13,10 +15,9 @@ impl Plugin for AppStatePlugin {
             .add_sub_state::<TowerPlacingState>()
             .add_sub_state::<UiHoverState>()
             .add_sub_state::<WaveState>()
-            .add_systems(Startup, set_app_state)
             .add_systems(
                 Update,
-                escape_to_menu
+                escape_game
                     .run_if(in_state(AppState::Game))
                     .run_if(input_just_pressed(KeyCode::Escape)),
             )
@@ -26,18 +27,13 @@ impl Plugin for AppStatePlugin {
 
 #[derive(States, Debug, Default, Hash, PartialEq, Eq, Clone)]
 pub enum AppState {
+    /// Preloads all assets, see `ui::loading_screen`
     #[default]
     Loading,
     Menu,
     Game,
 }
 
-// https://github.com/bevyengine/bevy/discussions/17625#discussioncomment-12058159
-// Used this to fix a panic in soundtracks.rs
-fn set_app_state(mut next_state: ResMut<NextState<AppState>>) {
-    next_state.set(AppState::Menu);
-}
-
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, SubStates)]
 #[source(AppState = AppState::Menu)]
 pub enum MenuState {
@@ -80,23 +76,31 @@ pub enum WaveState {
     AllFinished,
 }
 
-fn escape_to_menu(mut next_state: ResMut<NextState<AppState>>) {
-    next_state.set(AppState::Menu);
+/// Escape pauses and resumes the game, once it's over it leads back to the menu. Systems
+/// using Escape for something else clear it before this runs
+pub fn escape_game(
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut next_app_state: ResMut<NextState<AppState>>,
+) {
+    match state.get() {
+        GameState::Running => next_state.set(GameState::Paused),
+        GameState::Paused => next_state.set(GameState::Running),
+        GameState::GameOver => next_app_state.set(AppState::Menu),
+    }
 }
 
-fn clear_game(
-    mut commands: Commands,
-    entities: Query<
-        Entity,
-        (
-            Without<Camera>,
-            Without<Window>,
-            Without<Monitor>,
-            Without<Observer>,
-            Without<PointerId>,
-        ),
-    >,
-) {
+/// Entities that outlive a game, everything else is despawned when leaving it
+type Persistent = (
+    Without<Camera>,
+    Without<Window>,
+    Without<Monitor>,
+    Without<Observer>,
+    Without<PointerId>,
+    Without<AssetLock>,
+);
+
+fn clear_game(mut commands: Commands, entities: Query<Entity, Persistent>) {
     for entity in &entities {
         commands.entity(entity).despawn();
     }
@@ -1,34 +1,45 @@
+use std::time::Duration;
+
 use bevy::{ecs::component::HookContext, prelude::*};
 
 use crate::{
     app_state::GameState,
-    game_loop::{Currency, GameStatistics},
+    game_loop::{GameStatistics, Wallet},
     grid::Grid,
     health::Health,
-    tower::Tower,
+    tower::{Tower, TowerDestroyed},
 };
 
-use super::{Enemy, PathChangedEvent, goal::EnemyGoal};
+use super::{
+    Enemy, EnemyDamaged, EnemyKilled, PathChangedEvent, Shield, goal::EnemyGoal,
+};
 
 pub struct EnemyAttackPlugin;
 
 impl Plugin for EnemyAttackPlugin {
     fn build(&self, app: &mut App) {
-        app.register_type::<Attacking>().add_systems(
-            Update,
-            (
-                advance_enemy_attack_timers,
-                enemy_attacking,
-                enemy_attacking_goal,
-            )
-                .run_if(in_state(GameState::Running)),
-        );
+        app.register_type::<Attacking>()
+            .register_type::<ContactTimer>()
+            .add_systems(
+                Update,
+                (
+                    advance_enemy_attack_timers,
+                    release_dangling_attackers,
+                    contact_damage,
+                    enemy_attacking,
+                    enemy_attacking_goal,
+                    resume_walking,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Running)),
+            );
         app.world_mut()
             .register_component_hooks::<Attacking>()
             .on_remove(|mut world, HookContext { entity, .. }: HookContext| {
                 let weapon_id = world.get::<Attacking>(entity).unwrap().weapon_id;
+                // The weapon is a child, so it is already gone if the enemy got despawned
                 if let Ok(mut entity_cmds) = world.commands().get_entity(weapon_id) {
-                    entity_cmds.despawn();
+                    entity_cmds.try_despawn();
                 }
             });
     }
@@ -45,11 +56,18 @@ impl Attacking {
     pub fn new(target: Entity, weapon_id: Entity) -> Self {
         Attacking { target, weapon_id }
     }
+
+    pub fn target(&self) -> Entity {
+        self.target
+    }
 }
 
+/// Attacks the goal it reached until either of them dies
 #[derive(Component, Reflect)]
 #[reflect(Component)]
-pub struct AttackingGoal;
+pub struct AttackingGoal {
+    pub goal: Entity,
+}
 
 fn advance_enemy_attack_timers(mut enemies: Query<&mut Enemy>, time: Res<Time>) {
     for mut enemy in &mut enemies {
@@ -57,17 +75,107 @@ fn advance_enemy_attack_timers(mut enemies: Query<&mut Enemy>, time: Res<Time>)
     }
 }
 
+/// Stops enemies from attacking a tower which got destroyed or removed in the meantime
+fn release_dangling_attackers(
+    mut commands: Commands,
+    enemies: Query<(Entity, &Attacking)>,
+    towers: Query<(), With<Tower>>,
+) {
+    for (entity, attacking) in &enemies {
+        if !towers.contains(attacking.target) {
+            commands.entity(entity).remove::<Attacking>();
+        }
+    }
+}
+
+/// Ticks while the enemy attacks a tower dealing contact damage, kept between attacks so
+/// the damage follows `TowerType::contact_damage_cooldown` and not the enemy's attack speed
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct ContactTimer(Timer);
+
+impl ContactTimer {
+    /// Advances the timer and returns how often the contact damage is dealt. A changed
+    /// `cooldown` is picked up without starting over
+    fn tick(&mut self, cooldown: Duration, delta: Duration) -> u32 {
+        if self.0.duration() != cooldown {
+            self.0.set_duration(cooldown);
+        }
+        self.0.tick(delta);
+        // Short cooldowns can elapse several times during a long frame
+        self.0.times_finished_this_tick()
+    }
+}
+
+/// Runs before `enemy_attacking`, so the tower is still there even if this attack
+/// destroys it
+fn contact_damage(
+    mut commands: Commands,
+    mut enemies: Query<(
+        Entity,
+        &Enemy,
+        &Attacking,
+        &mut Health,
+        &Transform,
+        Option<&Shield>,
+        Option<&mut ContactTimer>,
+    )>,
+    towers: Query<&Tower, Without<Enemy>>,
+    time: Res<Time>,
+    mut wallet: Wallet,
+    mut killed: EventWriter<EnemyKilled>,
+    mut damaged: EventWriter<EnemyDamaged>,
+) {
+    for (entity, enemy, attacking, mut health, transform, shield, timer) in &mut enemies {
+        let Ok(tower) = towers.get(attacking.target()) else {
+            continue;
+        };
+        let cooldown = tower.contact_damage_cooldown();
+        if tower.contact_damage() == 0 || cooldown.is_zero() {
+            continue;
+        }
+        let Some(mut timer) = timer else {
+            commands
+                .entity(entity)
+                .insert(ContactTimer(Timer::new(cooldown, TimerMode::Repeating)));
+            continue;
+        };
+        let ticks = timer.tick(cooldown, time.delta()) as isize;
+        if ticks == 0 || enemy.immune_to(tower.damage_type()) || Shield::blocks(shield) {
+            continue;
+        }
+
+        let damage =
+            enemy.damage_taken(tower.contact_damage(), tower.damage_type()) * ticks;
+        **health -= damage;
+        damaged.write(EnemyDamaged {
+            damage,
+            translation: transform.translation,
+        });
+        if **health <= 0 {
+            wallet.reward_kill(enemy.reward());
+            commands.entity(entity).despawn();
+            killed.write(EnemyKilled {
+                variant: **enemy,
+                translation: transform.translation,
+            });
+        }
+    }
+}
+
 fn enemy_attacking(
-    mut enemies: Query<(&mut Enemy, &Attacking, Entity, &mut Health)>,
+    mut enemies: Query<(&mut Enemy, &Attacking, Entity, &Health)>,
     mut towers: Query<(&mut Health, &Tower), Without<Enemy>>,
-    mut currency: ResMut<Currency>,
     mut commands: Commands,
     mut grid: ResMut<Grid>,
-    asset_server: Res<AssetServer>,
-    mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
     mut event_writer: EventWriter<PathChangedEvent>,
+    mut destroyed: EventWriter<TowerDestroyed>,
 ) {
-    for (mut enemy, attacking, entity, mut enemy_health) in &mut enemies {
+    for (mut enemy, attacking, entity, enemy_health) in &mut enemies {
+        // Killed by contact damage earlier this frame
+        if **enemy_health <= 0 {
+            continue;
+        }
         if !enemy.attack_timer.finished() {
             continue;
         }
@@ -76,60 +184,194 @@ fn enemy_attacking(
         if let Ok((mut health, tower)) = towers.get_mut(attacking.target) {
             **health -= enemy.damage();
 
-            if **health <= 0 {
+            // Several enemies can destroy the same tower in a single frame
+            if **health <= 0 && grid.tower_origins.contains_key(&attacking.target) {
                 commands.entity(attacking.target).despawn();
                 event_writer.write(PathChangedEvent::now_free(
                     tower.clear_grid(&mut grid, attacking.target),
                 ));
-            }
-
-            **enemy_health -= tower.contact_damage();
-
-            if **enemy_health <= 0 {
-                **currency += enemy.reward();
-                commands.entity(entity).despawn();
-
-                return;
+                destroyed.write(TowerDestroyed);
             }
         }
 
-        commands.entity(entity).remove::<Attacking>().insert((
+        commands.entity(entity).remove::<Attacking>();
+    }
+}
+
+/// Switches enemies back to their walk frames, no matter why they stopped attacking.
+/// Without an `EnemyPath`, `enemy_get_path` picks them up again
+fn resume_walking(
+    mut commands: Commands,
+    mut removed: RemovedComponents<Attacking>,
+    enemies: Query<&Enemy, Without<Attacking>>,
+    asset_server: Res<AssetServer>,
+    mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+) {
+    for entity in removed.read() {
+        // Despawned enemies show up here as well
+        let Ok(enemy) = enemies.get(entity) else {
+            continue;
+        };
+        commands.entity(entity).insert((
             enemy.walk_animation_config(),
-            Sprite {
-                image: asset_server.load(enemy.walk_sprites()),
-                texture_atlas: Some(enemy.walk_layout(&mut texture_atlas_layouts)),
-                ..Default::default()
-            },
+            enemy.walk_sprite(&asset_server, &mut texture_atlas_layouts),
         ));
     }
 }
 
 fn enemy_attacking_goal(
-    mut enemies: Query<(&mut Enemy, &mut Health, Entity), With<AttackingGoal>>,
+    mut enemies: Query<(&mut Enemy, &mut Health, Entity, &Transform, &AttackingGoal)>,
     mut commands: Commands,
-    mut goal: Single<(&EnemyGoal, &mut Health), Without<Enemy>>,
-    mut currency: ResMut<Currency>,
+    mut goals: Query<(&EnemyGoal, &mut Health), Without<Enemy>>,
     mut stats: ResMut<GameStatistics>,
     mut next_state: ResMut<NextState<GameState>>,
+    mut killed: EventWriter<EnemyKilled>,
 ) {
-    let (goal, goal_health) = (goal.0, &mut goal.1);
-    for (mut enemy, mut enemy_health, entity) in &mut enemies {
+    for (mut enemy, mut enemy_health, entity, transform, attacking) in &mut enemies {
         if !enemy.attack_timer.finished() {
             continue;
         }
+        let Ok((goal, mut goal_health)) = goals.get_mut(attacking.goal) else {
+            continue;
+        };
         enemy.attack_timer.reset();
 
         **enemy_health -= goal.thorn_damage();
+        // Enemies that made it to the goal don't pay out, even when the thorns kill them
         if **enemy_health <= 0 {
-            **currency += enemy.reward();
-            stats.money_earned += enemy.reward();
             stats.enemies_killed += 1;
             commands.entity(entity).despawn();
+            killed.write(EnemyKilled {
+                variant: **enemy,
+                translation: transform.translation,
+            });
         }
 
-        ***goal_health -= enemy.damage();
-        if ***goal_health <= 0 {
+        **goal_health -= enemy.damage();
+        if **goal_health <= 0 {
             next_state.set(GameState::GameOver);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use crate::{
+        Orientation, enemy::EnemyType, game_loop::Difficulty, grid::GridPos, test_app,
+        tower::TowerType,
+    };
+
+    use super::*;
+
+    fn spawn_target(app: &mut App, variant: TowerType) -> Entity {
+        app.world_mut()
+            .spawn((
+                Tower::new(variant, Orientation::Up),
+                Health::new(100, Vec2::ZERO),
+            ))
+            .id()
+    }
+
+    /// An enemy in the middle of attacking `tower`, showing its attack frames
+    fn spawn_attacker(app: &mut App, tower: Entity) -> Entity {
+        app.world_mut()
+            .run_system_once(
+                move |mut commands: Commands,
+                      asset_server: Res<AssetServer>,
+                      mut layouts: ResMut<Assets<TextureAtlasLayout>>| {
+                    let enemy = Enemy::new(
+                        GridPos::new(3, 3),
+                        EnemyType::Skeleton,
+                        Difficulty::Normal,
+                    );
+                    commands
+                        .spawn((
+                            Attacking::new(tower, Entity::PLACEHOLDER),
+                            enemy.attack_animation_config(),
+                            enemy.attack_sprite(&asset_server, &mut layouts),
+                            Health::new(12, Vec2::ZERO),
+                            Transform::default(),
+                            enemy,
+                        ))
+                        .id()
+                },
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn removed_targets_release_their_attackers() {
+        let mut app = test_app();
+        app.add_systems(Update, (release_dangling_attackers, resume_walking).chain());
+        let tower = spawn_target(&mut app, TowerType::Wall);
+        let attacker = spawn_attacker(&mut app, tower);
+
+        app.update();
+        assert!(app.world().get::<Attacking>(attacker).is_some());
+
+        app.world_mut().despawn(tower);
+        app.update();
+
+        let entity = app.world().entity(attacker);
+        assert!(entity.get::<Attacking>().is_none());
+        let enemy = entity.get::<Enemy>().unwrap();
+        let walk_sprites = app
+            .world()
+            .resource::<AssetServer>()
+            .load::<Image>(enemy.walk_sprites());
+        let sprite = entity.get::<Sprite>().unwrap();
+        assert_eq!(sprite.image.id(), walk_sprites.id());
+        assert_eq!(
+            sprite.texture_atlas.as_ref().unwrap().index,
+            enemy.walk_sprite_indices().0
+        );
+    }
+
+    #[test]
+    fn spiked_walls_wear_down_their_attackers() {
+        let mut app = test_app();
+        app.add_systems(Update, contact_damage);
+        let wall = spawn_target(&mut app, TowerType::SpikedWall);
+        let attacker = spawn_attacker(&mut app, wall);
+
+        // The first frame only starts the cooldown
+        app.update();
+        let mut health = 12;
+        while app.world().get_entity(attacker).is_ok() {
+            app.world_mut()
+                .resource_mut::<Time>()
+                .advance_by(TowerType::SpikedWall.contact_damage_cooldown());
+            app.update();
+            let Some(left) = app.world().get::<Health>(attacker) else {
+                break;
+            };
+            assert!(left.current < health);
+            health = left.current;
+        }
+
+        assert_eq!(app.world().resource::<GameStatistics>().enemies_killed, 1);
+    }
+
+    #[test]
+    fn halving_the_cooldown_doubles_the_damage() {
+        let damage = TowerType::SpikedWall.contact_damage();
+        // Total contact damage over four seconds at 10 fps
+        let damage_over_window = |cooldown: Duration| {
+            let mut timer = ContactTimer(Timer::new(cooldown, TimerMode::Repeating));
+            (0..40)
+                .map(|_| {
+                    timer.tick(cooldown, Duration::from_millis(100)) as isize * damage
+                })
+                .sum::<isize>()
+        };
+
+        let cooldown = TowerType::SpikedWall.contact_damage_cooldown();
+        assert_eq!(damage_over_window(cooldown), 4 * damage);
+        assert_eq!(
+            damage_over_window(cooldown / 2),
+            2 * damage_over_window(cooldown)
+        );
+    }
+}
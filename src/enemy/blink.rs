@@ -0,0 +1,82 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{
+    app_state::GameState,
+    grid::{Grid, GridConfig},
+};
+
+use super::{
+    Enemy, EnemyType,
+    movement::{EnemyPath, move_enemies},
+};
+
+const BLINK_COOLDOWN: Duration = Duration::from_secs(4);
+/// Maximum amount of path steps skipped by a single blink
+const BLINK_TILES: usize = 4;
+
+pub struct EnemyBlinkPlugin;
+
+impl Plugin for EnemyBlinkPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Blink>().add_systems(
+            Update,
+            (add_blinks, blink)
+                .chain()
+                .before(move_enemies)
+                .run_if(in_state(GameState::Running)),
+        );
+    }
+}
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct Blink {
+    timer: Timer,
+}
+
+fn add_blinks(mut commands: Commands, enemies: Query<(Entity, &Enemy), Added<Enemy>>) {
+    for (entity, enemy) in &enemies {
+        if matches!(**enemy, EnemyType::Blinker) {
+            commands.entity(entity).insert(Blink {
+                timer: Timer::new(BLINK_COOLDOWN, TimerMode::Once),
+            });
+        }
+    }
+}
+
+/// Teleports blinkers ahead along their path. If a tower is in the way, they land in front
+/// of it and look for a new path, which may lead around it instead
+fn blink(
+    mut commands: Commands,
+    mut enemies: Query<(
+        Entity,
+        &mut Enemy,
+        &mut Blink,
+        &mut EnemyPath,
+        &mut Transform,
+    )>,
+    grid: Res<Grid>,
+    config: Res<GridConfig>,
+    time: Res<Time>,
+) {
+    for (entity, mut enemy, mut blink, mut path, mut transform) in &mut enemies {
+        blink.timer.tick(time.delta());
+        if !blink.timer.finished() {
+            continue;
+        }
+        // Mid-corner, try again next frame
+        let Some((tile, clamped)) = path.skip_ahead(enemy.current, BLINK_TILES, &grid)
+        else {
+            continue;
+        };
+        blink.timer.reset();
+
+        enemy.current = tile;
+        transform.translation = enemy.translation_on(tile, &config);
+        if clamped {
+            commands.entity(entity).remove::<EnemyPath>();
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use bevy::prelude::*;
+
+use crate::{
+    app_state::GameState,
+    game_loop::Difficulty,
+    grid::{Grid, GridConfig},
+    health::Health,
+};
+
+use super::{Enemy, EnemyType, spawn_enemy};
+
+/// Share of the max health below which a boss enters its second phase
+const SECOND_PHASE_HEALTH: f32 = 0.5;
+const SECOND_PHASE_VELOCITY: f32 = 1.6;
+/// Skeletons summoned when the second phase starts, and every `SUMMON_INTERVAL` after
+const SUMMON_COUNT: usize = 3;
+const SUMMON_INTERVAL: f32 = 8.;
+
+pub struct EnemyBossPlugin;
+
+impl Plugin for EnemyBossPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Boss>()
+            .register_type::<BossPhase>()
+            .add_systems(
+                Update,
+                (add_bosses, advance_boss_phase, summon_minions)
+                    .chain()
+                    .run_if(in_state(GameState::Running)),
+            );
+    }
+}
+
+#[derive(Reflect, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BossPhase {
+    First,
+    /// Faster and summoning skeletons, entered below `SECOND_PHASE_HEALTH`
+    Second,
+}
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Boss {
+    pub phase: BossPhase,
+    summon_timer: Timer,
+}
+
+impl Boss {
+    /// Velocity multiplier of an entity with this (optional) boss
+    pub fn factor(boss: Option<&Boss>) -> f32 {
+        match boss.map(|boss| boss.phase) {
+            Some(BossPhase::Second) => SECOND_PHASE_VELOCITY,
+            Some(BossPhase::First) | None => 1.,
+        }
+    }
+}
+
+fn add_bosses(mut commands: Commands, enemies: Query<(Entity, &Enemy), Added<Enemy>>) {
+    for (entity, enemy) in &enemies {
+        if !enemy.is_boss() {
+            continue;
+        }
+        commands.entity(entity).insert(Boss {
+            phase: BossPhase::First,
+            // Finished right away, so the first summon happens as the phase changes
+            summon_timer: Timer::from_seconds(0., TimerMode::Once),
+        });
+    }
+}
+
+fn advance_boss_phase(mut bosses: Query<(&mut Boss, &Health, &Enemy)>) {
+    for (mut boss, health, enemy) in &mut bosses {
+        if boss.phase == BossPhase::First
+            && (health.current as f32) < health.max as f32 * SECOND_PHASE_HEALTH
+        {
+            boss.phase = BossPhase::Second;
+            info!("The {:?} entered its second phase", **enemy);
+        }
+    }
+}
+
+/// Minions appear on the free tiles closest to the boss and look for a path on their own
+fn summon_minions(
+    mut commands: Commands,
+    mut bosses: Query<(&mut Boss, &Enemy)>,
+    grid: Res<Grid>,
+    time: Res<Time>,
+    difficulty: Res<Difficulty>,
+    asset_server: Res<AssetServer>,
+    mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+    config: Res<GridConfig>,
+) {
+    for (mut boss, enemy) in &mut bosses {
+        if boss.phase != BossPhase::Second {
+            continue;
+        }
+        boss.summon_timer.tick(time.delta());
+        if !boss.summon_timer.finished() {
+            continue;
+        }
+        boss.summon_timer = Timer::from_seconds(SUMMON_INTERVAL, TimerMode::Once);
+
+        for tile in grid.walkable_near(enemy.current, SUMMON_COUNT) {
+            spawn_enemy(
+                &mut commands,
+                EnemyType::Skeleton,
+                tile,
+                1.,
+                *difficulty,
+                &asset_server,
+                &mut layouts,
+                &config,
+            )
+            .insert(Name::new("Enemy: Skeleton (summoned)"));
+        }
+    }
+}
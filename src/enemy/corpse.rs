@@ -0,0 +1,97 @@
+use std::{f32::consts::FRAC_PI_2, time::Duration};
+
+use bevy::prelude::*;
+
+use crate::app_state::GameState;
+
+use super::EnemyType;
+
+/// Upper limit of corpses on the field, the oldest ones are removed first
+const MAX_CORPSES: usize = 200;
+const CORPSE_LIFETIME: Duration = Duration::from_secs(5);
+
+pub struct EnemyCorpsePlugin;
+
+impl Plugin for EnemyCorpsePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Corpse>()
+            .add_event::<EnemyKilled>()
+            .add_systems(
+                Update,
+                (spawn_corpses.run_if(on_event::<EnemyKilled>), fade_corpses)
+                    .run_if(in_state(GameState::Running)),
+            );
+    }
+}
+
+/// Fired whenever an enemy dies, carrying the place it died at
+#[derive(Event)]
+pub struct EnemyKilled {
+    pub variant: EnemyType,
+    pub translation: Vec3,
+}
+
+/// Purely decorative, corpses are never part of the grid and don't block anything
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Corpse {
+    timer: Timer,
+}
+
+fn spawn_corpses(
+    mut commands: Commands,
+    mut events: EventReader<EnemyKilled>,
+    corpses: Query<(Entity, &Corpse)>,
+    asset_server: Res<AssetServer>,
+    mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+) {
+    let new: Vec<&EnemyKilled> = events.read().collect();
+
+    // Make room for new corpses by removing the ones lying around the longest
+    let overflow = (corpses.iter().count() + new.len()).saturating_sub(MAX_CORPSES);
+    if overflow > 0 {
+        let mut existing: Vec<(Entity, &Corpse)> = corpses.iter().collect();
+        existing.sort_by_key(|(_, corpse)| std::cmp::Reverse(corpse.timer.elapsed()));
+        for (entity, _) in existing.into_iter().take(overflow) {
+            commands.entity(entity).despawn();
+        }
+    }
+
+    for killed in new.into_iter().rev().take(MAX_CORPSES) {
+        commands.spawn((
+            Name::new(format!("Corpse: {:?}", killed.variant)),
+            Corpse {
+                timer: Timer::new(CORPSE_LIFETIME, TimerMode::Once),
+            },
+            Sprite {
+                image: asset_server.load(killed.variant.walk_sprites()),
+                texture_atlas: Some(
+                    killed.variant.corpse_layout(&mut texture_atlas_layouts),
+                ),
+                color: Color::srgb(0.6, 0.6, 0.6),
+                ..Default::default()
+            },
+            Transform {
+                // Slightly below living enemies, so they walk over the corpse
+                translation: killed.translation.with_z(1.5),
+                rotation: Quat::from_rotation_z(FRAC_PI_2),
+                scale: killed.variant.scale(),
+            },
+        ));
+    }
+}
+
+fn fade_corpses(
+    mut commands: Commands,
+    mut corpses: Query<(Entity, &mut Corpse, &mut Sprite)>,
+    time: Res<Time>,
+) {
+    for (entity, mut corpse, mut sprite) in &mut corpses {
+        corpse.timer.tick(time.delta());
+        if corpse.timer.finished() {
+            commands.entity(entity).despawn();
+        } else {
+            sprite.color.set_alpha(corpse.timer.fraction_remaining());
+        }
+    }
+}
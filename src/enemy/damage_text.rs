@@ -0,0 +1,103 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{Settings, app_state::GameState};
+
+/// Upper limit of damage numbers on the field, the oldest ones are removed first
+const MAX_DAMAGE_TEXTS: usize = 150;
+const DAMAGE_TEXT_DURATION: Duration = Duration::from_millis(800);
+const DAMAGE_TEXT_RISE: f32 = 30.;
+
+pub struct EnemyDamageTextPlugin;
+
+impl Plugin for EnemyDamageTextPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<DamageText>()
+            .add_event::<EnemyDamaged>()
+            .add_systems(
+                Update,
+                (
+                    spawn_damage_texts.run_if(on_event::<EnemyDamaged>),
+                    float_damage_texts,
+                )
+                    .run_if(in_state(GameState::Running)),
+            );
+    }
+}
+
+/// Fired whenever an enemy loses health to a tower, carrying where it got hit. Beams deal
+/// their damage a few points per frame and don't fire it
+#[derive(Event)]
+pub struct EnemyDamaged {
+    pub damage: isize,
+    pub translation: Vec3,
+}
+
+/// The damage floating up from an enemy that got hit
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct DamageText {
+    timer: Timer,
+}
+
+fn spawn_damage_texts(
+    mut commands: Commands,
+    mut events: EventReader<EnemyDamaged>,
+    texts: Query<(Entity, &DamageText)>,
+    settings: Res<Settings>,
+) {
+    if !settings.damage_numbers {
+        events.clear();
+        return;
+    }
+    let new: Vec<&EnemyDamaged> = events.read().collect();
+
+    // Make room for new numbers by removing the ones floating around the longest
+    let overflow = (texts.iter().count() + new.len()).saturating_sub(MAX_DAMAGE_TEXTS);
+    if overflow > 0 {
+        let mut existing: Vec<(Entity, &DamageText)> = texts.iter().collect();
+        existing.sort_by_key(|(_, text)| std::cmp::Reverse(text.timer.elapsed()));
+        for (entity, _) in existing.into_iter().take(overflow) {
+            commands.entity(entity).despawn();
+        }
+    }
+
+    for damaged in new.into_iter().rev().take(MAX_DAMAGE_TEXTS) {
+        commands.spawn((
+            Name::new("Damage text"),
+            DamageText {
+                timer: Timer::new(DAMAGE_TEXT_DURATION, TimerMode::Once),
+            },
+            Text2d::new(damaged.damage.to_string()),
+            TextFont {
+                font_size: 14.,
+                ..default()
+            },
+            TextColor(Color::srgb(1., 0.9, 0.3)),
+            // Above enemies and their health bars
+            Transform::from_translation(damaged.translation.with_z(4.)),
+        ));
+    }
+}
+
+fn float_damage_texts(
+    mut commands: Commands,
+    mut texts: Query<(Entity, &mut DamageText, &mut Transform, &mut TextColor)>,
+    time: Res<Time>,
+    settings: Res<Settings>,
+) {
+    for (entity, mut text, mut transform, mut color) in &mut texts {
+        text.timer.tick(time.delta());
+        if text.timer.finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        if settings.reduced_motion {
+            continue;
+        }
+        transform.translation.y +=
+            DAMAGE_TEXT_RISE * time.delta_secs() / DAMAGE_TEXT_DURATION.as_secs_f32();
+        color.0.set_alpha(text.timer.fraction_remaining());
+    }
+}
@@ -0,0 +1,77 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{Settings, app_state::GameState};
+
+use super::{Enemy, movement::EnemyPath};
+
+const ENRAGE_AFTER: Duration = Duration::from_secs(60);
+const BREAK_THROUGH_AFTER: Duration = Duration::from_secs(90);
+pub const ENRAGED_VELOCITY: f32 = 1.5;
+pub const ENRAGED_DAMAGE: f32 = 2.;
+pub const ENRAGED_SCALE: f32 = 1.2;
+pub const ENRAGED_TINT: Color = Color::srgb(1., 0.15, 0.1);
+
+pub struct EnemyEnragePlugin;
+
+impl Plugin for EnemyEnragePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            age_enemies
+                .run_if(in_state(GameState::Running))
+                .run_if(|settings: Res<Settings>| settings.enrage),
+        );
+    }
+}
+
+/// How fed up an enemy is with walking through the maze. Only used with `--enrage`
+#[derive(Reflect, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Rage {
+    Calm,
+    /// Bigger, red, faster and hitting harder
+    Enraged,
+    /// Like enraged, but walks straight through towers instead of around them
+    BreakingThrough,
+}
+
+fn age_enemies(
+    mut commands: Commands,
+    mut enemies: Query<(
+        Entity,
+        &mut Enemy,
+        &mut Transform,
+        &mut Sprite,
+        Has<EnemyPath>,
+    )>,
+    time: Res<Time>,
+) {
+    for (entity, mut enemy, mut transform, mut sprite, has_path) in &mut enemies {
+        enemy.age.tick(time.delta());
+        let rage = match enemy.age.elapsed() {
+            age if age >= BREAK_THROUGH_AFTER => Rage::BreakingThrough,
+            age if age >= ENRAGE_AFTER => Rage::Enraged,
+            _ => Rage::Calm,
+        };
+        if rage == enemy.rage {
+            continue;
+        }
+
+        if enemy.rage == Rage::Calm {
+            // Grow around the feet, so the enemy stays on its tile
+            let offset = enemy.sprite_offset();
+            enemy.rage = rage;
+            transform.translation += enemy.sprite_offset() - offset;
+            transform.scale = enemy.scale();
+            sprite.color = enemy.tint();
+        }
+        enemy.rage = rage;
+
+        // Look for a new path with cheap towers. Attacking enemies get one once they are
+        // done, enemies that still can't find one wait like any other
+        if rage == Rage::BreakingThrough && has_path {
+            commands.entity(entity).remove::<EnemyPath>();
+        }
+    }
+}
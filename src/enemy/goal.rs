@@ -1,17 +1,39 @@
-use bevy::prelude::*;
+use std::time::Duration;
+
+use bevy::{color::Mix, prelude::*};
 
 use crate::{
-    app_state::AppState,
-    grid::{COLUMNS, Grid, GridPos, ROWS, grid_to_world_coords, spawn_grid},
+    Settings,
+    app_state::{AppState, GameState},
+    grid::{Grid, GridConfig, GridPos, Vision, grid_to_world_coords, spawn_grid},
     health::{Health, NoHealthBar},
+    map::Level,
+    theme::{Theme, ThemeColor},
 };
 
+const FLASH_DURATION: Duration = Duration::from_millis(400);
+const LEAK_TEXT_DURATION: Duration = Duration::from_secs(1);
+const LEAK_TEXT_RISE: f32 = 40.;
+
 pub struct EnemyGoalPlugin;
 
 impl Plugin for EnemyGoalPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<EnemyGoal>()
-            .add_systems(OnEnter(AppState::Game), spawn_enemy_goal.after(spawn_grid));
+            .register_type::<GoalLeaks>()
+            .register_type::<GoalFlash>()
+            .register_type::<LeakText>()
+            .add_event::<EnemyLeaked>()
+            .add_systems(OnEnter(AppState::Game), spawn_enemy_goal.after(spawn_grid))
+            .add_systems(
+                Update,
+                (
+                    on_enemy_leaked.run_if(on_event::<EnemyLeaked>),
+                    flash_goal,
+                    float_leak_text,
+                )
+                    .run_if(in_state(GameState::Running)),
+            );
     }
 }
 
@@ -35,7 +57,7 @@ impl EnemyGoal {
     }
 
     /// Returns all the tiles that belong to the goal, relative to the "origin tile"
-    fn other_tiles(&self, origin: &GridPos) -> Vec<GridPos> {
+    pub fn other_tiles(&self, origin: &GridPos) -> Vec<GridPos> {
         match self {
             EnemyGoal::Heart => {
                 vec![
@@ -47,7 +69,8 @@ impl EnemyGoal {
         }
     }
 
-    fn add_unbuildable_surroundings(&self, origin: &GridPos, grid: &mut Grid) {
+    pub fn add_unbuildable_surroundings(&self, origin: &GridPos, grid: &mut Grid) {
+        let config = grid.config();
         grid.unbuildable.extend(
             match self {
                 EnemyGoal::Heart => [
@@ -67,10 +90,17 @@ impl EnemyGoal {
             }
             .into_iter()
             .map(|offset| origin + offset)
-            .filter(|pos| pos.inside_grid_bounds()),
+            .filter(|pos| pos.inside_grid_bounds(&config)),
         );
     }
 
+    /// Vision radius in tiles
+    fn vision(&self) -> f32 {
+        match self {
+            EnemyGoal::Heart => 8.,
+        }
+    }
+
     fn sprite(&self) -> &str {
         match self {
             EnemyGoal::Heart => "sprites/goals/heart.png",
@@ -90,30 +120,209 @@ impl EnemyGoal {
     }
 }
 
-fn spawn_enemy_goal(
+/// Fired once for every enemy that reaches a goal
+#[derive(Event)]
+pub struct EnemyLeaked {
+    pub goal: Entity,
+    /// Taken from the health of the goal right away, before the enemy starts attacking it
+    pub damage: isize,
+}
+
+/// Amount of enemies that made it to this goal
+#[derive(Component, Reflect, Default, Deref)]
+#[reflect(Component)]
+pub struct GoalLeaks(pub usize);
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct GoalFlash(Timer);
+
+/// The damage floating up from the goal whenever an enemy reaches it
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct LeakText {
+    start: Vec3,
+    timer: Timer,
+}
+
+/// One goal for every goal of the level, or a single one at the right edge without a level
+pub fn spawn_enemy_goal(
     mut commands: Commands,
     mut grid: ResMut<Grid>,
     asset_server: Res<AssetServer>,
+    config: Res<GridConfig>,
+    level: Option<Res<Level>>,
+) {
+    let positions = match level {
+        Some(level) => level.goals.clone(),
+        None => vec![GridPos::new(config.rows / 2, config.columns - 2)],
+    };
+    for grid_pos in positions {
+        spawn_goal(&mut commands, grid_pos, &mut grid, &asset_server, &config);
+    }
+}
+
+fn spawn_goal(
+    commands: &mut Commands,
+    grid_pos: GridPos,
+    grid: &mut Grid,
+    asset_server: &AssetServer,
+    config: &GridConfig,
 ) {
-    let grid_pos = GridPos::new(ROWS / 2, COLUMNS - 2);
     let goal = EnemyGoal::Heart;
     let entity = commands
         .spawn((
             Name::new(format!("EnemyGoal: {goal:?}")),
             Health::new(goal.max_hp(), Vec2::ZERO),
             NoHealthBar,
+            Vision(goal.vision()),
+            ThemeColor::Goal,
+            GoalLeaks::default(),
             Sprite::from_image(asset_server.load(goal.sprite())),
             Transform {
-                translation: grid_to_world_coords(grid_pos).extend(1.0) + goal.offset(),
+                translation: grid_to_world_coords(grid_pos, config).extend(1.0)
+                    + goal.offset(),
                 scale: goal.scale(),
                 ..default()
             },
             goal,
         ))
         .id();
-    goal.add_unbuildable_surroundings(&grid_pos, &mut grid);
+    goal.add_unbuildable_surroundings(&grid_pos, grid);
     grid.enemy_goals.insert(grid_pos, entity);
     for pos in goal.other_tiles(&grid_pos) {
         grid.enemy_goals.insert(pos, entity);
     }
 }
+
+fn on_enemy_leaked(
+    mut commands: Commands,
+    mut events: EventReader<EnemyLeaked>,
+    mut goals: Query<(&mut GoalLeaks, &mut Health, &Transform)>,
+    mut this_frame: Local<Vec<Entity>>,
+    settings: Res<Settings>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    this_frame.clear();
+    for EnemyLeaked { goal, damage } in events.read() {
+        let Ok((mut leaks, mut health, transform)) = goals.get_mut(*goal) else {
+            continue;
+        };
+        leaks.0 += 1;
+        **health -= damage;
+        if **health <= 0 {
+            next_state.set(GameState::GameOver);
+        }
+
+        // Spread out the texts of simultaneous leaks, so every single one is visible
+        let index = this_frame.iter().filter(|g| *g == goal).count();
+        this_frame.push(*goal);
+        let offset =
+            Vec3::new((index % 3) as f32 * 20. - 20., (index / 3) as f32 * 20., 0.);
+
+        if !settings.reduced_motion {
+            commands
+                .entity(*goal)
+                .insert(GoalFlash(Timer::new(FLASH_DURATION, TimerMode::Once)));
+        }
+        commands.spawn((
+            Name::new("Leak text"),
+            LeakText {
+                start: transform.translation.with_z(4.) + offset,
+                timer: Timer::new(LEAK_TEXT_DURATION, TimerMode::Once),
+            },
+            Text2d::new(format!("-{damage}")),
+            TextFont {
+                font_size: 20.,
+                ..default()
+            },
+            TextColor(Color::srgb(1., 0.2, 0.2)),
+            Transform::from_translation(transform.translation.with_z(4.) + offset),
+        ));
+    }
+}
+
+fn flash_goal(
+    mut commands: Commands,
+    mut goals: Query<(Entity, &mut GoalFlash, &mut Sprite)>,
+    theme: Res<Theme>,
+    time: Res<Time>,
+    settings: Res<Settings>,
+) {
+    let base = theme.color(ThemeColor::Goal);
+    for (entity, mut flash, mut sprite) in &mut goals {
+        flash.0.tick(time.delta());
+        sprite.color = Color::srgb(1., 0.2, 0.2).mix(&base, flash.0.fraction());
+        if flash.0.finished() || settings.reduced_motion {
+            sprite.color = base;
+            commands.entity(entity).remove::<GoalFlash>();
+        }
+    }
+}
+
+fn float_leak_text(
+    mut commands: Commands,
+    mut texts: Query<(Entity, &mut LeakText, &mut Transform, &mut TextColor)>,
+    time: Res<Time>,
+    settings: Res<Settings>,
+) {
+    for (entity, mut text, mut transform, mut color) in &mut texts {
+        text.timer.tick(time.delta());
+        if text.timer.finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        // The text itself is essential feedback, only the rising and fading is dropped
+        if settings.reduced_motion {
+            transform.translation = text.start;
+            color.0.set_alpha(1.);
+            continue;
+        }
+        transform.translation =
+            text.start + Vec3::Y * LEAK_TEXT_RISE * text.timer.fraction();
+        color.0.set_alpha(text.timer.fraction_remaining());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        enemy::{Enemy, EnemyType},
+        game_loop::Difficulty,
+        test_app,
+    };
+
+    use super::*;
+
+    #[test]
+    fn every_leak_damages_the_goal() {
+        let mut app = test_app();
+        app.add_systems(Update, on_enemy_leaked);
+        let goal = app
+            .world_mut()
+            .spawn((
+                GoalLeaks::default(),
+                Health::new(EnemyGoal::Heart.max_hp(), Vec2::ZERO),
+                Transform::default(),
+            ))
+            .id();
+
+        // All in the same frame
+        let enemy =
+            Enemy::new(GridPos::new(0, 0), EnemyType::Skeleton, Difficulty::Normal);
+        for _ in 0..5 {
+            app.world_mut().send_event(EnemyLeaked {
+                goal,
+                damage: enemy.damage(),
+            });
+        }
+        app.update();
+
+        let goal = app.world().entity(goal);
+        assert_eq!(**goal.get::<GoalLeaks>().unwrap(), 5);
+        assert_eq!(
+            **goal.get::<Health>().unwrap(),
+            EnemyGoal::Heart.max_hp() - 5 * enemy.damage()
+        );
+    }
+}
@@ -0,0 +1,104 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{
+    app_state::GameState,
+    grid::{Grid, GridConfig, GridPos, TILE_SIZE, world_to_grid_coords},
+};
+
+use super::{
+    Enemy,
+    movement::{EnemyPath, move_enemies},
+};
+
+const KNOCKBACK_DISTANCE: f32 = TILE_SIZE * 0.75;
+const KNOCKBACK_DURATION: Duration = Duration::from_millis(200);
+
+pub struct EnemyKnockbackPlugin;
+
+impl Plugin for EnemyKnockbackPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Knockback>().add_systems(
+            Update,
+            knock_back
+                .before(move_enemies)
+                .run_if(in_state(GameState::Running)),
+        );
+    }
+}
+
+/// Shoves a walking enemy away from an explosion. Its normal movement is suspended until
+/// the timer runs out, applying it again replaces the old one
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Knockback {
+    /// World units per second
+    velocity: Vec2,
+    timer: Timer,
+    /// Tile the enemy was on when it got hit
+    from: GridPos,
+}
+
+impl Knockback {
+    /// Away from `impact`, or back along the way the enemy came if it got hit dead center
+    pub fn new(impact: Vec3, enemy: &Enemy, translation: Vec3) -> Self {
+        let direction = (translation - impact)
+            .truncate()
+            .try_normalize()
+            .unwrap_or(-Vec2::from_angle(enemy.orientation.angle()));
+        Self {
+            velocity: direction * KNOCKBACK_DISTANCE / KNOCKBACK_DURATION.as_secs_f32(),
+            timer: Timer::new(KNOCKBACK_DURATION, TimerMode::Once),
+            from: enemy.current,
+        }
+    }
+}
+
+/// Only walking enemies are moved, attackers stay at their target and bosses don't budge.
+/// The push stops early instead of entering a tile the enemy couldn't walk on, or leaving
+/// the grid
+fn knock_back(
+    mut commands: Commands,
+    mut enemies: Query<(
+        Entity,
+        &mut Enemy,
+        &mut Knockback,
+        &mut Transform,
+        Option<&mut EnemyPath>,
+    )>,
+    grid: Res<Grid>,
+    config: Res<GridConfig>,
+    time: Res<Time>,
+) {
+    for (entity, mut enemy, mut knockback, mut transform, path) in &mut enemies {
+        let Some(mut path) = path.filter(|_| !enemy.is_boss()) else {
+            commands.entity(entity).remove::<Knockback>();
+            continue;
+        };
+        knockback.timer.tick(time.delta());
+
+        let translation =
+            transform.translation + (knockback.velocity * time.delta_secs()).extend(0.);
+        let feet = (translation - enemy.sprite_offset()).truncate();
+        let tile = world_to_grid_coords(feet, &config).filter(|tile| {
+            !grid.enemy_goals.contains_key(tile)
+                && (enemy.is_flying()
+                    || !(grid.towers.contains_key(tile) || grid.terrain.contains(tile)))
+        });
+        if let Some(tile) = tile {
+            transform.translation = translation;
+            enemy.current = tile;
+        }
+        if tile.is_some() && !knockback.timer.finished() {
+            continue;
+        }
+
+        commands.entity(entity).remove::<Knockback>();
+        // Pushed onto another tile, the remaining steps don't start from there
+        let center = enemy.translation_on(enemy.current, &config);
+        if enemy.current != knockback.from || !path.resume_from(center) {
+            commands.entity(entity).remove::<EnemyPath>();
+        }
+    }
+}
@@ -1,48 +1,125 @@
 use std::time::Duration;
 
+pub use attack::Attacking;
 use attack::EnemyAttackPlugin;
 use bevy::{
-    input::common_conditions::input_just_pressed, prelude::*, window::PrimaryWindow,
+    color::Mix,
+    input::common_conditions::{input_just_pressed, input_pressed},
+    prelude::*,
+    time::Stopwatch,
+    window::PrimaryWindow,
+};
+use blink::EnemyBlinkPlugin;
+pub use boss::Boss;
+use boss::EnemyBossPlugin;
+use corpse::EnemyCorpsePlugin;
+pub use corpse::EnemyKilled;
+use damage_text::EnemyDamageTextPlugin;
+pub use damage_text::EnemyDamaged;
+use enrage::{
+    ENRAGED_DAMAGE, ENRAGED_SCALE, ENRAGED_TINT, ENRAGED_VELOCITY, EnemyEnragePlugin,
+    Rage,
 };
-pub use goal::EnemyGoal;
 use goal::EnemyGoalPlugin;
+pub use goal::{EnemyGoal, EnemyLeaked, GoalLeaks, spawn_enemy_goal};
+use knockback::EnemyKnockbackPlugin;
+pub use knockback::Knockback;
 use movement::EnemyMovementPlugin;
-pub use movement::PathChangedEvent;
+pub use movement::{EnemyPath, PathChangedEvent};
+pub use path_debug::EnemyPathDebugPlugin;
+use serde::{Deserialize, Serialize};
+use shield::EnemyShieldPlugin;
+pub use shield::Shield;
+use slow::EnemySlowPlugin;
+pub use slow::Slowed;
 use spawner::EnemySpawnerPlugin;
+pub use spawner::{EnemySpawn, SavedSpawner, SpawnMode, SpawnQueue, restore_spawner};
+use status::EnemyStatusPlugin;
 
 use crate::{
-    Orientation,
+    Orientation, RngResource,
     animation::AnimationConfig,
     app_state::AppState,
-    grid::{Grid, GridPos, grid_to_world_coords, world_to_grid_coords},
+    game_loop::Difficulty,
+    grid::{Grid, GridConfig, GridPos, grid_to_world_coords, world_to_grid_coords},
     health::Health,
+    tower::DamageType,
 };
 
+/// Living enemies are drawn above towers and corpses
+const ENEMY_Z: f32 = 2.;
+
 mod attack;
+mod blink;
+mod boss;
+mod corpse;
+mod damage_text;
+mod enrage;
 mod goal;
+mod knockback;
 mod movement;
+mod path_debug;
+mod shield;
+mod slow;
 mod spawner;
+mod status;
 
 pub struct EnemyPlugin;
 
 impl Plugin for EnemyPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<Enemy>()
+            .add_event::<ClearEnemies>()
+            .add_systems(Update, clear_enemies.run_if(on_event::<ClearEnemies>))
             .add_plugins((
                 EnemyMovementPlugin,
                 EnemySpawnerPlugin,
                 EnemyGoalPlugin,
                 EnemyAttackPlugin,
-            ))
-            .add_systems(
-                Update,
-                spawn_enemies_manual
-                    .run_if(input_just_pressed(MouseButton::Right))
-                    .run_if(in_state(AppState::Game)),
-            );
+                EnemyCorpsePlugin,
+                EnemyShieldPlugin,
+                EnemySlowPlugin,
+                EnemyStatusPlugin,
+                EnemyBlinkPlugin,
+                EnemyEnragePlugin,
+                EnemyDamageTextPlugin,
+                EnemyBossPlugin,
+                EnemyKnockbackPlugin,
+            ));
+    }
+}
+
+/// Lets enemies be spawned by right-clicking a free tile and cleared with `Delete`. Only
+/// added in debug builds or when running with `--dev`
+pub struct ManualSpawnPlugin;
+
+impl Plugin for ManualSpawnPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                spawn_enemies_manual.run_if(input_just_pressed(MouseButton::Right)),
+                (|mut events: EventWriter<ClearEnemies>| {
+                    events.write(ClearEnemies {
+                        count_deaths: false,
+                    });
+                })
+                .run_if(input_just_pressed(KeyCode::Delete))
+                // Ctrl+Delete clears the towers instead
+                .run_if(not(input_pressed(KeyCode::ControlLeft))),
+            )
+                .run_if(in_state(AppState::Game)),
+        );
     }
 }
 
+/// Despawns every enemy at once, together with their children (health bars, weapons)
+#[derive(Event, Clone, Copy)]
+pub struct ClearEnemies {
+    /// Whether the removed enemies count as deaths for pathfinding, like regular kills
+    pub count_deaths: bool,
+}
+
 #[derive(Reflect, Component, Deref, DerefMut, Debug)]
 #[reflect(Component)]
 pub struct Enemy {
@@ -51,15 +128,36 @@ pub struct Enemy {
     variant: EnemyType,
     orientation: Orientation,
     attack_timer: Timer,
+    /// Time alive, only advances while the game is running
+    age: Stopwatch,
+    rage: Rage,
+    /// Taken from the difficulty when spawning, so changing it never affects living enemies
+    damage_multiplier: f32,
 }
 
-#[derive(Reflect, Debug, Clone, Copy)]
+/// Serialized as its snake_case name, unknown names fail to deserialize with an error
+/// listing the known ones
+#[derive(Reflect, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
 pub enum EnemyType {
     Skeleton,
+    /// Immune to physical damage
+    Ghost,
+    /// Periodically raises a shield that nullifies all damage
+    Shielded,
+    /// Periodically teleports a few tiles ahead along its path
+    Blinker,
+    /// Slow, but takes a beating and hits towers hard
+    Orc,
+    /// Flies straight to the goal over every tower, only shooting towers can stop it
+    Bat,
+    /// Boss with a huge amount of health, speeds up and summons skeletons once it's badly
+    /// hurt, see the `boss` module
+    SkeletonKing,
 }
 
 impl Enemy {
-    fn new(current: GridPos, variant: EnemyType) -> Self {
+    fn new(current: GridPos, variant: EnemyType, difficulty: Difficulty) -> Self {
         Self {
             attack_timer: Timer::new(
                 Duration::from_secs_f32(variant.attack_cooldown()),
@@ -68,12 +166,72 @@ impl Enemy {
             current,
             variant,
             orientation: Orientation::default(),
+            age: Stopwatch::new(),
+            rage: Rage::Calm,
+            damage_multiplier: difficulty.enemy_damage(),
+        }
+    }
+
+    fn velocity(&self) -> f32 {
+        match self.rage {
+            Rage::Calm => self.variant.velocity(),
+            Rage::Enraged | Rage::BreakingThrough => {
+                self.variant.velocity() * ENRAGED_VELOCITY
+            }
+        }
+    }
+
+    fn damage(&self) -> isize {
+        let damage = self.variant.damage() as f32 * self.damage_multiplier;
+        match self.rage {
+            Rage::Calm => damage.round() as isize,
+            Rage::Enraged | Rage::BreakingThrough => (damage * ENRAGED_DAMAGE) as isize,
+        }
+    }
+
+    /// Once breaking through, towers cost no more than a free tile, so the enemy heads
+    /// straight for the goal and attacks whatever is in the way
+    fn travel_cost(&self, tower_hp: isize) -> usize {
+        match self.rage {
+            Rage::BreakingThrough => 1,
+            Rage::Calm | Rage::Enraged => {
+                (tower_hp as f32 * self.attack_cooldown() / self.damage() as f32) as usize
+                    * 5
+            }
         }
     }
 
+    fn tint(&self) -> Color {
+        match self.rage {
+            Rage::Calm => self.variant.tint(),
+            Rage::Enraged | Rage::BreakingThrough => {
+                self.variant.tint().mix(&ENRAGED_TINT, 0.6)
+            }
+        }
+    }
+
+    fn scale(&self) -> Vec3 {
+        match self.rage {
+            Rage::Calm => self.variant.scale(),
+            Rage::Enraged | Rage::BreakingThrough => self.variant.scale() * ENRAGED_SCALE,
+        }
+    }
+
+    /// Moves the sprite up, so the feet of the enemy are on the center of its tile no
+    /// matter how much it is scaled
+    fn sprite_offset(&self) -> Vec3 {
+        self.feet_offset() * self.scale()
+    }
+
     fn walk_layout(&self, layouts: &mut Assets<TextureAtlasLayout>) -> TextureAtlas {
         match self.variant {
-            EnemyType::Skeleton => TextureAtlas {
+            EnemyType::Skeleton
+            | EnemyType::Ghost
+            | EnemyType::Shielded
+            | EnemyType::Blinker
+            | EnemyType::Orc
+            | EnemyType::Bat
+            | EnemyType::SkeletonKing => TextureAtlas {
                 layout: layouts.add(TextureAtlasLayout::from_grid(
                     UVec2::splat(64),
                     9,
@@ -88,7 +246,13 @@ impl Enemy {
 
     fn attack_layout(&self, layouts: &mut Assets<TextureAtlasLayout>) -> TextureAtlas {
         match self.variant {
-            EnemyType::Skeleton => TextureAtlas {
+            EnemyType::Skeleton
+            | EnemyType::Ghost
+            | EnemyType::Shielded
+            | EnemyType::Blinker
+            | EnemyType::Orc
+            | EnemyType::Bat
+            | EnemyType::SkeletonKing => TextureAtlas {
                 layout: layouts.add(TextureAtlasLayout::from_grid(
                     UVec2::splat(64),
                     6,
@@ -103,7 +267,13 @@ impl Enemy {
 
     fn walk_animation_config(&self) -> AnimationConfig {
         match self.variant {
-            EnemyType::Skeleton => {
+            EnemyType::Skeleton
+            | EnemyType::Ghost
+            | EnemyType::Shielded
+            | EnemyType::Blinker
+            | EnemyType::Orc
+            | EnemyType::Bat
+            | EnemyType::SkeletonKing => {
                 let (first, last) = self.walk_sprite_indices();
                 AnimationConfig::new(first, last, 10)
             }
@@ -112,17 +282,73 @@ impl Enemy {
 
     fn attack_animation_config(&self) -> AnimationConfig {
         match self.variant {
-            EnemyType::Skeleton => {
+            EnemyType::Skeleton
+            | EnemyType::Ghost
+            | EnemyType::Shielded
+            | EnemyType::Blinker
+            | EnemyType::Orc
+            | EnemyType::Bat
+            | EnemyType::SkeletonKing => {
                 let (first, last) = self.attack_sprite_indices();
                 AnimationConfig::new(first, last, 10)
             }
         }
     }
 
+    /// Where the enemy has to be placed to stand in the middle of `tile`
+    fn translation_on(&self, tile: GridPos, config: &GridConfig) -> Vec3 {
+        grid_to_world_coords(tile, config).extend(ENEMY_Z) + self.sprite_offset()
+    }
+
+    fn walk_sprite(
+        &self,
+        asset_server: &AssetServer,
+        layouts: &mut Assets<TextureAtlasLayout>,
+    ) -> Sprite {
+        Sprite {
+            image: asset_server.load(self.walk_sprites()),
+            texture_atlas: Some(self.walk_layout(layouts)),
+            color: self.tint(),
+            ..Default::default()
+        }
+    }
+
+    fn attack_sprite(
+        &self,
+        asset_server: &AssetServer,
+        layouts: &mut Assets<TextureAtlasLayout>,
+    ) -> Sprite {
+        Sprite {
+            image: asset_server.load(self.attack_sprites()),
+            texture_atlas: Some(self.attack_layout(layouts)),
+            color: self.tint(),
+            ..Default::default()
+        }
+    }
+
+    fn weapon_sprite(
+        &self,
+        asset_server: &AssetServer,
+        layouts: &mut Assets<TextureAtlasLayout>,
+    ) -> Sprite {
+        Sprite {
+            image: asset_server.load(self.weapon_sprites()),
+            texture_atlas: Some(self.attack_layout(layouts)),
+            color: self.tint(),
+            ..Default::default()
+        }
+    }
+
     /// Returns (first_sprite_index, last_sprite_index)
     fn walk_sprite_indices(&self) -> (usize, usize) {
         match self.variant {
-            EnemyType::Skeleton => match self.orientation {
+            EnemyType::Skeleton
+            | EnemyType::Ghost
+            | EnemyType::Shielded
+            | EnemyType::Blinker
+            | EnemyType::Orc
+            | EnemyType::Bat
+            | EnemyType::SkeletonKing => match self.orientation {
                 Orientation::Up => (0, 8),
                 Orientation::Down => (18, 26),
                 Orientation::Left => (9, 17),
@@ -134,7 +360,13 @@ impl Enemy {
     /// Returns (first_sprite_index, last_sprite_index)
     fn attack_sprite_indices(&self) -> (usize, usize) {
         match self.variant {
-            EnemyType::Skeleton => match self.orientation {
+            EnemyType::Skeleton
+            | EnemyType::Ghost
+            | EnemyType::Shielded
+            | EnemyType::Blinker
+            | EnemyType::Orc
+            | EnemyType::Bat
+            | EnemyType::SkeletonKing => match self.orientation {
                 Orientation::Up => (0, 5),
                 Orientation::Down => (12, 17),
                 Orientation::Left => (6, 11),
@@ -148,18 +380,36 @@ impl EnemyType {
     fn max_hp(&self) -> isize {
         match self {
             EnemyType::Skeleton => 35,
+            EnemyType::Ghost => 25,
+            EnemyType::Shielded => 40,
+            EnemyType::Blinker => 25,
+            EnemyType::Orc => 80,
+            EnemyType::Bat => 20,
+            EnemyType::SkeletonKing => 600,
         }
     }
 
     pub fn reward(&self) -> i32 {
         match self {
             EnemyType::Skeleton => 5,
+            EnemyType::Ghost => 8,
+            EnemyType::Shielded => 10,
+            EnemyType::Blinker => 9,
+            EnemyType::Orc => 12,
+            EnemyType::Bat => 10,
+            EnemyType::SkeletonKing => 150,
         }
     }
 
     fn damage(&self) -> isize {
         match self {
             EnemyType::Skeleton => 8,
+            EnemyType::Ghost => 5,
+            EnemyType::Shielded => 8,
+            EnemyType::Blinker => 5,
+            EnemyType::Orc => 35,
+            EnemyType::Bat => 6,
+            EnemyType::SkeletonKing => 40,
         }
     }
 
@@ -167,52 +417,160 @@ impl EnemyType {
     fn attack_cooldown(&self) -> f32 {
         match self {
             EnemyType::Skeleton => 1.,
+            EnemyType::Ghost => 1.,
+            EnemyType::Shielded => 1.,
+            EnemyType::Blinker => 1.,
+            EnemyType::Orc => 1.5,
+            EnemyType::Bat => 1.,
+            EnemyType::SkeletonKing => 1.5,
         }
     }
 
-    fn travel_cost(&self, tower_hp: isize) -> usize {
-        (tower_hp as f32 * self.attack_cooldown() / self.damage() as f32) as usize * 5
-    }
-
     fn velocity(&self) -> f32 {
         match self {
             EnemyType::Skeleton => 80.,
+            EnemyType::Ghost => 100.,
+            EnemyType::Shielded => 70.,
+            EnemyType::Blinker => 75.,
+            EnemyType::Orc => 65.,
+            EnemyType::Bat => 70.,
+            EnemyType::SkeletonKing => 45.,
+        }
+    }
+
+    /// Flyers ignore towers when looking for a path and never attack them
+    pub fn is_flying(&self) -> bool {
+        matches!(self, EnemyType::Bat)
+    }
+
+    /// Bosses get a `Boss` component with their phases
+    pub fn is_boss(&self) -> bool {
+        matches!(self, EnemyType::SkeletonKing)
+    }
+
+    /// Multiplier for the damage taken of `damage_type`, 0 makes the enemy immune
+    pub fn resistance(&self, damage_type: DamageType) -> f32 {
+        match (self, damage_type) {
+            (EnemyType::Ghost, DamageType::Physical) => 0.,
+            // Armored
+            (EnemyType::Orc, DamageType::Magic) => 0.5,
+            _ => 1.,
+        }
+    }
+
+    pub fn immune_to(&self, damage_type: DamageType) -> bool {
+        self.resistance(damage_type) == 0.
+    }
+
+    /// What's left of `damage` after the resistance, rounded
+    pub fn damage_taken(&self, damage: isize, damage_type: DamageType) -> isize {
+        (damage as f32 * self.resistance(damage_type)).round() as isize
+    }
+
+    fn tint(&self) -> Color {
+        match self {
+            EnemyType::Skeleton => Color::WHITE,
+            EnemyType::Ghost => Color::srgba(0.6, 0.8, 1., 0.6),
+            EnemyType::Shielded => Color::srgb(1., 0.85, 0.5),
+            EnemyType::Blinker => Color::srgb(0.8, 0.5, 1.),
+            EnemyType::Orc => Color::srgb(0.55, 0.8, 0.45),
+            EnemyType::Bat => Color::srgb(0.45, 0.4, 0.5),
+            EnemyType::SkeletonKing => Color::srgb(1., 0.8, 0.3),
         }
     }
 
     fn walk_sprites(&self) -> &str {
         match self {
-            EnemyType::Skeleton => "sprites/enemies/BODY_skeleton_walk.png",
+            EnemyType::Skeleton
+            | EnemyType::Ghost
+            | EnemyType::Shielded
+            | EnemyType::Blinker
+            | EnemyType::Orc
+            | EnemyType::Bat
+            | EnemyType::SkeletonKing => "sprites/enemies/BODY_skeleton_walk.png",
         }
     }
 
     fn attack_sprites(&self) -> &str {
         match self {
-            EnemyType::Skeleton => "sprites/enemies/BODY_skeleton_attack.png",
+            EnemyType::Skeleton
+            | EnemyType::Ghost
+            | EnemyType::Shielded
+            | EnemyType::Blinker
+            | EnemyType::Orc
+            | EnemyType::Bat
+            | EnemyType::SkeletonKing => "sprites/enemies/BODY_skeleton_attack.png",
         }
     }
 
     fn weapon_sprites(&self) -> &str {
         match self {
-            EnemyType::Skeleton => "sprites/enemies/WEAPON_dagger.png",
+            EnemyType::Skeleton
+            | EnemyType::Ghost
+            | EnemyType::Shielded
+            | EnemyType::Blinker
+            | EnemyType::Orc
+            | EnemyType::Bat
+            | EnemyType::SkeletonKing => "sprites/enemies/WEAPON_dagger.png",
         }
     }
 
-    fn offset(&self) -> Vec3 {
+    /// Frame of the walk sprites that is used for the remains of a killed enemy
+    fn corpse_layout(&self, layouts: &mut Assets<TextureAtlasLayout>) -> TextureAtlas {
         match self {
-            EnemyType::Skeleton => Vec3::new(0., 10., 0.),
+            EnemyType::Skeleton
+            | EnemyType::Ghost
+            | EnemyType::Shielded
+            | EnemyType::Blinker
+            | EnemyType::Orc
+            | EnemyType::Bat
+            | EnemyType::SkeletonKing => TextureAtlas {
+                layout: layouts.add(TextureAtlasLayout::from_grid(
+                    UVec2::splat(64),
+                    9,
+                    4,
+                    None,
+                    None,
+                )),
+                index: 18,
+            },
+        }
+    }
+
+    /// Distance from the center of the unscaled sprite down to the feet of the enemy
+    fn feet_offset(&self) -> Vec3 {
+        match self {
+            EnemyType::Skeleton
+            | EnemyType::Ghost
+            | EnemyType::Shielded
+            | EnemyType::Blinker
+            | EnemyType::Orc
+            | EnemyType::Bat
+            | EnemyType::SkeletonKing => Vec3::new(0., 16., 0.),
         }
     }
 
     fn health_bar_offset(&self) -> Vec2 {
         match self {
-            EnemyType::Skeleton => Vec2::new(0., 25.),
+            EnemyType::Skeleton
+            | EnemyType::Ghost
+            | EnemyType::Shielded
+            | EnemyType::Blinker
+            | EnemyType::Orc
+            | EnemyType::Bat
+            | EnemyType::SkeletonKing => Vec2::new(0., 25.),
         }
     }
 
     fn scale(&self) -> Vec3 {
         match self {
-            EnemyType::Skeleton => Vec3::splat(0.6),
+            EnemyType::Skeleton
+            | EnemyType::Ghost
+            | EnemyType::Shielded
+            | EnemyType::Blinker => Vec3::splat(0.6),
+            EnemyType::Bat => Vec3::splat(0.5),
+            EnemyType::Orc => Vec3::splat(0.75),
+            EnemyType::SkeletonKing => Vec3::splat(1.),
         }
     }
 }
@@ -223,9 +581,16 @@ fn spawn_enemies_manual(
     window: Single<&Window, With<PrimaryWindow>>,
     cam: Single<(&Camera, &GlobalTransform)>,
     grid: Res<Grid>,
+    config: Res<GridConfig>,
     asset_server: Res<AssetServer>,
     mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+    mut rng: ResMut<RngResource>,
+    difficulty: Res<Difficulty>,
 ) {
+    if grid.primary_goal().is_none() {
+        warn!("No enemy goal on the grid, not spawning an enemy");
+        return;
+    }
     let mouse_pos = window.cursor_position();
 
     if let Some(mouse_pos) = mouse_pos {
@@ -233,35 +598,95 @@ fn spawn_enemies_manual(
 
         let world_pos = camera.viewport_to_world_2d(cam_transform, mouse_pos);
         if let Ok(world_pos) = world_pos {
-            if let Some(grid_pos) = world_to_grid_coords(world_pos) {
-                if grid.is_free(&grid_pos) {
-                    let enemy = Enemy::new(grid_pos, EnemyType::Skeleton);
-                    commands.spawn((
-                        Name::new(format!(
-                            "Enemy: {:?} (manually spawned)",
-                            enemy.variant
-                        )),
-                        Health::new(enemy.max_hp(), enemy.health_bar_offset()),
-                        Sprite {
-                            image: asset_server.load(enemy.walk_sprites()),
-                            texture_atlas: Some(
-                                enemy.walk_layout(&mut texture_atlas_layouts),
-                            ),
-                            ..Default::default()
-                        },
-                        Transform {
-                            translation: grid_to_world_coords(grid_pos).extend(2.)
-                                + enemy.offset(),
-                            scale: enemy.scale(),
-                            ..default()
-                        },
-                        enemy.walk_animation_config(),
-                        enemy,
-                    ));
-                }
+            if let Some(grid_pos) =
+                world_to_grid_coords(world_pos, &config).filter(|pos| grid.is_free(pos))
+            {
+                let variant = match rng.bool() {
+                    true => EnemyType::Skeleton,
+                    false => EnemyType::Orc,
+                };
+                spawn_enemy(
+                    &mut commands,
+                    variant,
+                    grid_pos,
+                    1.,
+                    *difficulty,
+                    &asset_server,
+                    &mut texture_atlas_layouts,
+                    &config,
+                )
+                .insert(Name::new(format!("Enemy: {variant:?} (manually spawned)")));
             }
         } else {
             warn!("Unable to get Cursor Position {:?}", world_pos.unwrap_err())
         }
     }
 }
+
+/// Spawns a walking enemy standing on `tile`, with its max health multiplied by `health`
+/// and the difficulty
+pub fn spawn_enemy<'a>(
+    commands: &'a mut Commands,
+    variant: EnemyType,
+    tile: GridPos,
+    health: f32,
+    difficulty: Difficulty,
+    asset_server: &AssetServer,
+    layouts: &mut Assets<TextureAtlasLayout>,
+    config: &GridConfig,
+) -> EntityCommands<'a> {
+    let enemy = Enemy::new(tile, variant, difficulty);
+    commands.spawn((
+        Name::new(format!("Enemy: {:?}", enemy.variant)),
+        Health::new(
+            (enemy.max_hp() as f32 * health * difficulty.enemy_health()).round() as isize,
+            enemy.health_bar_offset(),
+        ),
+        enemy.walk_sprite(asset_server, layouts),
+        Transform {
+            translation: enemy.translation_on(tile, config),
+            scale: enemy.scale(),
+            ..default()
+        },
+        enemy.walk_animation_config(),
+        enemy,
+    ))
+}
+
+fn clear_enemies(
+    mut commands: Commands,
+    mut events: EventReader<ClearEnemies>,
+    enemies: Query<(Entity, &Enemy)>,
+    mut grid: ResMut<Grid>,
+    mut path_change: EventWriter<PathChangedEvent>,
+) {
+    let count_deaths = events.read().any(|event| event.count_deaths);
+
+    let mut blocked = vec![];
+    for (entity, enemy) in &enemies {
+        // Goals and spawners are never enemies, so they can't be caught in here.
+        // Projectiles and tower references to the enemy are cleaned up by their own systems
+        commands.entity(entity).try_despawn();
+        if count_deaths {
+            *grid.death_count.entry(enemy.current).or_default() += 1;
+            blocked.push(enemy.current);
+        }
+    }
+
+    if !blocked.is_empty() {
+        path_change.write(PathChangedEvent::now_blocked(blocked));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn magic_resistance_only_reduces_magic_damage() {
+        let orc = Enemy::new(GridPos::new(0, 0), EnemyType::Orc, Difficulty::Normal);
+        assert_eq!(orc.damage_taken(10, DamageType::Magic), 5);
+        assert_eq!(orc.damage_taken(10, DamageType::Physical), 10);
+        assert!(!orc.immune_to(DamageType::Magic));
+    }
+}
@@ -1,23 +1,35 @@
-use bevy::{platform::collections::HashMap, prelude::*};
+use bevy::{
+    platform::collections::{HashMap, HashSet},
+    prelude::*,
+};
 
 use crate::{
-    Orientation,
+    Orientation, Settings,
     animation::AnimationConfig,
-    app_state::GameState,
-    enemy::Enemy,
-    grid::{Grid, GridPos, TILE_SIZE, grid_to_world_coords},
+    app_state::{AppState, GameState},
+    enemy::{Boss, Enemy, EnemyLeaked, EnemyType, Knockback, Slowed},
+    game_loop::GameStatistics,
+    grid::{FlowField, Grid, GridConfig, GridPos, TILE_SIZE, flight_path},
     health::Health,
     tower::{Tower, place_tower, projectile_damage},
 };
 
-use super::attack::{Attacking, AttackingGoal};
+use super::{
+    attack::{Attacking, AttackingGoal},
+    enrage::Rage,
+};
 
 pub struct EnemyMovementPlugin;
 
 impl Plugin for EnemyMovementPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<EnemyPath>()
+            .register_type::<PathBudget>()
+            .register_type::<Waiting>()
+            .init_resource::<FlowFields>()
+            .init_resource::<PathBudget>()
             .add_event::<PathChangedEvent>()
+            .add_systems(OnExit(AppState::Game), clear_flow_fields)
             .add_systems(
                 Update,
                 (
@@ -25,6 +37,7 @@ impl Plugin for EnemyMovementPlugin {
                         .run_if(on_event::<PathChangedEvent>)
                         .after(place_tower),
                     enemy_get_path.after(check_for_broken_paths),
+                    despawn_unreachable.after(enemy_get_path),
                     move_enemies.before(projectile_damage),
                 )
                     .run_if(in_state(GameState::Running)),
@@ -37,11 +50,117 @@ impl Plugin for EnemyMovementPlugin {
 pub struct EnemyPath {
     pub steps: Vec<GridPos>,
     next: Option<Vec3>,
+    corner: Option<PathCorner>,
+    /// The tile the enemy is walking away from, `Enemy::current` already points to the
+    /// one it's heading to. None while it's standing on `current`.
+    leaving: Option<GridPos>,
 }
 
 impl EnemyPath {
     pub fn new(steps: Vec<GridPos>) -> Self {
-        Self { steps, next: None }
+        Self {
+            steps,
+            next: None,
+            corner: None,
+            leaving: None,
+        }
+    }
+}
+
+impl EnemyPath {
+    /// Jumps up to `tiles` steps ahead without walking them, stopping in front of the first
+    /// tower or goal on the way. Returns the tile landed on and whether the jump had to stop
+    /// early. None while rounding a corner, the enemy isn't on a single tile then.
+    pub(super) fn skip_ahead(
+        &mut self,
+        from: GridPos,
+        tiles: usize,
+        grid: &Grid,
+    ) -> Option<(GridPos, bool)> {
+        if self.corner.is_some() {
+            return None;
+        }
+        let mut landed = from;
+        let mut clamped = false;
+        for _ in 0..tiles {
+            let Some(tile) = self.steps.last().copied() else {
+                break;
+            };
+            // Attacking and leaking still require walking into the tile
+            if grid.towers.contains_key(&tile) || grid.enemy_goals.contains_key(&tile) {
+                clamped = true;
+                break;
+            }
+            self.steps.pop();
+            landed = tile;
+        }
+        self.next = None;
+        self.leaving = None;
+        Some((landed, clamped))
+    }
+
+    /// Walks back to `center`, the middle of `Enemy::current`, before following the steps
+    /// again. False while rounding a corner, its exit was already taken off the steps
+    pub(super) fn resume_from(&mut self, center: Vec3) -> bool {
+        if self.corner.is_some() {
+            return false;
+        }
+        self.next = Some(center);
+        self.leaving = None;
+        true
+    }
+}
+
+/// Quadratic bezier curve used to round off a 90° turn of the path
+#[derive(Reflect, Debug, Clone, Copy)]
+struct PathCorner {
+    start: Vec3,
+    control: Vec3,
+    end: Vec3,
+    /// Tile the enemy enters once it's past the middle of the curve
+    exit: GridPos,
+    /// Progress along the curve from 0 to 1
+    progress: f32,
+}
+
+impl PathCorner {
+    fn point(&self, t: f32) -> Vec3 {
+        let u = 1. - t;
+        self.start * u * u + self.control * 2. * u * t + self.end * t * t
+    }
+
+    /// Cheap approximation of the arc length, good enough for a constant walking speed
+    fn length(&self) -> f32 {
+        (self.start.distance(self.end)
+            + self.start.distance(self.control)
+            + self.control.distance(self.end))
+            * 0.5
+    }
+}
+
+fn orientation_towards(from: GridPos, to: GridPos) -> Orientation {
+    match (to.row > from.row, to.col > from.col) {
+        (true, false) => Orientation::Up,
+        (false, true) => Orientation::Right,
+        _ => match to.row < from.row {
+            true => Orientation::Down,
+            false => Orientation::Left,
+        },
+    }
+}
+
+fn face_walking(
+    enemy: &mut Enemy,
+    orientation: Orientation,
+    animation: &mut AnimationConfig,
+    sprite: &mut Sprite,
+) {
+    if orientation != enemy.orientation {
+        enemy.orientation = orientation;
+        *animation = enemy.walk_animation_config();
+        if let Some(atlas) = &mut sprite.texture_atlas {
+            atlas.index = enemy.walk_sprite_indices().0;
+        }
     }
 }
 
@@ -67,70 +186,56 @@ impl PathChangedEvent {
     }
 }
 
-/// `tiles` maps a `tower_entity` and a `travel_cost` to every `GridPos`
-fn try_get_target(
-    tiles: &HashMap<GridPos, (Entity, usize)>,
-    enemy: &Enemy,
-    goals: &HashMap<GridPos, Entity>,
-    death_count: &HashMap<GridPos, usize>,
-) -> Option<(HashMap<GridPos, GridPos>, GridPos)> {
-    let distance = enemy.current.distance_to_closest(goals);
-    let default_travel_cost = (enemy.velocity() * 2. / TILE_SIZE) as usize;
-
-    // This is the A* algorithm, see https://www.youtube.com/watch?v=-L-WgKMFuhE
-
-    // open contains f_cost, g_cost, parent and tower_entity of every tile
-    let mut open: HashMap<GridPos, (usize, usize, GridPos, Option<Entity>)> =
-        HashMap::from([(enemy.current, (distance, 0, enemy.current, None))]);
-    let mut closed: HashMap<GridPos, GridPos> = HashMap::new();
-
-    while let Some((tile, (_, g_cost, parent, tower_entity))) = open
-        .iter()
-        .min_by_key(|x| x.1.0)
-        .map(|(tile, data)| (*tile, *data))
-    {
-        open.remove(&tile);
-        closed.insert(tile, parent);
+/// Everything that affects the travel costs of an enemy
+type CostProfile = (EnemyType, Rage);
 
-        if goals.contains_key(&tile) {
-            return Some((closed, tile));
-        }
+/// Flow fields shared by all enemies with the same travel costs, so a whole wave needing new
+/// paths at once doesn't run a search per enemy. Cleared whenever towers are placed or
+/// removed, and rebuilt after a while anyway to pick up tower damage and deaths.
+#[derive(Resource, Default)]
+struct FlowFields(HashMap<CostProfile, (FlowField, f32)>);
 
-        for (neighbor, nb_tower_entity, travel_cost) in
-            tile.neighbors(tiles, default_travel_cost, death_count)
-        {
-            if closed.contains_key(&neighbor) {
-                continue;
-            }
-            let new_nb_g_cost = g_cost
-                + if tower_entity.is_some_and(|id| Some(&id) == nb_tower_entity) {
-                    default_travel_cost
-                } else {
-                    travel_cost
-                };
-            if open
-                .get(&neighbor)
-                .is_none_or(|(_, nb_g_cost, _, _)| new_nb_g_cost < *nb_g_cost)
-            {
-                open.insert(
-                    neighbor,
-                    (
-                        new_nb_g_cost + neighbor.distance_to_closest(goals),
-                        new_nb_g_cost,
-                        tile,
-                        nb_tower_entity.copied(),
-                    ),
-                );
-            }
-        }
+/// Seconds a flow field is reused before it is built again
+const FLOW_FIELD_LIFETIME: f32 = 1.;
+
+/// Fields are keyed by their cost profile only, one built on the previous board could
+/// otherwise be reused right after starting a new game
+fn clear_flow_fields(mut flow_fields: ResMut<FlowFields>) {
+    flow_fields.0.clear();
+}
+
+const MAX_PATHS_PER_FRAME: usize = 40;
+
+/// Upper limit of enemies getting a new path in a single frame, the rest wait for the next
+/// ones. Enemies without any path count as well, they start waiting instead.
+#[derive(Resource, Reflect, Clone, Copy)]
+#[reflect(Resource)]
+pub struct PathBudget(pub usize);
+
+impl Default for PathBudget {
+    fn default() -> Self {
+        Self(MAX_PATHS_PER_FRAME)
     }
-    None
 }
 
+/// Enemies without a path stand still until a tile is freed, and only get despawned once
+/// they have been stuck for `UNREACHABLE_TIMEOUT` seconds
+#[derive(Reflect, Component, Clone, Copy, Debug)]
+#[reflect(Component)]
+pub struct Waiting {
+    /// Elapsed game time when the enemy first failed to find a path
+    pub since: f32,
+    /// Set once a tile was freed, cleared when the enemy actually tried again. Enemies
+    /// over the `PathBudget` keep it for the next frames
+    pub retry: bool,
+}
+
+const UNREACHABLE_TIMEOUT: f32 = 30.;
+
 fn enemy_get_path(
     mut commands: Commands,
-    enemies: Query<
-        (&Enemy, Entity),
+    mut enemies: Query<
+        (&Enemy, Entity, Option<&mut Waiting>),
         (
             Without<EnemyPath>,
             Without<Attacking>,
@@ -139,55 +244,163 @@ fn enemy_get_path(
     >,
     towers: Query<&Health, With<Tower>>,
     grid: Res<Grid>,
+    time: Res<Time>,
+    mut flow_fields: ResMut<FlowFields>,
+    mut path_changes: EventReader<PathChangedEvent>,
+    budget: Res<PathBudget>,
 ) {
-    let get_path = |closed: HashMap<GridPos, GridPos>, enemy: &Enemy, goal: GridPos| {
-        let mut path = vec![];
-        let mut current = goal;
-        while current != enemy.current {
-            path.push(current);
-            current = closed[&current];
+    let now = time.elapsed_secs();
+    // Waiting enemies only try again once something opened up
+    let mut freed = false;
+    for event in path_changes.read() {
+        flow_fields.0.clear();
+        freed |= event.now_free;
+    }
+    flow_fields
+        .0
+        .retain(|_, (_, built)| now - *built < FLOW_FIELD_LIFETIME);
+
+    if freed {
+        for (_, _, waiting) in &mut enemies {
+            if let Some(mut waiting) = waiting {
+                waiting.retry = true;
+            }
         }
-        path
-    };
-    for (enemy, entity) in &enemies {
-        if let Some((closed, goal)) = try_get_target(
-            &grid
-                .towers
-                .iter()
-                .filter_map(|(pos, id)| {
-                    towers
-                        .get(*id)
-                        .map(|hp| (*pos, (*id, enemy.travel_cost(**hp))))
-                        .ok()
-                })
-                .collect(),
-            enemy,
-            &grid.enemy_goals,
-            &grid.death_count,
-        ) {
-            let path = get_path(closed, enemy, goal);
-            if !path.is_empty() {
-                commands.entity(entity).insert(EnemyPath::new(path));
+    }
+
+    let enemies = enemies
+        .iter_mut()
+        .filter(|(_, _, waiting)| waiting.as_ref().is_none_or(|waiting| waiting.retry));
+    for (enemy, entity, waiting) in enemies.take(budget.0) {
+        if enemy.is_flying() {
+            // Without any goals the flyer waits like any enemy without a path
+            if let Some(path) = flight_path(enemy.current, &grid.enemy_goals) {
+                commands.entity(entity).remove::<Waiting>();
+                if !path.is_empty() {
+                    commands.entity(entity).insert(EnemyPath::new(path));
+                }
+                continue;
             }
+        }
+        let (flow_field, _) = flow_fields
+            .0
+            .entry((enemy.variant, enemy.rage))
+            .or_insert_with(|| {
+                let field = FlowField::new(
+                    &grid
+                        .towers
+                        .iter()
+                        .filter_map(|(pos, id)| {
+                            towers
+                                .get(*id)
+                                .map(|hp| (*pos, (*id, enemy.travel_cost(**hp))))
+                                .ok()
+                        })
+                        .collect(),
+                    &grid.terrain,
+                    &grid.enemy_goals,
+                    &grid.death_count,
+                    (enemy.velocity() * 2. / TILE_SIZE) as usize,
+                    &grid.config(),
+                );
+                (field, now)
+            });
+        let Some(path) = flow_field.path_from(enemy.current) else {
+            // Keep the time of the first failure, retries don't restart the timeout
+            match waiting {
+                Some(mut waiting) => waiting.retry = false,
+                None => {
+                    commands.entity(entity).insert(Waiting {
+                        since: now,
+                        retry: false,
+                    });
+                }
+            }
+            continue;
+        };
+        commands.entity(entity).remove::<Waiting>();
+        if !path.is_empty() {
+            commands.entity(entity).insert(EnemyPath::new(path));
+        }
+    }
+}
+
+fn despawn_unreachable(
+    mut commands: Commands,
+    enemies: Query<(&Enemy, &Waiting, Entity)>,
+    grid: Res<Grid>,
+    settings: Res<Settings>,
+    mut stats: ResMut<GameStatistics>,
+    time: Res<Time>,
+    mut last_log: Local<Option<f32>>,
+    mut suppressed: Local<usize>,
+) {
+    let now = time.elapsed_secs();
+    for (enemy, waiting, entity) in &enemies {
+        if now - waiting.since < UNREACHABLE_TIMEOUT {
+            continue;
+        }
+        commands.entity(entity).despawn();
+        stats.despawned_no_path += 1;
+
+        // A whole wave of stuck enemies shouldn't flood the console
+        if last_log.is_some_and(|last| now - last < NO_PATH_LOG_INTERVAL) {
+            *suppressed += 1;
+            continue;
+        }
+        *last_log = Some(now);
+
+        if settings.verbose_paths {
+            warn!(
+                current = %enemy.current,
+                goals = ?grid.enemy_goals.keys().collect::<Vec<_>>(),
+                towers = grid.tower_origins.len(),
+                enclosed = !grid.reachable_from_goals(&[]).contains(&enemy.current),
+                suppressed = *suppressed,
+                "No path was found for {UNREACHABLE_TIMEOUT}s! Despawning {:?}",
+                enemy.variant,
+            );
         } else {
-            unreachable!("No path was found! This shouldn't be possible.");
+            warn!(
+                "No path was found for {UNREACHABLE_TIMEOUT}s! Despawning {:?}",
+                enemy.variant
+            );
         }
+        *suppressed = 0;
     }
 }
 
+/// Minimum seconds between two logs about enemies without a path
+const NO_PATH_LOG_INTERVAL: f32 = 1.;
+
 fn check_for_broken_paths(
     mut events: EventReader<PathChangedEvent>,
     mut commands: Commands,
     enemies: Query<(&EnemyPath, Entity), (With<Enemy>, Without<AttackingGoal>)>,
+    mut positions: Query<
+        (&mut Enemy, &mut Transform, Option<&EnemyPath>, Entity),
+        Without<AttackingGoal>,
+    >,
+    grid: Res<Grid>,
+    config: Res<GridConfig>,
 ) {
-    let mut freed_tiles: Vec<&GridPos> = vec![];
-    let mut blocked_tiles: Vec<&GridPos> = vec![];
+    // All changes of a frame are handled together, e.g. a whole shift-dragged row of walls
+    let mut freed_tiles: HashSet<GridPos> = HashSet::new();
+    let mut blocked_tiles: HashSet<GridPos> = HashSet::new();
     for event in events.read() {
         match event.now_free {
             true => freed_tiles.extend(&event.changed),
             false => blocked_tiles.extend(&event.changed),
         }
     }
+    // A tile both freed and blocked in the same frame counts as whatever it is right now
+    let both: Vec<GridPos> = freed_tiles.intersection(&blocked_tiles).copied().collect();
+    for tile in both {
+        match grid.towers.contains_key(&tile) {
+            true => freed_tiles.remove(&tile),
+            false => blocked_tiles.remove(&tile),
+        };
+    }
     // If a new path is available, every Enemy should check if it's more optimal for them
     if !freed_tiles.is_empty() {
         for (_, entity) in &enemies {
@@ -198,136 +411,421 @@ fn check_for_broken_paths(
         }
     }
     if !blocked_tiles.is_empty() {
-        'outer: for (path, entity) in &enemies {
+        // Towers can be placed on top of enemies, get them out before they end up inside.
+        // Flyers just keep hovering above them
+        for (mut enemy, mut transform, path, entity) in &mut positions {
+            if enemy.is_flying() || !blocked_tiles.contains(&enemy.current) {
+                continue;
+            }
+            let leaving = path.and_then(|path| path.leaving);
+            let Some(tile) = leaving
+                .filter(|tile| !grid.towers.contains_key(tile))
+                .or_else(|| grid.nearest_walkable(enemy.current))
+            else {
+                continue;
+            };
+
+            // Still between two tiles, turning around is enough
+            if leaving != Some(tile) {
+                transform.translation = enemy.translation_on(tile, &config);
+            }
+            enemy.current = tile;
+            commands
+                .entity(entity)
+                .remove::<EnemyPath>()
+                .remove::<Attacking>();
+        }
+
+        // Every path got dropped above already
+        if !freed_tiles.is_empty() {
+            return;
+        }
+        for (path, entity) in &enemies {
+            // An enemy walking around a corner has already popped the tile it is heading to
+            if path
+                .corner
+                .is_some_and(|corner| blocked_tiles.contains(&corner.exit))
+            {
+                commands.entity(entity).remove::<EnemyPath>();
+                continue;
+            }
             if path
                 .steps
                 .last()
-                .is_some_and(|tile| blocked_tiles.contains(&tile))
+                .is_some_and(|tile| blocked_tiles.contains(tile))
             {
                 continue;
             }
-            for tile in &blocked_tiles {
-                if path.steps.contains(tile) {
-                    commands.entity(entity).remove::<EnemyPath>();
-                    continue 'outer;
-                }
+            if path.steps.iter().any(|tile| blocked_tiles.contains(tile)) {
+                commands.entity(entity).remove::<EnemyPath>();
             }
         }
     }
 }
 
+/// A walking enemy, together with the effects changing its speed
+type Walker = (
+    &'static mut EnemyPath,
+    &'static mut Enemy,
+    &'static mut AnimationConfig,
+    &'static mut Sprite,
+    &'static mut Transform,
+    Entity,
+    Option<&'static Slowed>,
+    Option<&'static Boss>,
+);
+
 pub fn move_enemies(
-    mut query: Query<(
-        &mut EnemyPath,
-        &mut Enemy,
-        &mut AnimationConfig,
-        &mut Sprite,
-        &mut Transform,
-        Entity,
-    )>,
+    // Knocked back enemies are moved by `knock_back` instead
+    mut query: Query<Walker, Without<Knockback>>,
     time: Res<Time>,
     grid: Res<Grid>,
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+    settings: Res<Settings>,
+    mut leaked: EventWriter<EnemyLeaked>,
+    config: Res<GridConfig>,
 ) {
-    for (mut path, mut enemy, mut animation, mut sprite, mut pos, entity) in &mut query {
-        let next = match path.next {
-            Some(target_pos) => target_pos,
-            None => {
-                let tile = path.steps.pop().unwrap();
-                let orientation =
-                    match (tile.row > enemy.current.row, tile.col > enemy.current.col) {
-                        (true, false) => Orientation::Up,
-                        (false, true) => Orientation::Right,
-                        _ => match tile.row < enemy.current.row {
-                            true => Orientation::Down,
-                            false => Orientation::Left,
-                        },
+    'enemies: for (
+        mut path,
+        mut enemy,
+        mut animation,
+        mut sprite,
+        mut pos,
+        entity,
+        slowed,
+        boss,
+    ) in &mut query
+    {
+        // Distance left to walk this frame. Reaching a waypoint doesn't end the movement,
+        // the rest is spent walking towards the following one.
+        let mut budget = time.delta_secs()
+            * enemy.velocity()
+            * Slowed::factor(slowed)
+            * Boss::factor(boss);
+
+        while budget > 0. {
+            if let Some(mut corner) = path.corner {
+                let length = corner.length();
+                let progress = (corner.progress + budget / length).min(1.);
+                budget -= (progress - corner.progress) * length;
+                corner.progress = progress;
+                pos.translation = corner.point(progress);
+
+                if progress >= 0.5 && enemy.current != corner.exit {
+                    let orientation = orientation_towards(enemy.current, corner.exit);
+                    face_walking(&mut enemy, orientation, &mut animation, &mut sprite);
+                    path.leaving = Some(enemy.current);
+                    enemy.current = corner.exit;
+                }
+
+                path.corner = (progress < 1.).then_some(corner);
+                if path.corner.is_none() {
+                    path.leaving = None;
+                }
+                continue;
+            }
+
+            let next = match path.next {
+                Some(target_pos) => target_pos,
+                // Entering a new tile is the only point where the enemy has to decide
+                // whether it keeps walking or starts attacking
+                None => {
+                    let Some(tile) = path.steps.pop() else {
+                        break;
                     };
+                    let orientation = orientation_towards(enemy.current, tile);
 
-                if let Some(tower_entity) = grid.towers.get(&tile) {
-                    if orientation != enemy.orientation {
-                        enemy.orientation = orientation;
-                    }
-                    let weapon_id = commands
-                        .spawn((
-                            enemy.attack_animation_config(),
-                            Sprite {
-                                image: asset_server.load(enemy.weapon_sprites()),
-                                texture_atlas: Some(
-                                    enemy.attack_layout(&mut texture_atlas_layouts),
+                    if let Some(tower_entity) =
+                        grid.towers.get(&tile).filter(|_| !enemy.is_flying())
+                    {
+                        if orientation != enemy.orientation {
+                            enemy.orientation = orientation;
+                        }
+                        let weapon_id = commands
+                            .spawn((
+                                enemy.attack_animation_config(),
+                                enemy.weapon_sprite(
+                                    &asset_server,
+                                    &mut texture_atlas_layouts,
                                 ),
-                                ..Default::default()
-                            },
-                            ChildOf(entity),
-                        ))
-                        .id();
-
-                    commands.entity(entity).remove::<EnemyPath>().insert((
-                        Attacking::new(*tower_entity, weapon_id),
-                        enemy.attack_animation_config(),
-                        Sprite {
-                            image: asset_server.load(enemy.attack_sprites()),
-                            texture_atlas: Some(
-                                enemy.attack_layout(&mut texture_atlas_layouts),
-                            ),
-                            ..Default::default()
-                        },
-                    ));
-                    return;
-                } else if grid.enemy_goals.contains_key(&tile) {
-                    if orientation != enemy.orientation {
-                        enemy.orientation = orientation;
-                    }
-                    commands
-                        .entity(entity)
-                        .remove::<EnemyPath>()
-                        .insert((
-                            AttackingGoal,
+                                ChildOf(entity),
+                            ))
+                            .id();
+
+                        commands.entity(entity).remove::<EnemyPath>().insert((
+                            Attacking::new(*tower_entity, weapon_id),
                             enemy.attack_animation_config(),
-                            Sprite {
-                                image: asset_server.load(enemy.attack_sprites()),
-                                texture_atlas: Some(
-                                    enemy.attack_layout(&mut texture_atlas_layouts),
+                            enemy
+                                .attack_sprite(&asset_server, &mut texture_atlas_layouts),
+                        ));
+                        continue 'enemies;
+                    } else if let Some(goal) = grid.enemy_goals.get(&tile) {
+                        leaked.write(EnemyLeaked {
+                            goal: *goal,
+                            damage: enemy.damage(),
+                        });
+                        // Arriving counts as the first hit, attacking the goal only starts
+                        // after a full cooldown
+                        enemy.attack_timer.reset();
+                        if orientation != enemy.orientation {
+                            enemy.orientation = orientation;
+                        }
+                        commands
+                            .entity(entity)
+                            .remove::<EnemyPath>()
+                            .insert((
+                                AttackingGoal { goal: *goal },
+                                enemy.attack_animation_config(),
+                                enemy.attack_sprite(
+                                    &asset_server,
+                                    &mut texture_atlas_layouts,
                                 ),
-                                ..Default::default()
-                            },
-                        ))
-                        .with_child((
-                            enemy.attack_animation_config(),
-                            Sprite {
-                                image: asset_server.load(enemy.weapon_sprites()),
-                                texture_atlas: Some(
-                                    enemy.attack_layout(&mut texture_atlas_layouts),
+                            ))
+                            .with_child((
+                                enemy.attack_animation_config(),
+                                enemy.weapon_sprite(
+                                    &asset_server,
+                                    &mut texture_atlas_layouts,
                                 ),
-                                ..Default::default()
-                            },
-                        ));
-                    return;
-                }
+                            ));
+                        continue 'enemies;
+                    }
+
+                    face_walking(&mut enemy, orientation, &mut animation, &mut sprite);
+                    let previous = enemy.current;
+                    enemy.current = tile;
+                    path.leaving = Some(previous);
 
-                if orientation != enemy.orientation {
-                    enemy.orientation = orientation;
-                    *animation = enemy.walk_animation_config();
-                    if let Some(atlas) = &mut sprite.texture_atlas {
-                        atlas.index = enemy.walk_sprite_indices().0;
+                    if let Some(exit) = path.steps.last().copied().filter(|exit| {
+                        settings.smooth_paths
+                            && corner_walkable(&grid, previous, tile, *exit)
+                    }) {
+                        path.steps.pop();
+                        path.corner = Some(PathCorner {
+                            start: pos.translation,
+                            control: enemy.translation_on(tile, &config),
+                            end: enemy.translation_on(exit, &config),
+                            exit,
+                            progress: 0.,
+                        });
+                        continue;
                     }
+
+                    let next = enemy.translation_on(tile, &config);
+                    path.next = Some(next);
+
+                    next
                 }
-                enemy.current = tile;
-                let next = grid_to_world_coords(tile).extend(2.) + enemy.offset();
-                path.next = Some(next);
+            };
 
-                next
+            let distance = pos.translation.distance(next);
+            if distance <= budget {
+                pos.translation = next;
+                budget -= distance;
+                path.next = None;
+                path.leaving = None;
+            } else {
+                pos.translation = pos.translation.move_towards(next, budget);
+                budget = 0.;
             }
+        }
+    }
+}
+
+/// Whether walking `from` -> `corner` -> `to` is a turn that can be rounded off.
+/// The curve cuts through the inner corner tile, so that one must not be blocked.
+fn corner_walkable(grid: &Grid, from: GridPos, corner: GridPos, to: GridPos) -> bool {
+    let inner = GridPos::new(
+        from.row + to.row - corner.row,
+        from.col + to.col - corner.col,
+    );
+    let is_blocked = |pos: &GridPos| {
+        grid.towers.contains_key(pos)
+            || grid.enemy_goals.contains_key(pos)
+            || grid.terrain.contains(pos)
+    };
+
+    from.row != to.row && from.col != to.col && !is_blocked(&to) && !is_blocked(&inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{game_loop::Difficulty, test_app, tower::TowerType};
+
+    use super::*;
+
+    /// Runs `move_enemies` with a manually advanced clock on an empty small grid
+    fn walking_app() -> App {
+        let mut app = test_app();
+        app.add_systems(Update, move_enemies);
+        app
+    }
+
+    fn spawn_walker(app: &mut App, start: GridPos, steps: Vec<GridPos>) -> Entity {
+        let enemy = Enemy::new(start, EnemyType::Skeleton, Difficulty::Normal);
+        app.world_mut()
+            .spawn((
+                EnemyPath::new(steps),
+                enemy.walk_animation_config(),
+                Sprite::default(),
+                Transform::from_translation(
+                    enemy.translation_on(start, &GridConfig::SMALL),
+                ),
+                enemy,
+            ))
+            .id()
+    }
+
+    fn run_for(app: &mut App, seconds: f32) {
+        app.world_mut()
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs_f32(seconds));
+        app.update();
+    }
+
+    #[test]
+    fn a_long_frame_stops_on_the_last_tile() {
+        let mut app = walking_app();
+        let target = GridPos::new(2, 3);
+        let walker = spawn_walker(&mut app, GridPos::new(2, 2), vec![target]);
+
+        // Enough to walk across the whole grid
+        run_for(&mut app, 10.);
+
+        let enemy = app.world().get::<Enemy>(walker).unwrap();
+        assert_eq!(enemy.current, target);
+        assert_eq!(
+            app.world().get::<Transform>(walker).unwrap().translation,
+            enemy.translation_on(target, &GridConfig::SMALL)
+        );
+    }
+
+    #[test]
+    fn slowed_enemies_cover_less_distance() {
+        let mut app = walking_app();
+        // Straight down a row, so the distance walked is the distance from the start
+        let row = |row| (3..12).rev().map(|col| GridPos::new(row, col)).collect();
+        let starts = [GridPos::new(2, 2), GridPos::new(6, 2)];
+        let walker = spawn_walker(&mut app, starts[0], row(2));
+        let slowed = spawn_walker(&mut app, starts[1], row(6));
+        app.world_mut()
+            .entity_mut(slowed)
+            .insert(Slowed::new(0.5, 10.));
+
+        run_for(&mut app, 0.2);
+
+        let walked = |entity: Entity, start: GridPos| {
+            let world = app.world();
+            let enemy = world.get::<Enemy>(entity).unwrap();
+            world
+                .get::<Transform>(entity)
+                .unwrap()
+                .translation
+                .distance(enemy.translation_on(start, &GridConfig::SMALL))
         };
-        let direction = next - pos.translation;
-        if direction.element_sum() != 0. {
-            pos.translation +=
-                direction.normalize() * time.delta_secs() * enemy.velocity();
+        let full_speed = walked(walker, starts[0]);
+        assert!(full_speed > 0.);
+        assert!((walked(slowed, starts[1]) - full_speed * 0.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn paths_break_through_the_weakest_tower() {
+        let mut grid = Grid::new(GridConfig {
+            rows: 5,
+            columns: 7,
+        });
+        grid.enemy_goals
+            .insert(GridPos::new(2, 6), Entity::PLACEHOLDER);
+
+        let mut app = test_app();
+        app.init_resource::<PathBudget>()
+            .init_resource::<FlowFields>()
+            .add_systems(Update, enemy_get_path);
+        // A wall across the board, the top tower only has a tenth of the others' health
+        let weak = GridPos::new(0, 3);
+        for row in 0..5 {
+            let tile = GridPos::new(row, 3);
+            let hp = match tile == weak {
+                true => 10,
+                false => 100,
+            };
+            let tower = app
+                .world_mut()
+                .spawn((
+                    Tower::new(TowerType::Wall, Orientation::Up),
+                    Health::new(hp, Vec2::ZERO),
+                ))
+                .id();
+            grid.towers.insert(tile, tower);
+        }
+        let wall: Vec<GridPos> = grid.towers.keys().copied().collect();
+        app.insert_resource(grid);
+        let enemy = app
+            .world_mut()
+            .spawn(Enemy::new(
+                GridPos::new(2, 0),
+                EnemyType::Skeleton,
+                Difficulty::Normal,
+            ))
+            .id();
+
+        app.update();
+
+        let path = app.world().get::<EnemyPath>(enemy).unwrap();
+        assert!(path.steps.contains(&weak));
+        assert!(
+            path.steps
+                .iter()
+                .all(|tile| *tile == weak || !wall.contains(tile))
+        );
+    }
+
+    #[test]
+    fn freed_enemies_over_the_budget_retry_later() {
+        let mut grid = Grid::new(GridConfig {
+            rows: 5,
+            columns: 5,
+        });
+        grid.enemy_goals
+            .insert(GridPos::new(2, 4), Entity::PLACEHOLDER);
+        let wall: Vec<GridPos> = (0..5).map(|row| GridPos::new(row, 2)).collect();
+        grid.terrain.extend(wall.iter().copied());
+
+        let mut app = test_app();
+        app.insert_resource(grid)
+            .insert_resource(PathBudget(1))
+            .init_resource::<FlowFields>()
+            .add_systems(Update, enemy_get_path);
+        let enemies: Vec<Entity> = (0..3)
+            .map(|row| {
+                let current = GridPos::new(row, 0);
+                app.world_mut()
+                    .spawn(Enemy::new(current, EnemyType::Skeleton, Difficulty::Normal))
+                    .id()
+            })
+            .collect();
+
+        // One enemy per frame finds out it's trapped
+        for _ in 0..3 {
+            app.update();
+        }
+        for enemy in &enemies {
+            assert!(app.world().get::<Waiting>(*enemy).is_some());
+        }
+
+        app.world_mut().resource_mut::<Grid>().terrain.clear();
+        app.world_mut().send_event(PathChangedEvent::now_free(wall));
+        for _ in 0..3 {
+            app.update();
         }
-        if pos.translation.distance(next) >= direction.length() {
-            path.next = None;
+        for enemy in &enemies {
+            assert!(app.world().get::<Waiting>(*enemy).is_none());
+            let path = app.world().get::<EnemyPath>(*enemy).unwrap();
+            assert!(path.steps.contains(&GridPos::new(2, 4)));
         }
     }
 }
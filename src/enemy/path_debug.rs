@@ -0,0 +1,57 @@
+use bevy::prelude::*;
+
+use crate::{
+    app_state::AppState,
+    grid::{Grid, GridConfig, TILE_SIZE, grid_to_world_coords},
+};
+
+use super::{Enemy, EnemyPath};
+
+/// Draws the remaining path of every enemy and marks the towers it's going to attack on
+/// the way. Only added when running with `--debug-paths`
+pub struct EnemyPathDebugPlugin;
+
+impl Plugin for EnemyPathDebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, draw_enemy_paths.run_if(in_state(AppState::Game)));
+    }
+}
+
+/// Gizmos are rebuilt every frame, past this many enemies the rest is left out
+const MAX_DRAWN_PATHS: usize = 64;
+
+const PATH_COLOR: Color = Color::srgba(0.3, 0.8, 1., 0.6);
+const ATTACK_TILE_COLOR: Color = Color::srgba(1., 0.2, 0.2, 0.9);
+
+fn draw_enemy_paths(
+    mut gizmos: Gizmos,
+    enemies: Query<(&Enemy, &EnemyPath, &Transform)>,
+    grid: Res<Grid>,
+    config: Res<GridConfig>,
+) {
+    for (enemy, path, transform) in enemies.iter().take(MAX_DRAWN_PATHS) {
+        // The next step is the last one, so the line starts at the enemy
+        let points = std::iter::once(transform.translation.truncate()).chain(
+            std::iter::once(enemy.current)
+                .chain(path.steps.iter().rev().copied())
+                .map(|tile| grid_to_world_coords(tile, &config)),
+        );
+        gizmos.linestrip_2d(points, PATH_COLOR);
+
+        // Flyers pass over towers without stopping
+        if enemy.is_flying() {
+            continue;
+        }
+        for tile in path
+            .steps
+            .iter()
+            .filter(|tile| grid.towers.contains_key(*tile))
+        {
+            gizmos.rect_2d(
+                Isometry2d::from_translation(grid_to_world_coords(*tile, &config)),
+                Vec2::splat(TILE_SIZE * 0.8),
+                ATTACK_TILE_COLOR,
+            );
+        }
+    }
+}
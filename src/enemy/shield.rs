@@ -0,0 +1,102 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::app_state::GameState;
+
+use super::{Enemy, EnemyType};
+
+const SHIELD_DOWN: Duration = Duration::from_secs(3);
+const SHIELD_UP: Duration = Duration::from_millis(1500);
+const BUBBLE_COLOR: Color = Color::srgba(0.4, 0.7, 1., 0.35);
+
+pub struct EnemyShieldPlugin;
+
+impl Plugin for EnemyShieldPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Shield>()
+            .register_type::<ShieldBubble>()
+            .add_systems(
+                Update,
+                (add_shields, cycle_shields).run_if(in_state(GameState::Running)),
+            );
+    }
+}
+
+/// Nullifies all damage while it is up. Damage is checked on impact, so projectiles
+/// fired before the shield came up still fizzle
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Shield {
+    pub up: bool,
+    timer: Timer,
+    bubble: Entity,
+}
+
+impl Shield {
+    /// Whether an entity with this (optional) shield currently takes damage
+    pub fn blocks(shield: Option<&Shield>) -> bool {
+        shield.is_some_and(|shield| shield.up)
+    }
+}
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct ShieldBubble;
+
+fn add_shields(
+    mut commands: Commands,
+    enemies: Query<(Entity, &Enemy), Added<Enemy>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for (entity, enemy) in &enemies {
+        if !matches!(**enemy, EnemyType::Shielded) {
+            continue;
+        }
+
+        let bubble = commands
+            .spawn((
+                Name::new("Shield bubble"),
+                ShieldBubble,
+                Mesh2d(meshes.add(Circle::new(40.))),
+                MeshMaterial2d(materials.add(ColorMaterial::from_color(BUBBLE_COLOR))),
+                Transform::from_xyz(0., -10., 0.5),
+                Visibility::Hidden,
+                ChildOf(entity),
+            ))
+            .id();
+        commands.entity(entity).insert(Shield {
+            up: false,
+            timer: Timer::new(SHIELD_DOWN, TimerMode::Once),
+            bubble,
+        });
+    }
+}
+
+fn cycle_shields(
+    mut shields: Query<&mut Shield>,
+    mut bubbles: Query<&mut Visibility, With<ShieldBubble>>,
+    time: Res<Time>,
+) {
+    for mut shield in &mut shields {
+        shield.timer.tick(time.delta());
+        if !shield.timer.finished() {
+            continue;
+        }
+
+        shield.up = !shield.up;
+        let duration = match shield.up {
+            true => SHIELD_UP,
+            false => SHIELD_DOWN,
+        };
+        shield.timer = Timer::new(duration, TimerMode::Once);
+
+        if let Ok(mut visibility) = bubbles.get_mut(shield.bubble) {
+            *visibility = match shield.up {
+                true => Visibility::Inherited,
+                false => Visibility::Hidden,
+            };
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use bevy::prelude::*;
+
+use crate::app_state::GameState;
+
+pub struct EnemySlowPlugin;
+
+impl Plugin for EnemySlowPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Slowed>()
+            .add_systems(Update, expire_slows.run_if(in_state(GameState::Running)));
+    }
+}
+
+/// Reduces the movement speed of an enemy until the timer runs out. Applying it again
+/// replaces the old one, so the timer starts over instead of the effects stacking
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Slowed {
+    /// Multiplier for the velocity, below 1
+    pub factor: f32,
+    timer: Timer,
+}
+
+impl Slowed {
+    pub fn new(factor: f32, duration: f32) -> Self {
+        Self {
+            factor,
+            timer: Timer::from_seconds(duration, TimerMode::Once),
+        }
+    }
+
+    /// Velocity multiplier of an entity with this (optional) slow
+    pub fn factor(slowed: Option<&Slowed>) -> f32 {
+        slowed.map_or(1., |slowed| slowed.factor)
+    }
+}
+
+fn expire_slows(
+    mut commands: Commands,
+    mut slowed: Query<(Entity, &mut Slowed)>,
+    time: Res<Time>,
+) {
+    for (entity, mut slowed) in &mut slowed {
+        slowed.timer.tick(time.delta());
+        if slowed.timer.finished() {
+            commands.entity(entity).remove::<Slowed>();
+        }
+    }
+}
@@ -1,16 +1,19 @@
-use std::time::Duration;
-
-use bevy::prelude::*;
+use bevy::{
+    color::Mix, input::common_conditions::input_just_pressed,
+    platform::collections::HashSet, prelude::*,
+};
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    RngResource,
-    app_state::GameState,
-    game_loop::{SpawnerInfo, WaveInfo, WaveStart},
-    grid::{Grid, GridPos, grid_to_world_coords},
-    health::Health,
+    RngResource, Settings,
+    app_state::{AppState, GameState},
+    game_loop::{Difficulty, Sandbox, WaveInfo, WaveStart, Waves},
+    grid::{Grid, GridConfig, GridPos, grid_to_world_coords, spawn_grid},
+    map::Level,
+    theme::{Theme, ThemeColor},
 };
 
-use super::{Enemy, EnemyType};
+use super::{Enemy, EnemyType, spawn_enemy};
 
 pub struct EnemySpawnerPlugin;
 
@@ -18,41 +21,137 @@ impl Plugin for EnemySpawnerPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<EnemySpawn>()
             .register_type::<SpawnQueue>()
+            .register_type::<SpawnMode>()
+            .add_systems(
+                OnEnter(AppState::Game),
+                (
+                    reserve_level_spawners
+                        .after(spawn_grid)
+                        .run_if(resource_exists::<Level>),
+                    reset_spawns_held,
+                ),
+            )
             .add_systems(
                 Update,
                 (
                     spawn_enemy_spawners.run_if(on_event::<WaveStart>),
-                    spawn_enemies.run_if(in_state(GameState::Running)),
+                    spawn_enemies
+                        .run_if(in_state(GameState::Running))
+                        .run_if(|held: Res<SpawnsHeld>| !held.0),
+                    telegraph_spawns
+                        .run_if(|settings: Res<Settings>| settings.spawn_warning)
+                        .run_if(in_state(GameState::Running)),
+                    toggle_spawns_held
+                        .run_if(input_just_pressed(KeyCode::KeyN))
+                        .run_if(|sandbox: Res<Sandbox>| sandbox.0)
+                        .run_if(in_state(GameState::Running)),
                 ),
             );
     }
 }
 
+/// Upper limit of enemies alive at once, spawners wait until there is room again
+const MAX_ENEMIES: usize = 150;
+
+/// Spawners start to pulse this long before they release their next enemy
+const SPAWN_WARNING_SECS: f32 = 1.;
+const SPAWN_WARNING_COLOR: Color = Color::srgb(1., 0.85, 0.3);
+
+/// Toggled with N in sandbox mode, spawners keep their queues but release nothing
+#[derive(Resource, Default)]
+struct SpawnsHeld(bool);
+
+fn reset_spawns_held(mut commands: Commands) {
+    commands.insert_resource(SpawnsHeld::default());
+}
+
+fn toggle_spawns_held(mut held: ResMut<SpawnsHeld>) {
+    held.0 = !held.0;
+    match held.0 {
+        true => info!("Enemy spawns held"),
+        false => info!("Enemy spawns resumed"),
+    }
+}
+
 #[derive(Reflect, Component)]
 #[reflect(Component)]
-struct EnemySpawn {
+pub struct EnemySpawn {
     variant: EnemySpawnType,
     pos: GridPos,
-    #[reflect(ignore)]
-    info: SpawnerInfo,
+    mode: SpawnMode,
+    /// Position in the order spawners appeared in, used to assign spawn groups
+    index: usize,
+}
+
+/// How a spawner releases the enemies of its queue
+#[derive(Reflect, Deserialize, Clone, Copy, Default, Debug)]
+pub enum SpawnMode {
+    /// One enemy per spawn interval
+    #[default]
+    Single,
+    /// Every `every` spawn intervals, the next `count` enemies are released as a tight
+    /// formation, `delay` seconds apart, each on its own tile near the spawner
+    Burst {
+        count: usize,
+        delay: f32,
+        every: usize,
+    },
 }
 
 #[derive(Reflect, Component)]
 #[reflect(Component)]
-struct SpawnQueue {
-    enemies: Vec<EnemyType>,
+pub struct SpawnQueue {
+    /// Enemies left to spawn with the delay before each of them, the next one is last
+    enemies: Vec<(EnemyType, f32)>,
     timer: Timer,
+    /// Spawn intervals since the last burst was released
+    since_burst: usize,
+    /// Tiles left for the members of the current burst, the closest one is popped first
+    burst: Vec<GridPos>,
+    burst_timer: Timer,
+    /// Multiplier for the max health of the spawned enemies, set by the wave
+    health: f32,
 }
 
 impl SpawnQueue {
-    fn new(info: &SpawnerInfo, wave: usize) -> Self {
-        Self {
-            enemies: (info.enemies)(wave),
-            timer: Timer::new(
-                Duration::from_secs_f32((info.interval)(wave)),
-                TimerMode::Repeating,
-            ),
+    fn new(mut enemies: Vec<(EnemyType, f32)>, mode: SpawnMode, health: f32) -> Self {
+        let delay = match mode {
+            SpawnMode::Single => 0.,
+            SpawnMode::Burst { delay, .. } => delay,
+        };
+        enemies.reverse();
+        let mut queue = Self {
+            enemies,
+            timer: Timer::default(),
+            since_burst: 0,
+            burst: vec![],
+            burst_timer: Timer::from_seconds(delay, TimerMode::Once),
+            health,
+        };
+        queue.wait_for_next();
+        queue
+    }
+
+    /// Restarts the timer with the delay of the next enemy, an empty queue finishes at once
+    fn wait_for_next(&mut self) {
+        let delay = self.enemies.last().map_or(0., |(_, delay)| *delay);
+        self.timer = Timer::from_seconds(delay, TimerMode::Once);
+    }
+
+    /// Reserves tiles for a new burst if one is due, the first member takes `spawn_tile`
+    fn start_burst(&mut self, mode: SpawnMode, spawn_tile: GridPos, grid: &Grid) {
+        let SpawnMode::Burst { count, every, .. } = mode else {
+            return;
+        };
+        self.since_burst += 1;
+        if self.since_burst < every {
+            return;
         }
+        self.since_burst = 0;
+        // Fewer free tiles than members just makes for a smaller burst
+        self.burst = grid.walkable_near(spawn_tile, count.min(self.enemies.len()));
+        self.burst.reverse();
+        self.burst_timer.reset();
     }
 }
 
@@ -62,8 +161,13 @@ enum EnemySpawnType {
 }
 
 impl EnemySpawn {
-    fn new(variant: EnemySpawnType, pos: GridPos, info: SpawnerInfo) -> Self {
-        Self { variant, pos, info }
+    fn new(variant: EnemySpawnType, pos: GridPos, index: usize, mode: SpawnMode) -> Self {
+        Self {
+            variant,
+            pos,
+            mode,
+            index,
+        }
     }
 
     /// Returns all the tiles that belong to the spawner, relative to the "origin tile"
@@ -80,6 +184,7 @@ impl EnemySpawn {
     }
 
     fn add_unbuildable_surroundings(&self, grid: &mut Grid) {
+        let config = grid.config();
         grid.unbuildable.extend(
             match self.variant {
                 EnemySpawnType::RedTower => [
@@ -99,15 +204,15 @@ impl EnemySpawn {
             }
             .into_iter()
             .map(|offset| self.pos + offset)
-            .filter(|pos| pos.inside_grid_bounds()),
+            .filter(|pos| pos.inside_grid_bounds(&config)),
         );
     }
 
-    fn spawn_point(&self) -> Vec2 {
-        grid_to_world_coords(self.pos)
-            + match self.variant {
-                EnemySpawnType::RedTower => Vec2::new(10., 0.),
-            }
+    /// Tile new enemies appear on, it's also their first `Enemy::current`
+    fn spawn_tile(&self) -> GridPos {
+        match self.variant {
+            EnemySpawnType::RedTower => self.pos,
+        }
     }
 
     fn sprite(&self) -> &str {
@@ -129,6 +234,11 @@ impl EnemySpawn {
     }
 }
 
+/// Spawners are kept at least this many tiles away from the closest goal, if possible
+const MIN_GOAL_DISTANCE: usize = 35;
+/// Random positions tried before the distance constraint gets relaxed
+const PLACEMENT_ATTEMPTS: usize = 500;
+
 fn spawn_enemy_spawners(
     mut event: EventReader<WaveStart>,
     mut commands: Commands,
@@ -136,93 +246,366 @@ fn spawn_enemy_spawners(
     asset_server: Res<AssetServer>,
     mut rng: ResMut<RngResource>,
     spawner_query: Query<(Entity, &EnemySpawn)>,
+    mut wave_info: ResMut<WaveInfo>,
+    waves: Res<Waves>,
+    config: Res<GridConfig>,
+    level: Option<Res<Level>>,
 ) {
     let Some(wave) = event.read().next() else {
         error!("Failed to read WaveStart event!");
         return;
     };
 
-    for info in &wave.new_spawners {
-        loop {
-            let grid_pos = GridPos::random(&mut rng);
+    let mut next_index = spawner_query.iter().count();
+    // Spawners walled in by terrain would never get their enemies to a goal
+    let reachable = grid.reachable_through_towers();
+    for _ in 0..wave.new_spawners {
+        let spawner = match &level {
+            Some(level) => level.spawners.get(next_index).map(|pos| {
+                EnemySpawn::new(
+                    EnemySpawnType::RedTower,
+                    *pos,
+                    next_index,
+                    waves.spawn_mode,
+                )
+            }),
+            None => random_spawner(
+                &grid,
+                &mut rng,
+                &config,
+                &reachable,
+                next_index,
+                waves.spawn_mode,
+            ),
+        };
 
-            let spawner = EnemySpawn::new(EnemySpawnType::RedTower, grid_pos, *info);
-            let other = spawner.other_tiles();
+        let Some(spawner) = spawner else {
+            error!("Found no place for a new spawner, skipping it");
+            wave_info.spawner_failed();
+            continue;
+        };
+        let queue = SpawnQueue::new(
+            waves.enemies_for(**wave, next_index),
+            spawner.mode,
+            waves.health_for(**wave),
+        );
+        next_index += 1;
 
-            if spawner.pos.distance_to_closest(&grid.enemy_goals) < 35
-                || other.iter().any(|pos| !grid.is_free(pos))
-            {
-                continue;
-            }
+        spawn_spawner(
+            &mut commands,
+            spawner,
+            Some(queue),
+            &mut grid,
+            &asset_server,
+            &config,
+        );
+    }
+
+    for (entity, spawner) in &spawner_query {
+        commands.entity(entity).insert(SpawnQueue::new(
+            waves.enemies_for(**wave, spawner.index),
+            spawner.mode,
+            waves.health_for(**wave),
+        ));
+    }
+}
+
+/// Tries random positions, preferring ones far away from the goals. None if the grid is too
+/// crowded for another spawner
+fn random_spawner(
+    grid: &Grid,
+    rng: &mut RngResource,
+    config: &GridConfig,
+    reachable: &HashSet<GridPos>,
+    index: usize,
+    mode: SpawnMode,
+) -> Option<EnemySpawn> {
+    // Small boards might not have any tile that far away, so start with at most half
+    // the distance between opposite corners instead of wasting the first attempts
+    let mut min_distance =
+        MIN_GOAL_DISTANCE.min((config.rows + config.columns) as usize / 2);
+    let mut attempts = 0;
 
-            spawner.add_unbuildable_surroundings(&mut grid);
-
-            let entity = commands
-                .spawn((
-                    Name::new(format!("Spawner: {:?}", spawner.variant)),
-                    Sprite::from_image(asset_server.load(spawner.sprite())),
-                    Transform {
-                        translation: grid_to_world_coords(grid_pos).extend(1.)
-                            + spawner.offset(),
-                        scale: spawner.scale(),
-                        ..Default::default()
-                    },
-                    spawner,
-                    SpawnQueue::new(info, **wave),
-                ))
-                .id();
-
-            grid.enemy_spawners.insert(grid_pos, entity);
-            for tile in other.into_iter() {
-                grid.enemy_spawners.insert(tile, entity);
+    loop {
+        if attempts == PLACEMENT_ATTEMPTS {
+            if min_distance == 0 {
+                return None;
             }
-            break;
+            // The grid is too crowded, allow spawners closer to the goal
+            min_distance /= 2;
+            attempts = 0;
         }
+        attempts += 1;
+
+        let grid_pos = GridPos::random(rng, config);
+        let spawner = EnemySpawn::new(EnemySpawnType::RedTower, grid_pos, index, mode);
+
+        if spawner
+            .pos
+            .distance_to_closest(&grid.enemy_goals)
+            .is_some_and(|distance| distance < min_distance)
+            || !grid.is_free(&spawner.pos)
+            || spawner.other_tiles().iter().any(|pos| !grid.is_free(pos))
+            || !reachable.contains(&spawner.spawn_tile())
+        {
+            continue;
+        }
+        return Some(spawner);
     }
+}
 
-    for (entity, spawner) in &spawner_query {
-        commands
-            .entity(entity)
-            .insert(SpawnQueue::new(&spawner.info, **wave));
+/// Keeps the spawner positions of the level free from the start, so no tower ends up where
+/// a spawner appears later on
+fn reserve_level_spawners(mut grid: ResMut<Grid>, level: Res<Level>, waves: Res<Waves>) {
+    for (index, pos) in level.spawners.iter().enumerate() {
+        let spawner =
+            EnemySpawn::new(EnemySpawnType::RedTower, *pos, index, waves.spawn_mode);
+        spawner.add_unbuildable_surroundings(&mut grid);
+        grid.unbuildable.insert(spawner.pos);
+        grid.unbuildable.extend(spawner.other_tiles());
     }
 }
 
+/// Tints spawners that are about to release an enemy, pulsing unless motion is reduced.
+/// Only the color is touched, `apply_theme` keeps setting the base color
+fn telegraph_spawns(
+    mut spawners: Query<(&mut Sprite, Option<&SpawnQueue>), With<EnemySpawn>>,
+    held: Res<SpawnsHeld>,
+    theme: Res<Theme>,
+    time: Res<Time>,
+    settings: Res<Settings>,
+) {
+    let base = theme.color(ThemeColor::Spawner);
+    let pulse = match settings.reduced_motion {
+        true => 0.5,
+        false => (time.elapsed_secs() * std::f32::consts::TAU * 2.).sin() * 0.25 + 0.35,
+    };
+    for (mut sprite, queue) in &mut spawners {
+        let warning = !held.0
+            && queue.is_some_and(|queue| {
+                !queue.enemies.is_empty()
+                    && queue.burst.is_empty()
+                    && queue.timer.remaining_secs() <= SPAWN_WARNING_SECS
+            });
+        let color = match warning {
+            true => base.mix(&SPAWN_WARNING_COLOR, pulse),
+            false => base,
+        };
+        if sprite.color != color {
+            sprite.color = color;
+        }
+    }
+}
+
+/// Blocks the tiles of the spawner on the grid and spawns it. Spawners without a queue are
+/// done for the current wave.
+fn spawn_spawner(
+    commands: &mut Commands,
+    spawner: EnemySpawn,
+    queue: Option<SpawnQueue>,
+    grid: &mut Grid,
+    asset_server: &AssetServer,
+    config: &GridConfig,
+) {
+    spawner.add_unbuildable_surroundings(grid);
+    let grid_pos = spawner.pos;
+    let other = spawner.other_tiles();
+
+    let mut entity_cmds = commands.spawn((
+        Name::new(format!("Spawner: {:?}", spawner.variant)),
+        Sprite::from_image(asset_server.load(spawner.sprite())),
+        ThemeColor::Spawner,
+        Transform {
+            translation: grid_to_world_coords(grid_pos, config).extend(1.)
+                + spawner.offset(),
+            scale: spawner.scale(),
+            ..Default::default()
+        },
+        spawner,
+    ));
+    if let Some(queue) = queue {
+        entity_cmds.insert(queue);
+    }
+    let entity = entity_cmds.id();
+
+    grid.enemy_spawners.insert(grid_pos, entity);
+    for tile in other.into_iter() {
+        grid.enemy_spawners.insert(tile, entity);
+    }
+}
+
+/// A spawner as stored in a save file, the timers of its queue start over when loading
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SavedSpawner {
+    pos: GridPos,
+    index: usize,
+    /// Enemies left to spawn this wave in spawn order, together with the health multiplier
+    queue: Option<(Vec<(EnemyType, f32)>, f32)>,
+}
+
+impl EnemySpawn {
+    pub fn save(&self, queue: Option<&SpawnQueue>) -> SavedSpawner {
+        SavedSpawner {
+            pos: self.pos,
+            index: self.index,
+            queue: queue.map(|queue| {
+                (queue.enemies.iter().rev().copied().collect(), queue.health)
+            }),
+        }
+    }
+}
+
+pub fn restore_spawner(
+    commands: &mut Commands,
+    saved: SavedSpawner,
+    mode: SpawnMode,
+    grid: &mut Grid,
+    asset_server: &AssetServer,
+    config: &GridConfig,
+) {
+    let spawner = EnemySpawn::new(EnemySpawnType::RedTower, saved.pos, saved.index, mode);
+    let queue = saved
+        .queue
+        .map(|(enemies, health)| SpawnQueue::new(enemies, mode, health));
+    spawn_spawner(commands, spawner, queue, grid, asset_server, config);
+}
+
 fn spawn_enemies(
     mut commands: Commands,
     time: Res<Time>,
     mut spawners: Query<(Entity, &EnemySpawn, &mut SpawnQueue)>,
+    enemies: Query<(), With<Enemy>>,
+    grid: Res<Grid>,
     asset_server: Res<AssetServer>,
     mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
     mut wave: ResMut<WaveInfo>,
+    config: Res<GridConfig>,
+    difficulty: Res<Difficulty>,
 ) {
+    // Nothing to walk towards, the enemies would only be despawned for lacking a path
+    if grid.primary_goal().is_none() {
+        warn_once!("No enemy goal on the grid, holding back all spawns");
+        return;
+    }
+    let mut alive = enemies.iter().count();
+    let delta = time.delta().mul_f32(difficulty.spawn_rate());
+
     for (entity, spawner, mut queue) in &mut spawners {
-        queue.timer.tick(time.delta());
-        if !queue.timer.just_finished() {
-            continue;
-        }
+        // The regular interval is paused while a burst is being released
+        let tile = match queue.burst.is_empty() {
+            true => {
+                queue.timer.tick(delta);
+                if !queue.timer.finished() || alive >= MAX_ENEMIES {
+                    continue;
+                }
+                queue.start_burst(spawner.mode, spawner.spawn_tile(), &grid);
+                queue.burst.pop().unwrap_or(spawner.spawn_tile())
+            }
+            false => {
+                queue.burst_timer.tick(delta);
+                // Members held back by the cap are released one by one once there is room
+                if !queue.burst_timer.finished() || alive >= MAX_ENEMIES {
+                    continue;
+                }
+                queue.burst_timer.reset();
+                queue.burst.pop().unwrap_or(spawner.spawn_tile())
+            }
+        };
 
-        let Some(enemy_ty) = queue.enemies.pop() else {
+        let Some((enemy_ty, _)) = queue.enemies.pop() else {
             commands.entity(entity).remove::<SpawnQueue>();
             wave.done_this_wave += 1;
-            return;
+            // Spawners without any enemies this wave finish right away, don't hold up the
+            // others
+            continue;
         };
-        let enemy = Enemy::new(spawner.pos, enemy_ty);
-
-        commands.spawn((
-            Name::new(format!("Enemy: {:?}", enemy.variant)),
-            Health::new(enemy.max_hp(), enemy.health_bar_offset()),
-            Sprite {
-                image: asset_server.load(enemy.walk_sprites()),
-                texture_atlas: Some(enemy.walk_layout(&mut texture_atlas_layouts)),
-                ..Default::default()
-            },
-            Transform {
-                translation: spawner.spawn_point().extend(2.) + enemy.offset(),
-                scale: enemy.scale(),
-                ..default()
-            },
-            enemy.walk_animation_config(),
-            enemy,
+        queue.wait_for_next();
+        alive += 1;
+
+        spawn_enemy(
+            &mut commands,
+            enemy_ty,
+            tile,
+            queue.health,
+            *difficulty,
+            &asset_server,
+            &mut texture_atlas_layouts,
+            &config,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{grid::world_to_grid_coords, test_app};
+
+    use super::*;
+
+    /// `spawn_enemies` on the small grid, with a spawner at `spawn_tile` releasing
+    /// `enemies` without any delay
+    fn spawning_app(grid: Grid, spawn_tile: GridPos, enemies: &[EnemyType]) -> App {
+        let mut app = test_app();
+        app.insert_resource(grid).add_systems(Update, spawn_enemies);
+        let mode = SpawnMode::Single;
+        let enemies = enemies.iter().map(|enemy| (*enemy, 0.)).collect();
+        app.world_mut().spawn((
+            EnemySpawn::new(EnemySpawnType::RedTower, spawn_tile, 0, mode),
+            SpawnQueue::new(enemies, mode, 1.),
         ));
+        app
+    }
+
+    #[test]
+    fn spawners_hold_back_without_a_goal() {
+        let grid = Grid::new(GridConfig::SMALL);
+        assert_eq!(grid.primary_goal(), None);
+
+        let mut app = spawning_app(grid, GridPos::new(5, 5), &[EnemyType::Skeleton; 3]);
+        for _ in 0..3 {
+            app.update();
+        }
+        let world = app.world_mut();
+        assert_eq!(world.query::<&Enemy>().iter(world).count(), 0);
+    }
+
+    #[test]
+    fn enemies_spawn_on_their_spawner_tile() {
+        let mut grid = Grid::new(GridConfig::SMALL);
+        grid.enemy_goals
+            .insert(GridPos::new(10, 30), Entity::PLACEHOLDER);
+        let spawn_tile = GridPos::new(5, 5);
+        // The boss is scaled up, but still has to stand in the middle of the tile
+        let mut app = spawning_app(
+            grid,
+            spawn_tile,
+            &[EnemyType::Skeleton, EnemyType::SkeletonKing],
+        );
+        for _ in 0..3 {
+            app.update();
+        }
+
+        let world = app.world_mut();
+        let mut spawned: Vec<_> = world
+            .query::<(&Enemy, &Transform)>()
+            .iter(world)
+            .map(|(enemy, transform)| {
+                assert_eq!(enemy.current, spawn_tile);
+                assert_eq!(
+                    transform.translation,
+                    enemy.translation_on(spawn_tile, &GridConfig::SMALL)
+                );
+                assert_eq!(
+                    world_to_grid_coords(
+                        transform.translation.truncate()
+                            - enemy.sprite_offset().truncate(),
+                        &GridConfig::SMALL
+                    ),
+                    Some(spawn_tile)
+                );
+                **enemy
+            })
+            .collect();
+        spawned.sort_by_key(|variant| variant.is_boss());
+        assert_eq!(spawned, [EnemyType::Skeleton, EnemyType::SkeletonKing]);
     }
 }
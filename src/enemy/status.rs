@@ -0,0 +1,99 @@
+use bevy::prelude::*;
+
+use crate::app_state::GameState;
+
+use super::{Enemy, Shield, Slowed};
+
+const ICON_SIZE: f32 = 8.;
+/// Horizontal distance between the centers of two icons
+const ICON_SPACING: f32 = 11.;
+/// Icons float right above the health bar
+const ICON_HEIGHT: f32 = 36.;
+
+pub struct EnemyStatusPlugin;
+
+impl Plugin for EnemyStatusPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<StatusIcon>().add_systems(
+            Update,
+            sync_status_icons.run_if(in_state(GameState::Running)),
+        );
+    }
+}
+
+/// Effects shown as an icon above affected enemies. New effects need a variant in here and a
+/// check in [`sync_status_icons`]
+#[derive(Reflect, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StatusEffect {
+    Shield,
+    Slowed,
+}
+
+impl StatusEffect {
+    fn color(&self) -> Color {
+        match self {
+            StatusEffect::Shield => Color::srgb(0.4, 0.7, 1.),
+            StatusEffect::Slowed => Color::srgb(0.75, 0.95, 1.),
+        }
+    }
+}
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct StatusIcon(StatusEffect);
+
+/// Spawns and despawns icon children to match the active effects of every enemy, laid out
+/// in a centered row. Icons of dead enemies are despawned together with them
+fn sync_status_icons(
+    mut commands: Commands,
+    enemies: Query<
+        (Entity, Option<&Shield>, Option<&Slowed>, Option<&Children>),
+        With<Enemy>,
+    >,
+    mut icons: Query<(&StatusIcon, &mut Transform)>,
+) {
+    for (entity, shield, slowed, children) in &enemies {
+        let mut active = vec![];
+        if Shield::blocks(shield) {
+            active.push(StatusEffect::Shield);
+        }
+        if slowed.is_some() {
+            active.push(StatusEffect::Slowed);
+        }
+
+        let mut shown = vec![];
+        for child in children.into_iter().flatten() {
+            let Ok((icon, _)) = icons.get(*child) else {
+                continue;
+            };
+            match active.contains(&icon.0) {
+                true => shown.push(icon.0),
+                false => commands.entity(*child).despawn(),
+            }
+        }
+
+        let position = |effect: &StatusEffect| {
+            let index = active.iter().position(|e| e == effect).unwrap_or_default();
+            let x = (index as f32 - (active.len() as f32 - 1.) / 2.) * ICON_SPACING;
+            Transform::from_xyz(x, ICON_HEIGHT, 1.)
+        };
+
+        for child in children.into_iter().flatten() {
+            if let Ok((icon, mut transform)) = icons.get_mut(*child) {
+                // Despawned icons are only gone once the commands are applied
+                if active.contains(&icon.0) {
+                    transform.set_if_neq(position(&icon.0));
+                }
+            }
+        }
+        for effect in active.iter().filter(|effect| !shown.contains(effect)) {
+            commands.spawn((
+                Name::new(format!("Status icon: {effect:?}")),
+                StatusIcon(*effect),
+                Sprite::from_color(effect.color(), Vec2::splat(ICON_SIZE)),
+                position(effect),
+                ChildOf(entity),
+            ));
+        }
+    }
+}
@@ -1,9 +1,18 @@
-use bevy::{prelude::*, time::Stopwatch};
+use bevy::{
+    ecs::system::SystemParam, input::common_conditions::input_just_pressed, prelude::*,
+    time::Stopwatch,
+};
+use serde::{Deserialize, Serialize};
 use wave::WavePlugin;
 
-use crate::app_state::{AppState, GameState};
+use crate::{
+    app_state::{AppState, GameState},
+    map::Level,
+};
 
-pub use wave::{SpawnerInfo, WaveInfo, WaveStart, insert_wave_info};
+pub use wave::{
+    SavedWaves, SpawnerCount, WaveInfo, WaveStart, Waves, WavesPath, insert_wave_info,
+};
 
 mod wave;
 
@@ -12,11 +21,25 @@ pub struct GameLoopPlugin;
 impl Plugin for GameLoopPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<GameStatistics>()
+            .register_type::<GameSpeed>()
+            .register_type::<Difficulty>()
+            .register_type::<Sandbox>()
+            .init_resource::<Difficulty>()
+            .init_resource::<Sandbox>()
             .add_plugins(WavePlugin)
             .add_systems(OnEnter(AppState::Game), insert_game_resources)
+            .add_systems(OnExit(AppState::Game), reset_game_speed)
             .add_systems(
                 Update,
-                advance_stat_time.run_if(in_state(GameState::Running)),
+                (
+                    advance_stat_time.run_if(in_state(GameState::Running)),
+                    cycle_game_speed
+                        .run_if(input_just_pressed(KeyCode::KeyF))
+                        .run_if(in_state(AppState::Game))
+                        .run_if(not(in_state(GameState::GameOver))),
+                    apply_game_speed.run_if(resource_exists_and_changed::<GameSpeed>),
+                )
+                    .chain(),
             );
     }
 }
@@ -28,15 +51,194 @@ pub struct GameStatistics {
     pub time: Stopwatch,
     pub money_earned: i32,
     pub money_spend: i32,
+    /// Towers placed by the player, upgrades don't count
+    pub towers_built: usize,
+    /// Enemies removed because they couldn't find any path to a goal
+    pub despawned_no_path: usize,
 }
 
-#[derive(Reflect, Resource, Deref, DerefMut)]
+#[derive(Reflect, Resource, Deref, DerefMut, Default)]
 #[reflect(Resource)]
 pub struct Currency(i32);
 
-fn insert_game_resources(mut commands: Commands) {
+/// Builder mode enabled with `--sandbox`. Towers, upgrades and repairs cost nothing, but
+/// what they would have cost still counts towards `GameStatistics::money_spend`
+#[derive(Reflect, Resource, Default, Clone, Copy)]
+#[reflect(Resource)]
+pub struct Sandbox(pub bool);
+
+impl Sandbox {
+    pub fn affords(self, currency: &Currency, cost: i32) -> bool {
+        self.0 || **currency >= cost
+    }
+
+    /// The part of `cost` actually taken from the currency
+    pub fn charge(self, cost: i32) -> i32 {
+        match self.0 {
+            true => 0,
+            false => cost,
+        }
+    }
+}
+
+/// The currency together with what goes along with every payment: the statistics tracking
+/// the money flow, and sandbox mode making towers, upgrades and repairs free
+#[derive(SystemParam)]
+pub struct Wallet<'w> {
+    pub currency: ResMut<'w, Currency>,
+    pub stats: ResMut<'w, GameStatistics>,
+    pub sandbox: Res<'w, Sandbox>,
+}
+
+impl Wallet<'_> {
+    pub fn affords(&self, cost: i32) -> bool {
+        self.sandbox.affords(&self.currency, cost)
+    }
+
+    /// Counts towards the spent money even in sandbox mode
+    pub fn spend(&mut self, cost: i32) {
+        **self.currency -= self.sandbox.charge(cost);
+        self.stats.money_spend += cost;
+    }
+
+    /// Gives back what `spend` took
+    pub fn refund(&mut self, cost: i32) {
+        **self.currency += self.sandbox.charge(cost);
+        self.stats.money_spend -= cost;
+    }
+
+    pub fn reward_kill(&mut self, reward: i32) {
+        **self.currency += reward;
+        self.stats.enemies_killed += 1;
+        self.stats.money_earned += reward;
+    }
+}
+
+/// How fast the game runs, cycled with F or by clicking the speed in the top bar. Applied
+/// to `Time<Virtual>`, so every timer and movement speeds up together. Pausing doesn't
+/// touch it, the game continues at the same speed once resumed
+#[derive(Reflect, Resource, Default, Clone, Copy, PartialEq, Debug)]
+#[reflect(Resource)]
+pub enum GameSpeed {
+    #[default]
+    Normal,
+    Double,
+    Quadruple,
+}
+
+impl GameSpeed {
+    pub fn next(self) -> Self {
+        match self {
+            GameSpeed::Normal => GameSpeed::Double,
+            GameSpeed::Double => GameSpeed::Quadruple,
+            GameSpeed::Quadruple => GameSpeed::Normal,
+        }
+    }
+
+    pub fn multiplier(self) -> f32 {
+        match self {
+            GameSpeed::Normal => 1.,
+            GameSpeed::Double => 2.,
+            GameSpeed::Quadruple => 4.,
+        }
+    }
+}
+
+/// Picked in the main menu and kept for the whole session. The multipliers are applied when
+/// an enemy spawns, enemies already on the field keep their stats
+#[derive(
+    Reflect, Resource, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Debug,
+)]
+#[reflect(Resource)]
+#[serde(rename_all = "snake_case")]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub const VARIANTS: &[&str] =
+        &["Difficulty: Easy", "Difficulty: Normal", "Difficulty: Hard"];
+
+    pub fn next(self) -> Self {
+        match self {
+            Difficulty::Easy => Difficulty::Normal,
+            Difficulty::Normal => Difficulty::Hard,
+            Difficulty::Hard => Difficulty::Easy,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Difficulty::Easy => Self::VARIANTS[0],
+            Difficulty::Normal => Self::VARIANTS[1],
+            Difficulty::Hard => Self::VARIANTS[2],
+        }
+    }
+
+    /// Multiplier for the max health of spawned enemies
+    pub fn enemy_health(self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.75,
+            Difficulty::Normal => 1.,
+            Difficulty::Hard => 1.5,
+        }
+    }
+
+    /// Multiplier for the damage spawned enemies deal to towers and goals
+    pub fn enemy_damage(self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.75,
+            Difficulty::Normal => 1.,
+            Difficulty::Hard => 1.25,
+        }
+    }
+
+    /// How much faster the timers of spawners run
+    pub fn spawn_rate(self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.8,
+            Difficulty::Normal => 1.,
+            Difficulty::Hard => 1.25,
+        }
+    }
+
+    fn starting_currency(self) -> i32 {
+        match self {
+            Difficulty::Easy => 120,
+            Difficulty::Normal => 80,
+            Difficulty::Hard => 60,
+        }
+    }
+}
+
+fn insert_game_resources(
+    mut commands: Commands,
+    difficulty: Res<Difficulty>,
+    level: Option<Res<Level>>,
+) {
+    let currency = level
+        .and_then(|level| level.starting_currency)
+        .unwrap_or(difficulty.starting_currency());
     commands.insert_resource(GameStatistics::default());
-    commands.insert_resource(Currency(80));
+    commands.insert_resource(Currency(currency));
+    commands.insert_resource(GameSpeed::default());
+}
+
+fn cycle_game_speed(mut speed: ResMut<GameSpeed>) {
+    *speed = speed.next();
+}
+
+fn apply_game_speed(speed: Res<GameSpeed>, mut time: ResMut<Time<Virtual>>) {
+    time.set_relative_speed(speed.multiplier());
+}
+
+/// Menus always run at normal speed
+fn reset_game_speed(mut commands: Commands, mut time: ResMut<Time<Virtual>>) {
+    commands.remove_resource::<GameSpeed>();
+    time.set_relative_speed(1.);
 }
 
 fn advance_stat_time(time: Res<Time>, mut stats: ResMut<GameStatistics>) {
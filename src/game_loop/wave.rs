@@ -1,10 +1,11 @@
-use std::time::Duration;
+use std::{path::PathBuf, time::Duration};
 
-use bevy::{platform::collections::HashMap, prelude::*};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize, de::value::StrDeserializer};
 
 use crate::{
     app_state::{AppState, GameState, WaveState},
-    enemy::{Enemy, EnemyType},
+    enemy::{Enemy, EnemyType, SpawnMode},
 };
 
 pub struct WavePlugin;
@@ -12,6 +13,7 @@ pub struct WavePlugin;
 impl Plugin for WavePlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<WaveStart>()
+            .add_systems(Startup, load_waves)
             .add_systems(OnEnter(AppState::Game), insert_wave_info)
             .add_systems(
                 Update,
@@ -30,12 +32,11 @@ type Wave = usize;
 pub struct WaveStart {
     #[deref]
     current: Wave,
-    pub new_spawners: Vec<SpawnerInfo>,
+    pub new_spawners: usize,
 }
 
 #[derive(Resource, Default)]
 pub struct WaveInfo {
-    spawners: HashMap<Wave, Vec<SpawnerInfo>>,
     current: Wave,
     pub last: Wave,
     pub margin: Timer,
@@ -45,36 +46,316 @@ pub struct WaveInfo {
     pub done_this_wave: usize,
 }
 
-#[derive(Clone, Copy)]
-pub struct SpawnerInfo {
-    pub interval: fn(Wave) -> f32,
-    pub enemies: fn(Wave) -> Vec<EnemyType>,
+impl WaveInfo {
+    /// Waves fully beaten, the one currently being fought doesn't count yet
+    pub fn survived(&self, state: WaveState) -> Wave {
+        match state {
+            WaveState::Ongoing => self.current.saturating_sub(1),
+            WaveState::Starting | WaveState::AllFinished => self.current,
+        }
+    }
+
+    /// Stops waiting for a spawner that couldn't be placed on the grid
+    pub fn spawner_failed(&mut self) {
+        self.current_spawners -= 1;
+    }
+
+    pub fn save(&self, state: WaveState) -> SavedWaves {
+        SavedWaves {
+            current: self.current,
+            current_spawners: self.current_spawners,
+            done_this_wave: self.done_this_wave,
+            ongoing: state == WaveState::Ongoing,
+        }
+    }
+
+    /// Returns the wave state to continue in. A wave that was about to start gets the full
+    /// margin again.
+    pub fn restore(&mut self, saved: SavedWaves) -> WaveState {
+        self.current = saved.current;
+        self.current_spawners = saved.current_spawners;
+        self.done_this_wave = saved.done_this_wave;
+        self.margin.reset();
+        match saved.ongoing {
+            true => WaveState::Ongoing,
+            false => WaveState::Starting,
+        }
+    }
+}
+
+/// Progress through the waves as stored in a save file
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SavedWaves {
+    current: Wave,
+    current_spawners: usize,
+    done_this_wave: usize,
+    ongoing: bool,
 }
 
-impl Default for SpawnerInfo {
-    fn default() -> Self {
+/// Enemies released during a wave by every spawner, or only by the listed ones
+#[derive(Clone, Debug)]
+pub struct SpawnGroup {
+    pub enemy: EnemyType,
+    pub count: usize,
+    /// Seconds a spawner waits before releasing each enemy of this group
+    pub delay: f32,
+    /// Indices of the spawners releasing this group, in the order they appeared on the grid.
+    /// None for all of them
+    pub spawners: Option<Vec<usize>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct WaveDefinition {
+    /// Spawners placed on the grid when this wave starts
+    pub new_spawners: usize,
+    pub groups: Vec<SpawnGroup>,
+    /// How often every spawner runs through its groups before going dormant for the rest
+    /// of the wave, so a short pattern like three Skeletons and an Orc can make up a whole
+    /// wave
+    pub repeat: usize,
+    /// Multiplier for the max health of every enemy spawned during this wave
+    pub health: f32,
+}
+
+/// Content of every wave, loaded at startup from the file passed via `--waves <path>`, or
+/// built in if there is none
+#[derive(Resource, Clone, Debug)]
+pub struct Waves {
+    pub spawn_mode: SpawnMode,
+    waves: Vec<WaveDefinition>,
+}
+
+impl Waves {
+    fn get(&self, wave: Wave) -> Option<&WaveDefinition> {
+        self.waves.get(wave.checked_sub(1)?)
+    }
+
+    /// Enemies the spawner with the given index releases during `wave`, each with the delay
+    /// before it is released, in spawn order
+    pub fn enemies_for(&self, wave: Wave, spawner: usize) -> Vec<(EnemyType, f32)> {
+        let Some(wave) = self.get(wave) else {
+            return vec![];
+        };
+        let schedule: Vec<(EnemyType, f32)> = wave
+            .groups
+            .iter()
+            .filter(|group| {
+                group
+                    .spawners
+                    .as_ref()
+                    .is_none_or(|spawners| spawners.contains(&spawner))
+            })
+            .flat_map(|group| {
+                std::iter::repeat_n((group.enemy, group.delay), group.count)
+            })
+            .collect();
+        schedule.repeat(wave.repeat)
+    }
+
+    /// Health multiplier of the enemies spawned during `wave`
+    pub fn health_for(&self, wave: Wave) -> f32 {
+        self.get(wave).map_or(1., |wave| wave.health)
+    }
+
+    pub fn built_in(spawner_count: Option<usize>) -> Self {
+        let last = 10;
+        let mut waves: Vec<WaveDefinition> = (1..=last)
+            .map(|wave| {
+                let group = |enemy, count| SpawnGroup {
+                    enemy,
+                    count,
+                    delay: 0.5,
+                    spawners: None,
+                };
+                WaveDefinition {
+                    new_spawners: 0,
+                    repeat: 1,
+                    groups: vec![
+                        // Bats fly over walls from the sixth wave on, so mazes need some
+                        // shooting towers as well
+                        group(EnemyType::Bat, wave / 6),
+                        group(EnemyType::Blinker, wave / 5),
+                        group(EnemyType::Shielded, wave / 4),
+                        // Ghosts join from the third wave on, requiring a Mage to deal with them
+                        group(EnemyType::Ghost, wave / 3),
+                        group(EnemyType::Orc, wave / 2),
+                        group(EnemyType::Skeleton, wave + 1),
+                        // A single boss closes every fifth wave
+                        SpawnGroup {
+                            spawners: Some(vec![0]),
+                            ..group(EnemyType::SkeletonKing, (wave % 5 == 0) as usize)
+                        },
+                    ],
+                    // On top of the growing enemy count, so later waves can't be held
+                    // off by just adding more of the same towers
+                    health: 1. + (wave - 1) as f32 * BUILT_IN_HEALTH_GROWTH,
+                }
+            })
+            .collect();
+
+        for i in 0..spawner_count.unwrap_or(SPAWNER_WAVES.len()) {
+            // Spawners beyond the default schedule are added one per wave
+            let wave = SPAWNER_WAVES
+                .get(i)
+                .copied()
+                .unwrap_or((i - SPAWNER_WAVES.len()) % last + 1);
+            waves[wave - 1].new_spawners += 1;
+        }
+
         Self {
-            interval: |_| 1.,
-            enemies: |wave| vec![EnemyType::Skeleton; wave],
+            spawn_mode: SpawnMode::Burst {
+                count: 5,
+                delay: 0.1,
+                every: 6,
+            },
+            waves,
         }
     }
+
+    /// Groups with an unknown enemy type are skipped, anything else that is wrong with the
+    /// file fails the whole parse
+    fn parse(source: &str) -> Result<Self, String> {
+        let file: WaveFile = ron::from_str(source).map_err(|e| e.to_string())?;
+        if file.waves.is_empty() {
+            return Err("no waves defined".to_string());
+        }
+
+        let waves = file
+            .waves
+            .into_iter()
+            .enumerate()
+            .map(|(i, wave)| WaveDefinition {
+                new_spawners: wave.new_spawners,
+                repeat: wave.repeat,
+                // Enemies with zero health would die before they could be hit
+                health: wave.health.max(0.1),
+                groups: wave
+                    .groups
+                    .into_iter()
+                    .filter_map(|group| {
+                        let enemy = EnemyType::deserialize(StrDeserializer::<
+                            serde::de::value::Error,
+                        >::new(
+                            &group.enemy
+                        ));
+                        match enemy {
+                            Ok(enemy) => Some(SpawnGroup {
+                                enemy,
+                                count: group.count,
+                                delay: group.delay.max(0.),
+                                spawners: group.spawners,
+                            }),
+                            Err(e) => {
+                                warn!("Skipping spawn group in wave {}: {e}", i + 1);
+                                None
+                            }
+                        }
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        Ok(Self {
+            spawn_mode: file.spawn_mode,
+            waves,
+        })
+    }
 }
 
-pub fn insert_wave_info(mut commands: Commands) {
-    let spawner = SpawnerInfo {
-        interval: |_| 0.5,
-        enemies: |wave| vec![EnemyType::Skeleton; wave + 1],
+#[derive(Deserialize)]
+struct WaveFile {
+    #[serde(default)]
+    spawn_mode: SpawnMode,
+    waves: Vec<WaveFileEntry>,
+}
+
+#[derive(Deserialize)]
+struct WaveFileEntry {
+    #[serde(default)]
+    new_spawners: usize,
+    #[serde(default)]
+    groups: Vec<SpawnGroupFileEntry>,
+    #[serde(default = "default_repeat")]
+    repeat: usize,
+    #[serde(default = "default_health")]
+    health: f32,
+}
+
+#[derive(Deserialize)]
+struct SpawnGroupFileEntry {
+    /// Kept as a string, so unknown enemy types only invalidate their own group
+    enemy: String,
+    count: usize,
+    #[serde(default = "default_spawn_delay")]
+    delay: f32,
+    #[serde(default)]
+    spawners: Option<Vec<usize>>,
+}
+
+fn default_spawn_delay() -> f32 {
+    0.5
+}
+
+fn default_repeat() -> usize {
+    1
+}
+
+fn default_health() -> f32 {
+    1.
+}
+
+/// Total amount of spawners over the whole game, set via `--spawners N`. Only applies to the
+/// built in waves
+#[derive(Resource, Clone, Copy)]
+pub struct SpawnerCount(pub usize);
+
+/// Wave file passed via `--waves <path>`
+#[derive(Resource, Clone)]
+pub struct WavesPath(pub PathBuf);
+
+/// Added to the health multiplier of the built in waves with every wave
+const BUILT_IN_HEALTH_GROWTH: f32 = 0.05;
+
+/// The waves in which new spawners appear by default, one entry per spawner
+const SPAWNER_WAVES: [Wave; 9] = [1, 1, 2, 5, 5, 6, 7, 8, 8];
+
+fn load_waves(
+    mut commands: Commands,
+    path: Option<Res<WavesPath>>,
+    count: Option<Res<SpawnerCount>>,
+) {
+    let built_in = || Waves::built_in(count.as_ref().map(|c| c.0));
+    let waves = match path {
+        None => built_in(),
+        Some(path) => {
+            let waves = std::fs::read_to_string(&path.0)
+                .map_err(|e| e.to_string())
+                .and_then(|source| Waves::parse(&source));
+            match waves {
+                Ok(waves) => {
+                    info!(
+                        "Loaded {} waves from {}",
+                        waves.waves.len(),
+                        path.0.display()
+                    );
+                    waves
+                }
+                Err(e) => {
+                    warn!(
+                        "Failed to load waves from {}, using the built in ones: {e}",
+                        path.0.display()
+                    );
+                    built_in()
+                }
+            }
+        }
     };
+    commands.insert_resource(waves);
+}
+
+pub fn insert_wave_info(mut commands: Commands, waves: Res<Waves>) {
     commands.insert_resource(WaveInfo {
-        spawners: HashMap::from([
-            (1, vec![spawner; 2]),
-            (2, vec![spawner]),
-            (5, vec![spawner; 2]),
-            (6, vec![spawner]),
-            (7, vec![spawner]),
-            (8, vec![spawner; 2]),
-        ]),
-        last: 10,
+        last: waves.waves.len(),
         margin: Timer::new(Duration::from_secs(5), TimerMode::Once),
         ..Default::default()
     });
@@ -82,6 +363,7 @@ pub fn insert_wave_info(mut commands: Commands) {
 
 fn count_wave_margin(
     mut wave: ResMut<WaveInfo>,
+    waves: Res<Waves>,
     time: Res<Time>,
     mut next_state: ResMut<NextState<WaveState>>,
     mut events: EventWriter<WaveStart>,
@@ -93,12 +375,11 @@ fn count_wave_margin(
         wave.margin.reset();
         wave.done_this_wave = 0;
 
-        let spawners = wave
-            .spawners
-            .get(&wave.current)
-            .cloned()
+        let spawners = waves
+            .get(wave.current)
+            .map(|wave| wave.new_spawners)
             .unwrap_or_default();
-        wave.current_spawners += spawners.len();
+        wave.current_spawners += spawners;
         events.write(WaveStart {
             current: wave.current,
             new_spawners: spawners,
@@ -0,0 +1,50 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::GridPos;
+
+/// Size of the board in tiles. Read when a game starts, changing it during a game is not
+/// supported.
+#[derive(
+    Reflect, Resource, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq,
+)]
+#[reflect(Resource)]
+pub struct GridConfig {
+    pub rows: isize,
+    pub columns: isize,
+}
+
+impl GridConfig {
+    pub const SMALL: Self = Self {
+        rows: 20,
+        columns: 35,
+    };
+    pub const MEDIUM: Self = Self {
+        rows: 30,
+        columns: 52,
+    };
+    pub const LARGE: Self = Self {
+        rows: 40,
+        columns: 70,
+    };
+
+    /// Parses a preset name, as passed via `--map-size`
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "small" => Some(Self::SMALL),
+            "medium" => Some(Self::MEDIUM),
+            "large" => Some(Self::LARGE),
+            _ => None,
+        }
+    }
+
+    pub fn contains(&self, pos: &GridPos) -> bool {
+        (0..self.rows).contains(&pos.row) && (0..self.columns).contains(&pos.col)
+    }
+}
+
+impl Default for GridConfig {
+    fn default() -> Self {
+        Self::LARGE
+    }
+}
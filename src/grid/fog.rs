@@ -0,0 +1,145 @@
+use bevy::{
+    asset::RenderAssetUsages,
+    image::ImageSampler,
+    platform::collections::HashSet,
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+
+use crate::{Settings, app_state::AppState, enemy::Enemy};
+
+use super::{GridConfig, GridPos, TILE_SIZE, world_to_grid_coords};
+
+const FOG_COLOR: Color = Color::srgba(0., 0., 0., 0.75);
+
+pub struct FogPlugin;
+
+impl Plugin for FogPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Vision>()
+            .register_type::<VisibleTiles>()
+            .add_systems(
+                OnEnter(AppState::Game),
+                spawn_fog.run_if(|settings: Res<Settings>| settings.fog),
+            )
+            .add_systems(OnExit(AppState::Game), remove_fog)
+            .add_systems(
+                Update,
+                (update_visible_tiles, hide_enemies_in_fog)
+                    .chain()
+                    .run_if(resource_exists::<VisibleTiles>),
+            );
+    }
+}
+
+/// Uncovers all tiles within this radius (in tiles) around the origin tile of the entity.
+/// Only has an effect when running with `--fog`
+#[derive(Component, Reflect, Clone, Copy)]
+#[reflect(Component)]
+pub struct Vision(pub f32);
+
+/// Tiles currently seen by any [`Vision`]. Fog is purely visual, so this only gates tower
+/// targeting if `--fog-targeting` is set as well
+#[derive(Resource, Reflect, Default, Deref)]
+#[reflect(Resource)]
+pub struct VisibleTiles(HashSet<GridPos>);
+
+#[derive(Component)]
+struct FogOverlay;
+
+fn spawn_fog(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    config: Res<GridConfig>,
+) {
+    // One pixel per tile, stretched over the whole grid
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: config.columns as u32,
+            height: config.rows as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &FOG_COLOR.to_srgba().to_u8_array(),
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    image.sampler = ImageSampler::nearest();
+
+    commands.spawn((
+        Name::new("Fog"),
+        FogOverlay,
+        Sprite {
+            image: images.add(image),
+            custom_size: Some(Vec2::new(
+                config.columns as f32 * TILE_SIZE,
+                config.rows as f32 * TILE_SIZE,
+            )),
+            ..default()
+        },
+        // Above towers and enemies, but below projectiles
+        Transform::from_xyz(-TILE_SIZE * 0.5, -TILE_SIZE * 0.5, 2.5),
+    ));
+    commands.insert_resource(VisibleTiles::default());
+}
+
+fn remove_fog(mut commands: Commands) {
+    commands.remove_resource::<VisibleTiles>();
+}
+
+fn update_visible_tiles(
+    mut visible: ResMut<VisibleTiles>,
+    sources: Query<(&Vision, &Transform)>,
+    new_sources: Query<(), Added<Vision>>,
+    mut removed: RemovedComponents<Vision>,
+    overlay: Single<&Sprite, With<FogOverlay>>,
+    mut images: ResMut<Assets<Image>>,
+    config: Res<GridConfig>,
+) {
+    if new_sources.is_empty() && removed.read().count() == 0 {
+        return;
+    }
+
+    let mut tiles = HashSet::new();
+    for (vision, transform) in &sources {
+        let Some(center) =
+            world_to_grid_coords(transform.translation.truncate(), &config)
+        else {
+            continue;
+        };
+        tiles.extend(center.tiles_in_radius(vision.0, &config));
+    }
+
+    let Some(image) = images.get_mut(&overlay.image) else {
+        return;
+    };
+    for row in 0..config.rows {
+        for col in 0..config.columns {
+            let color = match tiles.contains(&GridPos::new(row, col)) {
+                true => Color::NONE,
+                false => FOG_COLOR,
+            };
+            // Image rows start at the top, grid rows at the bottom
+            if let Err(e) =
+                image.set_color_at(col as u32, (config.rows - 1 - row) as u32, color)
+            {
+                error!("Failed to update fog: {e}");
+                return;
+            }
+        }
+    }
+    visible.0 = tiles;
+}
+
+/// Enemies in the fog keep walking and fighting, they are just not drawn
+fn hide_enemies_in_fog(
+    visible: Res<VisibleTiles>,
+    mut enemies: Query<(&Enemy, &mut Visibility)>,
+) {
+    for (enemy, mut visibility) in &mut enemies {
+        visibility.set_if_neq(match visible.contains(&enemy.current) {
+            true => Visibility::Inherited,
+            false => Visibility::Hidden,
+        });
+    }
+}
@@ -5,10 +5,12 @@ use std::{
 
 use bevy::{platform::collections::HashMap, prelude::*};
 use fastrand::Rng;
+use serde::{Deserialize, Serialize};
 
-use super::{COLUMNS, ROWS};
+use super::GridConfig;
 
-#[derive(Reflect, PartialEq, Eq, Hash, Clone, Copy, Default)]
+#[derive(Reflect, Serialize, Deserialize, PartialEq, Eq, Hash, Clone, Copy, Default)]
+#[serde(deny_unknown_fields)]
 pub struct GridPos {
     pub row: isize,
     pub col: isize,
@@ -92,36 +94,24 @@ impl GridPos {
         GridPos { row, col }
     }
 
-    pub fn random(rng: &mut Rng) -> Self {
+    pub fn random(rng: &mut Rng, config: &GridConfig) -> Self {
         Self {
-            row: rng.isize(0..(ROWS - 1)),
-            col: rng.isize(0..(COLUMNS - 1)),
+            row: rng.isize(0..(config.rows - 1)),
+            col: rng.isize(0..(config.columns - 1)),
         }
     }
 
-    pub fn inside_grid_bounds(&self) -> bool {
-        (0..ROWS).contains(&self.row) && (0..COLUMNS).contains(&self.col)
+    pub fn inside_grid_bounds(&self, config: &GridConfig) -> bool {
+        config.contains(self)
     }
 
     pub fn distance_to(&self, other: &GridPos) -> usize {
         other.row.abs_diff(self.row) + other.col.abs_diff(self.col)
     }
 
-    pub fn closest<'a>(&self, goals: &'a HashMap<GridPos, Entity>) -> &'a GridPos {
-        goals
-            .keys()
-            .map(|pos| (pos, self.distance_to(pos)))
-            .min_by_key(|x| x.1)
-            .expect("No goals exist anymore!")
-            .0
-    }
-
-    pub fn distance_to_closest(&self, goals: &HashMap<GridPos, Entity>) -> usize {
-        goals
-            .keys()
-            .map(|pos| self.distance_to(pos))
-            .min()
-            .expect("No goals exist anymore!")
+    /// None if there are no goals at all
+    pub fn distance_to_closest(&self, goals: &HashMap<GridPos, Entity>) -> Option<usize> {
+        goals.keys().map(|pos| self.distance_to(pos)).min()
     }
 
     /// * `towers` - Every tower position mapped to its Entity and travel cost
@@ -130,12 +120,13 @@ impl GridPos {
         towers: &'a HashMap<GridPos, (Entity, usize)>,
         default_travel_cost: usize,
         death_count: &HashMap<GridPos, usize>,
+        config: &GridConfig,
     ) -> Vec<(GridPos, Option<&'a Entity>, usize)> {
         let mut neighbors = vec![];
 
         let mut push_maybe = |row, col| {
             let tile = GridPos::new(row, col);
-            if tile.inside_grid_bounds() {
+            if tile.inside_grid_bounds(config) {
                 match towers.get(&tile) {
                     Some((entity, travel_cost)) => {
                         neighbors.push((tile, Some(entity), *travel_cost))
@@ -1,16 +1,23 @@
-use std::time::Duration;
+use std::{collections::VecDeque, time::Duration};
 
 use bevy::platform::collections::{HashMap, HashSet};
 use bevy::prelude::*;
 
+pub use config::GridConfig;
+use fog::FogPlugin;
+pub use fog::{VisibleTiles, Vision};
 pub use grid_pos::GridPos;
+pub use pathfinding::{FlowField, flight_path};
+pub use queries::world_to_tiles;
 
-use crate::app_state::AppState;
+use crate::app_state::{AppState, GameState};
 
+mod config;
+mod fog;
 mod grid_pos;
+mod pathfinding;
+mod queries;
 
-pub const ROWS: isize = 40;
-pub const COLUMNS: isize = 70;
 pub const TILE_SIZE: f32 = 25.;
 
 const LINE_WIDTH: f32 = 1.5;
@@ -22,10 +29,14 @@ pub struct GridPlugin;
 impl Plugin for GridPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<Grid>();
+        app.register_type::<GridConfig>();
+        // Might already be inserted from the command line
+        app.init_resource::<GridConfig>();
+        app.add_plugins(FogPlugin);
         app.add_systems(OnEnter(AppState::Game), spawn_grid);
         app.add_systems(
             Update,
-            decrease_death_count.run_if(in_state(AppState::Game)),
+            decrease_death_count.run_if(in_state(GameState::Running)),
         );
         app.add_systems(OnExit(AppState::Game), exit);
     }
@@ -41,13 +52,19 @@ pub struct Grid {
     pub enemy_spawners: HashMap<GridPos, Entity>,
     pub enemy_goals: HashMap<GridPos, Entity>,
     pub unbuildable: HashSet<GridPos>,
+    /// Rock and water loaded with the map, nothing can be built on it and only flying
+    /// enemies can cross it
+    pub terrain: HashSet<GridPos>,
     pub death_count: HashMap<GridPos, usize>,
     death_count_reset_timer: Timer,
+    /// Copy of the [`GridConfig`] the grid was created with, for the bounds checks below
+    config: GridConfig,
 }
 
 impl Grid {
-    pub fn new() -> Self {
+    pub fn new(config: GridConfig) -> Self {
         Self {
+            config,
             death_count_reset_timer: Timer::new(
                 Duration::from_secs(6),
                 TimerMode::Repeating,
@@ -56,11 +73,129 @@ impl Grid {
         }
     }
 
+    pub fn config(&self) -> GridConfig {
+        self.config
+    }
+
+    /// The lowest goal tile, None once there are no goals. Enemies walk to whichever goal
+    /// is closest, this is for everything that only needs a goal to exist
+    pub fn primary_goal(&self) -> Option<GridPos> {
+        self.enemy_goals
+            .keys()
+            .min_by_key(|tile| (tile.row, tile.col))
+            .copied()
+    }
+
     pub fn is_free(&self, position: &GridPos) -> bool {
         !self.towers.contains_key(position)
             && !self.enemy_spawners.contains_key(position)
             && !self.enemy_goals.contains_key(position)
             && !self.unbuildable.contains(position)
+            && !self.terrain.contains(position)
+    }
+
+    /// Returns false if any enemy spawner would be cut off from every goal, assuming towers
+    /// can't be walked through and the `blocked` tiles are occupied as well
+    pub fn spawners_reach_goal(&self, blocked: &[GridPos]) -> bool {
+        let reached = self.reachable_from_goals(blocked);
+        self.enemy_spawners.keys().all(|pos| reached.contains(pos))
+    }
+
+    /// Every tile connected to a goal without walking through towers or `blocked` tiles
+    pub fn reachable_from_goals(&self, blocked: &[GridPos]) -> HashSet<GridPos> {
+        self.flood_from_goals(|pos| {
+            !self.towers.contains_key(pos) && !blocked.contains(pos)
+        })
+    }
+
+    /// Every tile connected to a goal if all towers were broken through, only the terrain
+    /// can cut tiles off for good
+    pub fn reachable_through_towers(&self) -> HashSet<GridPos> {
+        self.flood_from_goals(|_| true)
+    }
+
+    /// Flood fill starting at the goals, so a single pass covers every tile
+    fn flood_from_goals(&self, passable: impl Fn(&GridPos) -> bool) -> HashSet<GridPos> {
+        self.flood_from(self.enemy_goals.keys().copied(), passable)
+    }
+
+    /// Every tile connected to one of `starts`. Terrain and tiles outside the grid are
+    /// never entered, other tiles only if they are `passable`
+    fn flood_from(
+        &self,
+        starts: impl IntoIterator<Item = GridPos>,
+        passable: impl Fn(&GridPos) -> bool,
+    ) -> HashSet<GridPos> {
+        let mut reached: HashSet<GridPos> = starts.into_iter().collect();
+        let mut queue: VecDeque<GridPos> = reached.iter().copied().collect();
+
+        while let Some(tile) = queue.pop_front() {
+            for neighbor in [tile + [1, 0], tile + [-1, 0], tile + [0, 1], tile + [0, -1]]
+            {
+                if neighbor.inside_grid_bounds(&self.config)
+                    && !self.terrain.contains(&neighbor)
+                    && passable(&neighbor)
+                    && reached.insert(neighbor)
+                {
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        reached
+    }
+
+    /// Returns the closest tile to `from` (including itself) an enemy can stand on
+    pub fn nearest_walkable(&self, from: GridPos) -> Option<GridPos> {
+        let walkable = |pos: &GridPos| {
+            !self.towers.contains_key(pos)
+                && !self.enemy_goals.contains_key(pos)
+                && !self.terrain.contains(pos)
+        };
+        let mut visited = HashSet::from([from]);
+        let mut queue = VecDeque::from([from]);
+
+        while let Some(tile) = queue.pop_front() {
+            if walkable(&tile) {
+                return Some(tile);
+            }
+            for neighbor in [tile + [1, 0], tile + [-1, 0], tile + [0, 1], tile + [0, -1]]
+            {
+                if neighbor.inside_grid_bounds(&self.config) && visited.insert(neighbor) {
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        None
+    }
+
+    /// Returns up to `count` distinct tiles enemies can stand on, connected to `from` and
+    /// ordered by their distance to it
+    pub fn walkable_near(&self, from: GridPos, count: usize) -> Vec<GridPos> {
+        let walkable = |pos: &GridPos| {
+            !self.towers.contains_key(pos)
+                && !self.enemy_goals.contains_key(pos)
+                && !self.terrain.contains(pos)
+        };
+        let mut tiles = vec![];
+        let mut visited = HashSet::from([from]);
+        let mut queue = VecDeque::from([from]);
+
+        while let Some(tile) = queue.pop_front() {
+            if tiles.len() == count {
+                break;
+            }
+            tiles.push(tile);
+            for neighbor in [tile + [1, 0], tile + [-1, 0], tile + [0, 1], tile + [0, -1]]
+            {
+                if neighbor.inside_grid_bounds(&self.config)
+                    && walkable(&neighbor)
+                    && visited.insert(neighbor)
+                {
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        tiles
     }
 
     fn decrease_death_count(&mut self) {
@@ -71,18 +206,18 @@ impl Grid {
     }
 }
 
-pub fn spawn_grid(mut commands: Commands) {
-    commands.insert_resource(Grid::new());
+pub fn spawn_grid(mut commands: Commands, config: Res<GridConfig>) {
+    commands.insert_resource(Grid::new(*config));
 
     let position = |total: f32, current| {
         (-(total * 0.5 * TILE_SIZE) + current * TILE_SIZE) - TILE_SIZE * 0.5
     };
 
-    let total_size_x = ROWS as f32 * TILE_SIZE;
-    let total_size_y = COLUMNS as f32 * TILE_SIZE;
+    let total_size_x = config.rows as f32 * TILE_SIZE;
+    let total_size_y = config.columns as f32 * TILE_SIZE;
 
-    for column in 0..=COLUMNS {
-        let x = position(COLUMNS as f32, column as f32);
+    for column in 0..=config.columns {
+        let x = position(config.columns as f32, column as f32);
         commands.spawn((
             Name::new(format!("Grid column border: {column}")),
             Sprite::from_color(
@@ -103,8 +238,8 @@ pub fn spawn_grid(mut commands: Commands) {
         ));
     }
 
-    for row in 0..=ROWS {
-        let y = position(ROWS as f32, row as f32);
+    for row in 0..=config.rows {
+        let y = position(config.rows as f32, row as f32);
         commands.spawn((
             Name::new(format!("Grid row border: {row}")),
             Sprite::from_color(
@@ -137,25 +272,200 @@ fn exit(mut commands: Commands) {
     commands.remove_resource::<Grid>();
 }
 
-pub fn world_to_grid_coords(pos: Vec2) -> Option<GridPos> {
-    let max_pos_x = TILE_SIZE * COLUMNS as f32 * 0.5;
-    let max_pos_y = TILE_SIZE * ROWS as f32 * 0.5;
-
-    if (pos.x + TILE_SIZE * 0.5).abs() > max_pos_x
-        || (pos.y + TILE_SIZE * 0.5).abs() > max_pos_y
-    {
-        return None;
-    }
+/// Maps a world position to the tile covering it. A tile covers the square of size
+/// `TILE_SIZE` centered at [`grid_to_world_coords`], positions on the border between two
+/// tiles always belong to the tile to the right / above.
+pub fn world_to_grid_coords(pos: Vec2, config: &GridConfig) -> Option<GridPos> {
+    // Bottom left corner of the grid
+    let origin = grid_to_world_coords(GridPos::new(0, 0), config) - TILE_SIZE * 0.5;
 
-    Some(GridPos {
-        row: ((pos.y + max_pos_y) / TILE_SIZE).round() as isize,
-        col: ((pos.x + max_pos_x) / TILE_SIZE).round() as isize,
-    })
+    let grid_pos = GridPos {
+        row: ((pos.y - origin.y) / TILE_SIZE).floor() as isize,
+        col: ((pos.x - origin.x) / TILE_SIZE).floor() as isize,
+    };
+    grid_pos.inside_grid_bounds(config).then_some(grid_pos)
 }
 
-pub fn grid_to_world_coords(pos: GridPos) -> Vec2 {
+/// Returns the center of the tile in world coordinates. Sprites anchored at the bottom left,
+/// like towers, have to be placed `TILE_SIZE * 0.5` below and left of it. The board is
+/// centered around the origin regardless of its size, so the camera doesn't have to know it.
+pub fn grid_to_world_coords(pos: GridPos, config: &GridConfig) -> Vec2 {
     Vec2 {
-        x: -(COLUMNS as f32 * 0.5 * TILE_SIZE) + pos.col as f32 * TILE_SIZE,
-        y: -(ROWS as f32 * 0.5 * TILE_SIZE) + pos.row as f32 * TILE_SIZE,
+        x: -(config.columns as f32 * 0.5 * TILE_SIZE) + pos.col as f32 * TILE_SIZE,
+        y: -(config.rows as f32 * 0.5 * TILE_SIZE) + pos.row as f32 * TILE_SIZE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Orientation,
+        tower::{Tower, TowerType},
+    };
+
+    use super::*;
+
+    /// 5x5 grid with a spawner on the left, a goal on the right and a wall of towers in the
+    /// middle column, open only at `gap`
+    fn walled_grid(gap: GridPos) -> Grid {
+        let mut grid = Grid::new(GridConfig {
+            rows: 5,
+            columns: 5,
+        });
+        grid.enemy_spawners
+            .insert(GridPos::new(2, 0), Entity::PLACEHOLDER);
+        grid.enemy_goals
+            .insert(GridPos::new(2, 4), Entity::PLACEHOLDER);
+        for row in (0..5).filter(|row| *row != gap.row) {
+            grid.towers
+                .insert(GridPos::new(row, 2), Entity::PLACEHOLDER);
+        }
+        grid
+    }
+
+    #[test]
+    fn closing_the_last_gap_cuts_off_the_spawner() {
+        let gap = GridPos::new(4, 2);
+        let grid = walled_grid(gap);
+
+        assert!(grid.spawners_reach_goal(&[]));
+        assert!(!grid.spawners_reach_goal(&[gap]));
+        // Blocking any other tile keeps the gap open
+        assert!(grid.spawners_reach_goal(&[GridPos::new(0, 1)]));
+    }
+
+    #[test]
+    fn canon_overlapping_a_spawner_corner_is_not_free() {
+        let mut grid = Grid::new(GridConfig {
+            rows: 10,
+            columns: 10,
+        });
+        // Spawners and goals cover 2x2 tiles
+        for tile in [[2, 0], [3, 0], [2, 1], [3, 1]] {
+            grid.enemy_spawners
+                .insert(GridPos::new(2, 0) + tile, Entity::PLACEHOLDER);
+        }
+        for tile in [[0, 0], [1, 0], [0, 1], [1, 1]] {
+            grid.enemy_goals
+                .insert(GridPos::new(6, 8) + tile, Entity::PLACEHOLDER);
+        }
+        let canon = Tower::new(TowerType::Canon, Orientation::Up);
+        let footprint_free = |origin| {
+            canon
+                .footprint(origin)
+                .iter()
+                .all(|tile| grid.is_free(tile))
+        };
+
+        // Only the bottom left tile of the Canon covers the top right tile of the spawner
+        assert!(!footprint_free(GridPos::new(3, 1)));
+        assert!(footprint_free(GridPos::new(3, 2)));
+        // The same for the bottom left corner of the goal
+        assert!(!footprint_free(GridPos::new(4, 6)));
+        assert!(footprint_free(GridPos::new(3, 5)));
+    }
+
+    #[test]
+    fn tower_queries_skip_goals_and_spawners() {
+        let mut grid = Grid::new(GridConfig {
+            rows: 5,
+            columns: 5,
+        });
+        let (near, far) = (Entity::from_raw(1), Entity::from_raw(2));
+        let (goal, spawner) = (GridPos::new(2, 4), GridPos::new(2, 0));
+        grid.enemy_goals.insert(goal, Entity::from_raw(3));
+        grid.enemy_spawners.insert(spawner, Entity::from_raw(4));
+        // A two tile tower next to the goal and a single tile one in the corner
+        for tile in [GridPos::new(1, 3), GridPos::new(2, 3)] {
+            grid.towers.insert(tile, near);
+        }
+        grid.tower_origins.insert(near, GridPos::new(1, 3));
+        grid.towers.insert(GridPos::new(4, 0), far);
+        grid.tower_origins.insert(far, GridPos::new(4, 0));
+        // Neither of these ever counts as a tower
+        grid.towers.insert(goal, Entity::from_raw(3));
+        grid.towers.insert(spawner, Entity::from_raw(4));
+
+        // Listed once with its origin, even though only its other tile is in range
+        assert_eq!(
+            grid.towers_in_radius(goal, 1.),
+            [(GridPos::new(1, 3), near)]
+        );
+        assert_eq!(
+            grid.nearest_tower(GridPos::new(3, 0)),
+            Some((GridPos::new(4, 0), far))
+        );
+
+        assert!(!grid.is_path_blocked(GridPos::new(0, 0), GridPos::new(0, 4)));
+        assert!(grid.is_path_blocked(GridPos::new(4, 0), GridPos::new(0, 4)));
+        for row in 0..5 {
+            grid.towers.insert(GridPos::new(row, 1), far);
+        }
+        assert!(grid.is_path_blocked(GridPos::new(0, 0), GridPos::new(0, 4)));
+    }
+
+    #[test]
+    fn terrain_blocks_paths_like_towers() {
+        let mut grid = Grid::new(GridConfig {
+            rows: 5,
+            columns: 5,
+        });
+        let (from, to) = (GridPos::new(2, 0), GridPos::new(2, 4));
+        // Rock all across the middle column, without any tower
+        grid.terrain.extend((0..5).map(|row| GridPos::new(row, 2)));
+        assert!(grid.is_path_blocked(from, to));
+
+        grid.terrain.remove(&GridPos::new(4, 2));
+        assert!(!grid.is_path_blocked(from, to));
+    }
+
+    #[test]
+    fn tile_centers_map_back_to_their_tile() {
+        for config in [GridConfig::SMALL, GridConfig::MEDIUM, GridConfig::LARGE] {
+            for row in 0..config.rows {
+                for col in 0..config.columns {
+                    let pos = GridPos::new(row, col);
+                    let center = grid_to_world_coords(pos, &config);
+                    assert_eq!(world_to_grid_coords(center, &config), Some(pos));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn tile_borders_belong_to_the_right_and_upper_tile() {
+        let config = GridConfig::SMALL;
+        let half = TILE_SIZE * 0.5;
+        let pos = GridPos::new(4, 7);
+        let center = grid_to_world_coords(pos, &config);
+
+        assert_eq!(world_to_grid_coords(center - half, &config), Some(pos));
+        assert_eq!(
+            world_to_grid_coords(center + Vec2::splat(half - 0.01), &config),
+            Some(pos)
+        );
+        assert_eq!(
+            world_to_grid_coords(center + Vec2::new(half, 0.), &config),
+            Some(GridPos::new(4, 8))
+        );
+        assert_eq!(
+            world_to_grid_coords(center + Vec2::new(0., half), &config),
+            Some(GridPos::new(5, 7))
+        );
+
+        // The outer borders of the grid
+        let bottom_left = grid_to_world_coords(GridPos::new(0, 0), &config) - half;
+        assert_eq!(
+            world_to_grid_coords(bottom_left, &config),
+            Some(GridPos::new(0, 0))
+        );
+        assert_eq!(world_to_grid_coords(bottom_left - 0.01, &config), None);
+        let top_right = GridPos::new(config.rows - 1, config.columns - 1);
+        let top_right_corner = grid_to_world_coords(top_right, &config) + half;
+        assert_eq!(
+            world_to_grid_coords(top_right_corner - 0.01, &config),
+            Some(top_right)
+        );
+        assert_eq!(world_to_grid_coords(top_right_corner, &config), None);
     }
 }
@@ -0,0 +1,164 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use bevy::{
+    platform::collections::{HashMap, HashSet},
+    prelude::*,
+};
+
+use super::{GridConfig, GridPos};
+
+/// The cheapest next step towards any goal for every tile that can reach one. Built once
+/// and shared by every enemy with the same travel costs, instead of searching a path per
+/// enemy. Doesn't depend on any systems or resources, so it can be built from anywhere.
+pub struct FlowField {
+    next: HashMap<GridPos, GridPos>,
+    goals: HashSet<GridPos>,
+}
+
+impl FlowField {
+    /// Runs Dijkstra's algorithm backwards, starting at the goals
+    ///
+    /// * `tiles` - Every tower position mapped to its Entity and travel cost
+    /// * `terrain` - Tiles that can't be walked on at all
+    /// * `default_travel_cost` - Cost of walking onto a free tile
+    pub fn new(
+        tiles: &HashMap<GridPos, (Entity, usize)>,
+        terrain: &HashSet<GridPos>,
+        goals: &HashMap<GridPos, Entity>,
+        death_count: &HashMap<GridPos, usize>,
+        default_travel_cost: usize,
+        config: &GridConfig,
+    ) -> Self {
+        let mut costs: HashMap<GridPos, usize> =
+            goals.keys().map(|goal| (*goal, 0)).collect();
+        let mut next = HashMap::new();
+        let mut open: BinaryHeap<_> = goals
+            .keys()
+            .map(|goal| (Reverse(0), goal.row, goal.col))
+            .collect();
+
+        while let Some((Reverse(cost), row, col)) = open.pop() {
+            let tile = GridPos::new(row, col);
+            if costs.get(&tile).is_some_and(|known| *known < cost) {
+                continue;
+            }
+            let tower = tiles.get(&tile).map(|(entity, _)| entity);
+            let enter_cost = tiles.get(&tile).map_or_else(
+                || {
+                    // Increases the travel_cost by 300% per death on the field
+                    let multiplier =
+                        death_count.get(&tile).map(|c| 1 + c * 3).unwrap_or(1);
+                    default_travel_cost * multiplier
+                },
+                |(_, travel_cost)| *travel_cost,
+            );
+
+            // Every neighbor could step onto `tile` next
+            for (neighbor, nb_tower, _) in
+                tile.neighbors(tiles, default_travel_cost, death_count, config)
+            {
+                if terrain.contains(&neighbor) {
+                    continue;
+                }
+                // Moving within the same tower only costs as much as a free tile, it's
+                // already being broken through
+                let step = match tower.is_some() && tower == nb_tower {
+                    true => default_travel_cost,
+                    false => enter_cost,
+                };
+                let new_cost = cost + step;
+                if costs.get(&neighbor).is_none_or(|known| new_cost < *known) {
+                    costs.insert(neighbor, new_cost);
+                    next.insert(neighbor, tile);
+                    open.push((Reverse(new_cost), neighbor.row, neighbor.col));
+                }
+            }
+        }
+        Self {
+            next,
+            goals: goals.keys().copied().collect(),
+        }
+    }
+
+    /// Returns the cheapest path from `start` to any goal, ordered from the goal back to the
+    /// first step after `start`. Empty if `start` is a goal, None if no goal can be reached.
+    pub fn path_from(&self, start: GridPos) -> Option<Vec<GridPos>> {
+        let mut path = vec![];
+        let mut current = start;
+        while let Some(step) = self.next.get(&current) {
+            path.push(*step);
+            current = *step;
+        }
+        if path.is_empty() && !self.goals.contains(&start) {
+            return None;
+        }
+        path.reverse();
+        Some(path)
+    }
+}
+
+/// The tiles on the straightest line from `start` to the closest goal, ignoring everything
+/// in between. Ordered like [`FlowField::path_from`], None if there are no goals.
+pub fn flight_path(
+    start: GridPos,
+    goals: &HashMap<GridPos, Entity>,
+) -> Option<Vec<GridPos>> {
+    let target = *goals.keys().min_by_key(|goal| start.distance_to(goal))?;
+    let (d_row, d_col) = (target.row - start.row, target.col - start.col);
+    // How far a tile is off the line, the step keeping closest to it is taken
+    let deviation = |tile: GridPos| {
+        ((tile.row - start.row) * d_col - (tile.col - start.col) * d_row).abs()
+    };
+
+    let mut path = vec![];
+    let mut current = start;
+    while current != target {
+        let row_step = current + [(target.row - current.row).signum(), 0];
+        let col_step = current + [0, (target.col - current.col).signum()];
+        current = match (current.row == target.row, current.col == target.col) {
+            (true, _) => col_step,
+            (_, true) => row_step,
+            _ if deviation(row_step) < deviation(col_step) => row_step,
+            _ => col_step,
+        };
+        path.push(current);
+    }
+    path.reverse();
+    Some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn enemies_head_to_the_closer_goal() {
+        let config = GridConfig {
+            rows: 5,
+            columns: 11,
+        };
+        let (left, right) = (GridPos::new(2, 0), GridPos::new(2, 10));
+        let goals = HashMap::from_iter([
+            (left, Entity::PLACEHOLDER),
+            (right, Entity::PLACEHOLDER),
+        ]);
+
+        let field = FlowField::new(
+            &HashMap::new(),
+            &HashSet::new(),
+            &goals,
+            &HashMap::new(),
+            4,
+            &config,
+        );
+        for (start, closer) in [
+            (GridPos::new(2, 3), left),
+            (GridPos::new(0, 1), left),
+            (GridPos::new(2, 7), right),
+            (GridPos::new(4, 9), right),
+        ] {
+            let path = field.path_from(start).unwrap();
+            assert_eq!(path.first(), Some(&closer), "{start:?}");
+        }
+    }
+}
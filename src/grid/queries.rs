@@ -0,0 +1,89 @@
+use bevy::{platform::collections::HashSet, prelude::*};
+
+use super::{Grid, GridConfig, GridPos, TILE_SIZE};
+
+/// Converts a distance in world units, like a tower range, to tiles
+pub fn world_to_tiles(distance: f32) -> f32 {
+    distance / TILE_SIZE
+}
+
+impl GridPos {
+    /// Straight-line distance between the centers of both tiles, in tiles
+    pub fn tile_distance(&self, other: &GridPos) -> f32 {
+        Vec2::new((other.col - self.col) as f32, (other.row - self.row) as f32).length()
+    }
+
+    /// Every tile inside the grid whose center is at most `radius` tiles away from this one
+    pub fn tiles_in_radius(
+        &self,
+        radius: f32,
+        config: &GridConfig,
+    ) -> impl Iterator<Item = GridPos> {
+        let (center, config) = (*self, *config);
+        let reach = radius.max(0.).floor() as isize;
+        (-reach..=reach)
+            .flat_map(move |row| (-reach..=reach).map(move |col| center + [row, col]))
+            .filter(move |pos| {
+                pos.inside_grid_bounds(&config) && center.tile_distance(pos) <= radius
+            })
+    }
+}
+
+/// Read-only queries, so features like AoE or auras don't have to scan the raw maps and
+/// tower footprints themselves. Spawner and goal tiles never count as towers.
+impl Grid {
+    /// Every tower with at least one tile within `radius` tiles of `center`, listed once as
+    /// (origin tile, entity)
+    pub fn towers_in_radius(
+        &self,
+        center: GridPos,
+        radius: f32,
+    ) -> Vec<(GridPos, Entity)> {
+        let mut seen = HashSet::new();
+        center
+            .tiles_in_radius(radius, &self.config)
+            .filter_map(|tile| Some((tile, self.tower_at(&tile)?)))
+            .filter(|(_, entity)| seen.insert(*entity))
+            .map(|(tile, entity)| (self.origin_of(entity).unwrap_or(tile), entity))
+            .collect()
+    }
+
+    /// The tower with the tile closest to `pos`, as (origin tile, entity)
+    // No caller yet, part of the API for plugins
+    #[allow(dead_code)]
+    pub fn nearest_tower(&self, pos: GridPos) -> Option<(GridPos, Entity)> {
+        self.towers
+            .keys()
+            .filter_map(|tile| Some((*tile, self.tower_at(tile)?)))
+            .min_by(|(a, _), (b, _)| {
+                pos.tile_distance(a).total_cmp(&pos.tile_distance(b))
+            })
+            .map(|(tile, entity)| (self.origin_of(entity).unwrap_or(tile), entity))
+    }
+
+    /// Whether every way from `from` to `to` leads through a tower or terrain
+    // No caller yet, part of the API for plugins
+    #[allow(dead_code)]
+    pub fn is_path_blocked(&self, from: GridPos, to: GridPos) -> bool {
+        if self.tower_at(&from).is_some()
+            || self.tower_at(&to).is_some()
+            || self.terrain.contains(&from)
+        {
+            return true;
+        }
+        !self
+            .flood_from([from], |pos| self.tower_at(pos).is_none())
+            .contains(&to)
+    }
+
+    fn tower_at(&self, tile: &GridPos) -> Option<Entity> {
+        if self.enemy_goals.contains_key(tile) || self.enemy_spawners.contains_key(tile) {
+            return None;
+        }
+        self.towers.get(tile).copied()
+    }
+
+    fn origin_of(&self, tower: Entity) -> Option<GridPos> {
+        self.tower_origins.get(&tower).copied()
+    }
+}
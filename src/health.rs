@@ -92,13 +92,21 @@ fn add_health_bar(
     }
 }
 
+/// Hides the bar again once the entity is back at full health, e.g. after a repair
 fn adjust_health_bar(
     query: Query<(&HasHealthBar, &Health), Changed<Health>>,
     mut children: Query<&mut Sprite, With<HealthBar>>,
+    mut visibilities: Query<&mut Visibility>,
 ) {
     for (entity, health) in &query {
         if let Ok(mut sprite) = children.get_mut(entity.childs_child) {
             sprite.custom_size = Some(Vec2::new(40. * health.percentage(), 8.));
         }
+        if let Ok(mut visibility) = visibilities.get_mut(entity.child) {
+            visibility.set_if_neq(match health.current < health.max {
+                true => Visibility::Inherited,
+                false => Visibility::Hidden,
+            });
+        }
     }
 }
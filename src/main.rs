@@ -2,18 +2,21 @@ use app_state::AppStatePlugin;
 use bevy::{
     audio::{AudioPlugin, Volume},
     prelude::*,
-    window::WindowResolution,
+    window::{WindowMode, WindowResolution},
 };
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
 use bevy_lunex::UiSourceCamera;
-use enemy::EnemyPlugin;
+use enemy::{EnemyPathDebugPlugin, EnemyPlugin, ManualSpawnPlugin};
 use fastrand::Rng;
-use game_loop::GameLoopPlugin;
-use grid::GridPlugin;
+use game_loop::{GameLoopPlugin, Sandbox, SpawnerCount, WavesPath};
+use grid::{GridConfig, GridPlugin, GridPos};
 use health::HealthPlugin;
-use map::MapPlugin;
+use map::{LevelName, MapPlugin, TerrainPath};
+use save::SavePlugin;
+use serde::{Deserialize, Serialize};
 use soundtrack::SoundtrackPlugin;
-use tower::TowerPlugin;
+use theme::ThemePlugin;
+use tower::{ClearTowersPlugin, TowerPlugin};
 use ui::UIPlugin;
 
 mod animation;
@@ -23,23 +26,35 @@ mod game_loop;
 mod grid;
 mod health;
 mod map;
+mod save;
 mod soundtrack;
+mod theme;
 mod tower;
 mod ui;
 
 fn main() {
     let mut app = App::new();
 
+    let resolution = resolution_arg();
+    let windowed = resolution.is_some() || std::env::args().any(|a| a == "--windowed");
+    let (width, height) = match resolution {
+        Some(Ok(size)) => size,
+        _ => DEFAULT_WINDOW_SIZE,
+    };
+
     app.add_plugins(
         DefaultPlugins
             .set(WindowPlugin {
                 primary_window: Some(Window {
                     #[cfg(debug_assertions)]
                     present_mode: bevy::window::PresentMode::AutoNoVsync,
-                    mode: bevy::window::WindowMode::BorderlessFullscreen(
-                        MonitorSelection::Primary,
-                    ),
-                    resolution: WindowResolution::default()
+                    mode: match windowed {
+                        true => WindowMode::Windowed,
+                        false => {
+                            WindowMode::BorderlessFullscreen(MonitorSelection::Primary)
+                        }
+                    },
+                    resolution: WindowResolution::new(width, height)
                         .with_scale_factor_override(1.),
                     ..default()
                 }),
@@ -51,8 +66,24 @@ fn main() {
             }),
     );
 
+    // Logging is only available once the DefaultPlugins are added
+    if let Some(Err(arg)) = resolution {
+        warn!(
+            "Invalid resolution {arg:?}, expected WxH. Falling back to {}x{}",
+            DEFAULT_WINDOW_SIZE.0, DEFAULT_WINDOW_SIZE.1
+        );
+    }
+
     if std::env::args().any(|a| a == "--egui") {
-        app.add_plugins(WorldInspectorPlugin::new());
+        app.add_plugins((WorldInspectorPlugin::new(), ClearTowersPlugin));
+    }
+
+    if cfg!(debug_assertions) || std::env::args().any(|a| a == "--dev") {
+        app.add_plugins(ManualSpawnPlugin);
+    }
+
+    if std::env::args().any(|a| a == "--debug-paths") {
+        app.add_plugins(EnemyPathDebugPlugin);
     }
 
     app.register_type::<Settings>();
@@ -60,16 +91,42 @@ fn main() {
 
     app.insert_resource(RngResource(Rng::new()));
 
-    let [sfx_enabled, soundtrack_enabled] =
-        match std::env::args().any(|a| a == "--silent") {
-            true => [false, false],
-            false => [true, true],
-        };
+    if let Some(arg) = std::env::args().skip_while(|a| a != "--spawners").nth(1) {
+        match arg.parse() {
+            Ok(count) => {
+                app.insert_resource(SpawnerCount(count));
+            }
+            Err(_) => warn!("Invalid spawner count {arg:?}, using the default"),
+        }
+    }
 
-    app.insert_resource(Settings {
-        sfx_enabled,
-        soundtrack_enabled,
-    });
+    if let Some(arg) = std::env::args().skip_while(|a| a != "--map-size").nth(1) {
+        match GridConfig::preset(&arg) {
+            Some(config) => {
+                app.insert_resource(config);
+            }
+            None => warn!("Invalid map size {arg:?}, expected small, medium or large"),
+        }
+    }
+
+    if let Some(path) = std::env::args().skip_while(|a| a != "--waves").nth(1) {
+        app.insert_resource(WavesPath(path.into()));
+    }
+
+    if let Some(path) = std::env::args().skip_while(|a| a != "--terrain").nth(1) {
+        app.insert_resource(TerrainPath(path.into()));
+    }
+
+    if let Some(name) = std::env::args().skip_while(|a| a != "--level").nth(1) {
+        app.insert_resource(LevelName(name));
+    }
+
+    if std::env::args().any(|a| a == "--sandbox") {
+        app.insert_resource(Sandbox(true));
+    }
+
+    let args: Vec<String> = std::env::args().collect();
+    app.insert_resource(Settings::new(&args, SettingsFile::load()));
 
     app.add_plugins((
         animation::AnimationPlugin,
@@ -79,7 +136,9 @@ fn main() {
         GridPlugin,
         HealthPlugin,
         MapPlugin,
+        SavePlugin,
         SoundtrackPlugin,
+        ThemePlugin,
         TowerPlugin,
         UIPlugin,
     ));
@@ -87,20 +146,149 @@ fn main() {
     app.add_systems(PreStartup, preload_assets);
     app.add_systems(Startup, setup);
     app.add_systems(Update, exit_on_ctrl_q);
+    app.add_systems(
+        Update,
+        write_settings_file.run_if(|settings: Res<Settings>| {
+            settings.is_changed() && !settings.is_added()
+        }),
+    );
 
     app.run();
 }
 
+const DEFAULT_WINDOW_SIZE: (f32, f32) = (1280., 720.);
+const DEFAULT_SELL_REFUND: f32 = 0.5;
+
+/// Returns the size passed via `--resolution WxH`, or the raw argument if it is invalid
+fn resolution_arg() -> Option<Result<(f32, f32), String>> {
+    let mut args = std::env::args().skip_while(|a| a != "--resolution");
+    args.next()?;
+    let arg = args.next().unwrap_or_default();
+
+    let size = arg.split_once('x').and_then(|(width, height)| {
+        Some((width.parse::<u32>().ok()?, height.parse::<u32>().ok()?))
+    });
+    match size {
+        Some((width, height)) if width > 0 && height > 0 => {
+            Some(Ok((width as f32, height as f32)))
+        }
+        _ => Some(Err(arg)),
+    }
+}
+
 #[derive(Resource, Reflect)]
 #[reflect(Resource)]
 struct Settings {
     sfx_enabled: bool,
     soundtrack_enabled: bool,
+    /// Between 0 and 1, in steps of `VOLUME_STEP`. The enabled flags still mute on top
+    sfx_volume: f32,
+    music_volume: f32,
+    /// Let enemies walk around corners in a curve instead of turning on the spot
+    smooth_paths: bool,
+    /// Shooting towers need power, which is produced by generators
+    power: bool,
+    /// Hide everything that isn't within the vision of a tower or the goal
+    fog: bool,
+    /// Towers can't target enemies hidden in the fog, only has an effect together with `fog`
+    fog_targeting: bool,
+    /// Log details about every enemy that failed to find a path
+    verbose_paths: bool,
+    /// Pause the game when the window loses focus
+    focus_pause: bool,
+    /// Unpause again on focus gain, instead of waiting for the player to press P
+    focus_resume: bool,
+    /// Skip purely decorative motion like recoil, flashes and tweens. Essential feedback such
+    /// as health bars and placement tints stays
+    reduced_motion: bool,
+    /// Place towers with a cursor moved by the arrow keys and Enter instead of the mouse
+    keyboard_placement: bool,
+    /// Enemies alive for too long get faster and stronger, and eventually break through
+    /// walls, so a maze can't stall the game forever
+    enrage: bool,
+    /// Show the damage dealt to enemies as numbers floating up from them
+    damage_numbers: bool,
+    /// Tint spawners shortly before they release an enemy
+    spawn_warning: bool,
+    /// Fraction of the tower cost refunded when selling it
+    sell_refund: f32,
 }
 
 impl Settings {
     const SFX_VARIANTS: &[&str] = &["Sfx enabled", "Sfx disabled"];
     const SOUNDTRACK_VARIANTS: &[&str] = &["Soundtrack enabled", "Soundtrack disabled"];
+    const REDUCED_MOTION_VARIANTS: &[&str] =
+        &["Reduced motion: On", "Reduced motion: Off"];
+    const KEYBOARD_PLACEMENT_VARIANTS: &[&str] =
+        &["Keyboard placement: On", "Keyboard placement: Off"];
+    const VOLUME_STEP: f32 = 0.1;
+    const SFX_VOLUME_VARIANTS: &[&str] = &[
+        "Sfx volume: 0%",
+        "Sfx volume: 10%",
+        "Sfx volume: 20%",
+        "Sfx volume: 30%",
+        "Sfx volume: 40%",
+        "Sfx volume: 50%",
+        "Sfx volume: 60%",
+        "Sfx volume: 70%",
+        "Sfx volume: 80%",
+        "Sfx volume: 90%",
+        "Sfx volume: 100%",
+    ];
+    const MUSIC_VOLUME_VARIANTS: &[&str] = &[
+        "Music volume: 0%",
+        "Music volume: 10%",
+        "Music volume: 20%",
+        "Music volume: 30%",
+        "Music volume: 40%",
+        "Music volume: 50%",
+        "Music volume: 60%",
+        "Music volume: 70%",
+        "Music volume: 80%",
+        "Music volume: 90%",
+        "Music volume: 100%",
+    ];
+
+    /// Combines the command line flags with the settings file, the flags take precedence
+    fn new(args: &[String], file: SettingsFile) -> Self {
+        let flag = |name: &str| args.iter().any(|a| a == name);
+        let sell_refund = match args.iter().skip_while(|a| *a != "--sell-refund").nth(1) {
+            Some(arg) => match arg.parse::<f32>() {
+                Ok(fraction) if (0. ..=1.).contains(&fraction) => fraction,
+                _ => {
+                    warn!(
+                        "Invalid sell refund {arg:?}, expected a fraction between 0 and 1"
+                    );
+                    DEFAULT_SELL_REFUND
+                }
+            },
+            None => DEFAULT_SELL_REFUND,
+        };
+        let [sfx_enabled, soundtrack_enabled] = match flag("--silent") {
+            true => [false, false],
+            false => [file.sfx_enabled, file.soundtrack_enabled],
+        };
+
+        Self {
+            sfx_enabled,
+            soundtrack_enabled,
+            sfx_volume: file.sfx_volume.clamp(0., 1.),
+            music_volume: file.music_volume.clamp(0., 1.),
+            smooth_paths: !flag("--no-path-smoothing"),
+            power: flag("--power"),
+            fog: flag("--fog"),
+            fog_targeting: flag("--fog-targeting"),
+            verbose_paths: flag("--verbose-paths"),
+            focus_pause: flag("--focus-pause") || flag("--focus-pause-manual"),
+            focus_resume: !flag("--focus-pause-manual"),
+            reduced_motion: file.reduced_motion || flag("--reduced-motion"),
+            keyboard_placement: file.keyboard_placement || flag("--keyboard-placement"),
+            enrage: flag("--enrage"),
+            damage_numbers: !flag("--no-damage-numbers"),
+            spawn_warning: !flag("--no-spawn-warning"),
+            sell_refund,
+        }
+    }
 
     fn sfx_label(&self) -> &'static str {
         match self.sfx_enabled {
@@ -115,12 +303,128 @@ impl Settings {
             false => Self::SOUNDTRACK_VARIANTS[1],
         }
     }
+
+    fn reduced_motion_label(&self) -> &'static str {
+        match self.reduced_motion {
+            true => Self::REDUCED_MOTION_VARIANTS[0],
+            false => Self::REDUCED_MOTION_VARIANTS[1],
+        }
+    }
+
+    fn keyboard_placement_label(&self) -> &'static str {
+        match self.keyboard_placement {
+            true => Self::KEYBOARD_PLACEMENT_VARIANTS[0],
+            false => Self::KEYBOARD_PLACEMENT_VARIANTS[1],
+        }
+    }
+
+    fn sfx_volume_label(&self) -> &'static str {
+        Self::SFX_VOLUME_VARIANTS[Self::volume_index(self.sfx_volume)]
+    }
+
+    fn music_volume_label(&self) -> &'static str {
+        Self::MUSIC_VOLUME_VARIANTS[Self::volume_index(self.music_volume)]
+    }
+
+    fn volume_index(volume: f32) -> usize {
+        ((volume / Self::VOLUME_STEP).round() as usize).min(10)
+    }
+
+    /// Moves the volume one step up or down, staying between 0 and 1
+    fn step_volume(volume: f32, up: bool) -> f32 {
+        let step = match up {
+            true => Self::VOLUME_STEP,
+            false => -Self::VOLUME_STEP,
+        };
+        (volume + step).clamp(0., 1.)
+    }
+}
+
+/// The part of `Settings` changeable in the settings menu, stored in
+/// `roadblock-td/settings.ron` whenever it changes. Settings forced by command line flags
+/// like `--silent` end up in the file as well once anything else is changed in the menu
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct SettingsFile {
+    sfx_enabled: bool,
+    soundtrack_enabled: bool,
+    sfx_volume: f32,
+    music_volume: f32,
+    reduced_motion: bool,
+    keyboard_placement: bool,
+}
+
+impl Default for SettingsFile {
+    fn default() -> Self {
+        Self {
+            sfx_enabled: true,
+            soundtrack_enabled: true,
+            sfx_volume: 1.,
+            music_volume: 1.,
+            reduced_motion: false,
+            keyboard_placement: false,
+        }
+    }
+}
+
+impl SettingsFile {
+    /// Falls back to the defaults if the file is missing or broken, and writes them to it
+    fn load() -> Self {
+        let Some(path) = save::config_path("settings.ron") else {
+            return Self::default();
+        };
+        let loaded = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| ron::from_str(&content).map_err(|e| e.to_string()));
+        match loaded {
+            Ok(file) => file,
+            Err(e) => {
+                warn!(
+                    "Couldn't read the settings file {path:?}, using the defaults: {e}"
+                );
+                let file = Self::default();
+                file.write();
+                file
+            }
+        }
+    }
+
+    fn write(&self) {
+        let Some(path) = save::config_path("settings.ron") else {
+            warn!("Couldn't find a config directory to store the settings in");
+            return;
+        };
+        let written = ron::ser::to_string_pretty(self, default())
+            .map_err(|e| e.to_string())
+            .and_then(|content| {
+                if let Some(dir) = path.parent() {
+                    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+                }
+                std::fs::write(&path, content).map_err(|e| e.to_string())
+            });
+        if let Err(e) = written {
+            warn!("Failed to write the settings file {path:?}: {e}");
+        }
+    }
+}
+
+fn write_settings_file(settings: Res<Settings>) {
+    SettingsFile {
+        sfx_enabled: settings.sfx_enabled,
+        soundtrack_enabled: settings.soundtrack_enabled,
+        sfx_volume: settings.sfx_volume,
+        music_volume: settings.music_volume,
+        reduced_motion: settings.reduced_motion,
+        keyboard_placement: settings.keyboard_placement,
+    }
+    .write();
 }
 
 #[derive(Resource, Deref, DerefMut)]
 struct RngResource(Rng);
 
-#[derive(Reflect, Default, PartialEq, Debug, Clone, Copy)]
+#[derive(Reflect, Serialize, Deserialize, Default, PartialEq, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
 enum Orientation {
     #[default]
     Up,
@@ -136,6 +440,26 @@ impl Orientation {
             Orientation::Left | Orientation::Right => true,
         }
     }
+
+    /// Offset to the neighbouring tile in this direction
+    fn step(&self) -> GridPos {
+        match self {
+            Orientation::Up => GridPos::new(1, 0),
+            Orientation::Down => GridPos::new(-1, 0),
+            Orientation::Left => GridPos::new(0, -1),
+            Orientation::Right => GridPos::new(0, 1),
+        }
+    }
+
+    /// Rotation around the z axis facing this way, 0 is facing right
+    fn angle(&self) -> f32 {
+        match self {
+            Orientation::Right => 0.,
+            Orientation::Up => std::f32::consts::FRAC_PI_2,
+            Orientation::Left => std::f32::consts::PI,
+            Orientation::Down => -std::f32::consts::FRAC_PI_2,
+        }
+    }
 }
 
 const CAMERA_POS: Vec3 = Vec3::new(0., 0., 900.);
@@ -154,21 +478,72 @@ fn exit_on_ctrl_q(mut app_exit: EventWriter<AppExit>, input: Res<ButtonInput<Key
     }
 }
 
-#[allow(dead_code)]
 #[derive(Component, Reflect)]
 #[reflect(Component)]
 struct AssetLock(#[reflect(ignore)] Vec<UntypedHandle>);
 
+const PRELOADED_IMAGES: &[&str] = &[
+    "title_image.png",
+    "button_border.png",
+    "button_highlight.png",
+    "sprites/enemies/BODY_skeleton_walk.png",
+    "sprites/enemies/BODY_skeleton_attack.png",
+    "sprites/enemies/WEAPON_dagger.png",
+    "sprites/goals/heart.png",
+    "sprites/spawners/red_spawner.png",
+];
+const PRELOADED_AUDIO: &[&str] = &["sfx/Cloud Click.ogg", "sfx/Toom Click.ogg"];
+
 /// This system makes sure certain Assets never get dropped by bevy, keeping them in memory for the
-/// entire lifetime of the game
+/// entire lifetime of the game. The loading screen waits until all of them are loaded, so
+/// nothing has to be loaded in the middle of a wave.
 fn preload_assets(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let images = PRELOADED_IMAGES
+        .iter()
+        .map(|path| asset_server.load::<Image>(*path).untyped());
+    let audio = PRELOADED_AUDIO
+        .iter()
+        .map(|path| asset_server.load::<AudioSource>(*path).untyped());
+
     commands.spawn((
         Name::new("AssetLock"),
-        AssetLock(vec![
-            asset_server.load::<Image>("title_image.png").untyped(),
-            asset_server
-                .load::<AudioSource>("sfx/Cloud Click.ogg")
-                .untyped(),
-        ]),
+        AssetLock(images.chain(audio).collect()),
     ));
 }
+
+/// Headless game on the small grid with a manually advanced clock, holding the resources
+/// and events most systems rely on. Tests only add the systems they run
+#[cfg(test)]
+fn test_app() -> App {
+    use bevy::{app::TaskPoolPlugin, state::app::StatesPlugin};
+
+    use app_state::{AppState, GameState};
+    use enemy::{EnemyDamaged, EnemyKilled, EnemyLeaked, PathChangedEvent};
+    use game_loop::{Currency, Difficulty, GameStatistics, WaveInfo};
+    use grid::Grid;
+
+    let mut app = App::new();
+    app.add_plugins((
+        TaskPoolPlugin::default(),
+        AssetPlugin::default(),
+        StatesPlugin,
+    ))
+    .init_asset::<Image>()
+    .init_asset::<TextureAtlasLayout>()
+    .init_resource::<Time>()
+    .insert_state(AppState::Game)
+    .add_sub_state::<GameState>()
+    .insert_resource(Grid::new(GridConfig::SMALL))
+    .insert_resource(GridConfig::SMALL)
+    .insert_resource(Settings::new(&[], SettingsFile::default()))
+    .init_resource::<Currency>()
+    .init_resource::<GameStatistics>()
+    .init_resource::<Sandbox>()
+    .init_resource::<Difficulty>()
+    .init_resource::<WaveInfo>()
+    .add_event::<PathChangedEvent>()
+    .add_event::<EnemyKilled>()
+    .add_event::<EnemyDamaged>()
+    .add_event::<EnemyLeaked>();
+    app
+}
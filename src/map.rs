@@ -1,23 +1,62 @@
+use std::path::PathBuf;
+
 use bevy::{
-    input::mouse::{AccumulatedMouseMotion, AccumulatedMouseScroll},
+    input::{
+        common_conditions::input_just_pressed,
+        mouse::{AccumulatedMouseMotion, AccumulatedMouseScroll},
+    },
+    platform::collections::HashMap,
     prelude::*,
     window::PrimaryWindow,
 };
+use serde::Deserialize;
+
+pub use level::{Level, LevelName};
+use level::{LevelPlugin, load_level};
+
+use crate::{
+    CAMERA_POS, Settings,
+    app_state::{AppState, TowerPlacingState},
+    enemy::spawn_enemy_goal,
+    grid::{Grid, GridConfig, GridPos, TILE_SIZE, grid_to_world_coords},
+};
 
-use crate::{CAMERA_POS, app_state::AppState};
+mod level;
 
 const BACKGROUND_COLOR: Color = Color::hsl(150., 1., 0.4);
+/// Seconds it takes the camera to return to its default position after pressing `Home`
+const CAMERA_RESET_DURATION: f32 = 0.3;
+const ROCK_COLOR: Color = Color::srgb(0.45, 0.42, 0.4);
+const WATER_COLOR: Color = Color::srgb(0.2, 0.45, 0.8);
 
 pub struct MapPlugin;
 
 impl Plugin for MapPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<MapInfo>()
-            .add_systems(OnEnter(AppState::Game), init)
+            .register_type::<CameraReset>()
+            .add_plugins(LevelPlugin)
+            .add_systems(Startup, load_terrain.after(load_level))
+            .add_systems(
+                OnEnter(AppState::Game),
+                (init, place_terrain.after(spawn_enemy_goal)),
+            )
             .add_systems(OnExit(AppState::Game), exit)
             .add_systems(
                 Update,
-                (pan_camera, camera_zoom).run_if(in_state(AppState::Game)),
+                (
+                    start_camera_reset.run_if(input_just_pressed(KeyCode::Home)),
+                    // A reset cancels manual panning and zooming until it is done
+                    reset_camera.run_if(any_with_component::<CameraReset>),
+                    (
+                        pan_camera,
+                        // The wheel rotates the tower being placed instead
+                        camera_zoom.run_if(not(in_state(TowerPlacingState::Placing))),
+                    )
+                        .run_if(not(any_with_component::<CameraReset>)),
+                )
+                    .chain()
+                    .run_if(in_state(AppState::Game)),
             );
     }
 }
@@ -30,6 +69,141 @@ struct MapInfo {
     anchor: Vec2,
 }
 
+/// Moves the camera from where it was when the reset started back to [`CAMERA_POS`]
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct CameraReset {
+    from: Vec3,
+    from_scale: f32,
+    timer: Timer,
+}
+
+/// Terrain file passed via `--terrain <path>`
+#[derive(Resource, Clone)]
+pub struct TerrainPath(pub PathBuf);
+
+#[derive(Clone, Copy, Debug)]
+enum TerrainKind {
+    Rock,
+    Water,
+}
+
+impl TerrainKind {
+    fn from_char(c: char) -> Option<Self> {
+        match c {
+            '#' => Some(Self::Rock),
+            '~' => Some(Self::Water),
+            _ => None,
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            Self::Rock => ROCK_COLOR,
+            Self::Water => WATER_COLOR,
+        }
+    }
+}
+
+/// Blocked tiles of the map, copied into `Grid::terrain` whenever a game starts. Empty
+/// without a terrain file
+#[derive(Resource, Default)]
+struct Terrain(HashMap<GridPos, TerrainKind>);
+
+#[derive(Deserialize)]
+struct TerrainFile {
+    /// One string per row of tiles, starting with the top one
+    rows: Vec<String>,
+}
+
+impl Terrain {
+    fn parse(source: &str, config: &GridConfig) -> Result<Self, String> {
+        let file: TerrainFile = ron::from_str(source).map_err(|e| e.to_string())?;
+        Ok(Self::from_rows(&file.rows, config))
+    }
+
+    /// One string per row of tiles, starting with the top one. Tiles outside of the grid
+    /// are dropped
+    fn from_rows(rows: &[String], config: &GridConfig) -> Self {
+        let mut tiles = HashMap::new();
+        let mut outside = 0;
+        for (i, line) in rows.iter().enumerate() {
+            for (col, c) in line.chars().enumerate() {
+                let Some(kind) = TerrainKind::from_char(c) else {
+                    continue;
+                };
+                let pos = GridPos::new(config.rows - 1 - i as isize, col as isize);
+                match pos.inside_grid_bounds(config) {
+                    true => {
+                        tiles.insert(pos, kind);
+                    }
+                    false => outside += 1,
+                }
+            }
+        }
+        if outside > 0 {
+            warn!("Skipping {outside} terrain tiles outside of the grid");
+        }
+        Self(tiles)
+    }
+}
+
+/// Levels bring their own terrain, see `level::load_level`
+fn load_terrain(
+    mut commands: Commands,
+    path: Option<Res<TerrainPath>>,
+    config: Res<GridConfig>,
+    level: Option<Res<Level>>,
+) {
+    if level.is_some() {
+        return;
+    }
+    let Some(path) = path else {
+        commands.init_resource::<Terrain>();
+        return;
+    };
+    let terrain = std::fs::read_to_string(&path.0)
+        .map_err(|e| e.to_string())
+        .and_then(|source| Terrain::parse(&source, &config));
+    match terrain {
+        Ok(terrain) => {
+            info!(
+                "Loaded {} terrain tiles from {}",
+                terrain.0.len(),
+                path.0.display()
+            );
+            commands.insert_resource(terrain);
+        }
+        Err(e) => {
+            warn!(
+                "Failed to load terrain from {}, using an open map: {e}",
+                path.0.display()
+            );
+            commands.init_resource::<Terrain>();
+        }
+    }
+}
+
+/// The goal and its surroundings always stay open, so it can't be walled in by the map
+fn place_terrain(
+    mut commands: Commands,
+    terrain: Res<Terrain>,
+    mut grid: ResMut<Grid>,
+    config: Res<GridConfig>,
+) {
+    for (pos, kind) in &terrain.0 {
+        if grid.enemy_goals.contains_key(pos) || grid.unbuildable.contains(pos) {
+            continue;
+        }
+        grid.terrain.insert(*pos);
+        commands.spawn((
+            Name::new(format!("Terrain: {kind:?}")),
+            Sprite::from_color(kind.color(), Vec2::splat(TILE_SIZE)),
+            Transform::from_translation(grid_to_world_coords(*pos, &config).extend(0.5)),
+        ));
+    }
+}
+
 fn init(mut commands: Commands) {
     let map_size = Vec2::new(3000., 2000.);
     let map_anchor = Vec2::new(-map_size.x / 2., -map_size.y / 2.);
@@ -49,11 +223,55 @@ fn init(mut commands: Commands) {
     });
 }
 
-fn exit(mut commands: Commands, mut camera: Single<&mut Transform, With<Camera>>) {
-    camera.translation = CAMERA_POS;
+fn exit(mut commands: Commands, camera: Single<(Entity, &mut Transform), With<Camera>>) {
+    let (entity, mut transform) = camera.into_inner();
+    transform.translation = CAMERA_POS;
+    commands.entity(entity).remove::<CameraReset>();
     commands.remove_resource::<MapInfo>();
 }
 
+fn start_camera_reset(
+    mut commands: Commands,
+    camera: Single<(Entity, &Transform, Has<CameraReset>), With<Camera>>,
+) {
+    let (entity, transform, resetting) = *camera;
+    // Already on its way back, restarting from here would only make the camera stutter
+    if resetting {
+        return;
+    }
+    commands.entity(entity).insert(CameraReset {
+        from: transform.translation,
+        from_scale: transform.scale.x,
+        timer: Timer::from_seconds(CAMERA_RESET_DURATION, TimerMode::Once),
+    });
+}
+
+fn reset_camera(
+    mut commands: Commands,
+    camera: Single<(Entity, &mut Transform, &mut CameraReset)>,
+    time: Res<Time<Real>>,
+    settings: Res<Settings>,
+) {
+    let (entity, mut transform, mut reset) = camera.into_inner();
+    reset.timer.tick(time.delta());
+    // Jump straight to the default instead of gliding there
+    let done = reset.timer.finished() || settings.reduced_motion;
+
+    // Ease out, so the camera slows down as it arrives
+    let t = match done {
+        true => 1.,
+        false => 1. - (1. - reset.timer.fraction()).powi(3),
+    };
+    transform.translation = reset.from.lerp(CAMERA_POS, t);
+    let scale = reset.from_scale + (1. - reset.from_scale) * t;
+    transform.scale.x = scale;
+    transform.scale.y = scale;
+
+    if done {
+        commands.entity(entity).remove::<CameraReset>();
+    }
+}
+
 fn pan_camera(
     mut camera: Single<&mut Transform, With<Camera>>,
     input: Res<ButtonInput<KeyCode>>,
@@ -61,7 +279,7 @@ fn pan_camera(
     motion: Res<AccumulatedMouseMotion>,
     map_info: Res<MapInfo>,
     window: Single<&Window, With<PrimaryWindow>>,
-    time: Res<Time>,
+    time: Res<Time<Real>>,
 ) {
     let [w_pressed, a_pressed, s_pressed, d_pressed] = [
         input.pressed(KeyCode::KeyW),
@@ -79,36 +297,49 @@ fn pan_camera(
             camera.translation += direction.normalize() * time.delta_secs() * 500.;
         }
     } else if mouse_input.pressed(MouseButton::Middle) && motion.delta != Vec2::ZERO {
-        camera.translation.x -= motion.delta.x;
-        camera.translation.y += motion.delta.y;
+        // Scaled, so the board stays under the cursor at any zoom
+        camera.translation.x -= motion.delta.x * camera.scale.x;
+        camera.translation.y += motion.delta.y * camera.scale.y;
     } else {
         return;
     }
 
-    let window_size = window.size() / 2.;
-
-    // Prevent panning to infinity
-    if camera.translation.x < map_info.anchor.x + window_size.x {
-        camera.translation.x = map_info.anchor.x + window_size.x;
-    } else if camera.translation.x > map_info.anchor.x + map_info.size.x - window_size.x {
-        camera.translation.x = map_info.anchor.x + map_info.size.x - window_size.x;
-    }
-
-    if camera.translation.y < map_info.anchor.y + window_size.y {
-        camera.translation.y = map_info.anchor.y + window_size.y;
-    } else if camera.translation.y > map_info.anchor.y + map_info.size.y - window_size.y {
-        camera.translation.y = map_info.anchor.y + map_info.size.y - window_size.y;
-    }
+    clamp_camera(&mut camera, &map_info, &window);
 }
 
+const MIN_ZOOM: f32 = 0.5;
+const MAX_ZOOM: f32 = 1.;
+
 fn camera_zoom(
     mut camera: Single<&mut Transform, With<Camera>>,
     scroll: Res<AccumulatedMouseScroll>,
+    map_info: Res<MapInfo>,
+    window: Single<&Window, With<PrimaryWindow>>,
 ) {
     if scroll.delta.y != 0. {
         let mut new_scale = camera.scale.x - 0.05 * scroll.delta.y;
-        new_scale = new_scale.clamp(0.5, 1.);
+        new_scale = new_scale.clamp(MIN_ZOOM, MAX_ZOOM);
         camera.scale.x = new_scale;
         camera.scale.y = new_scale;
+        // Zooming out near an edge would otherwise show what's beyond the board
+        clamp_camera(&mut camera, &map_info, &window);
     }
 }
+
+/// Keeps the visible area on the board. Placing and spawning convert the cursor through
+/// the camera's `GlobalTransform`, so they keep working at any position and zoom
+fn clamp_camera(camera: &mut Transform, map_info: &MapInfo, window: &Window) {
+    let half_view = window.size() / 2. * camera.scale.truncate();
+    let min = map_info.anchor + half_view;
+    let max = map_info.anchor + map_info.size - half_view;
+    // A board smaller than the view is simply centered
+    let center = map_info.anchor + map_info.size / 2.;
+    camera.translation.x = match min.x <= max.x {
+        true => camera.translation.x.clamp(min.x, max.x),
+        false => center.x,
+    };
+    camera.translation.y = match min.y <= max.y {
+        true => camera.translation.y.clamp(min.y, max.y),
+        false => center.y,
+    };
+}
@@ -0,0 +1,128 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    enemy::EnemyGoal,
+    grid::{Grid, GridConfig, GridPos},
+};
+
+use super::Terrain;
+
+pub struct LevelPlugin;
+
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, load_level);
+    }
+}
+
+/// Name of the level passed via `--level <name>`, loaded from `assets/levels/<name>.ron`
+#[derive(Resource, Clone)]
+pub struct LevelName(pub String);
+
+/// Fixed goals, spawners and starting currency of the chosen level. Without one, the goal
+/// sits at the right edge and spawners are placed randomly
+#[derive(Resource, Clone, Debug)]
+pub struct Level {
+    /// As passed via `--level`, saves only load into the level they were made on
+    pub name: String,
+    pub goals: Vec<GridPos>,
+    /// Used in order, one for every new spawner of the waves
+    pub spawners: Vec<GridPos>,
+    /// Replaces the amount given by the difficulty
+    pub starting_currency: Option<i32>,
+}
+
+#[derive(Deserialize)]
+struct LevelFile {
+    size: GridConfig,
+    /// Same format as the rows of a terrain file
+    #[serde(default)]
+    terrain: Vec<String>,
+    goals: Vec<GridPos>,
+    spawners: Vec<GridPos>,
+    #[serde(default)]
+    starting_currency: Option<i32>,
+}
+
+impl LevelFile {
+    /// Every goal and spawner has to be on the board and off the terrain, and every
+    /// spawner has to be connected to a goal
+    fn validate(&self, terrain: &Terrain) -> Result<(), String> {
+        if self.goals.is_empty() {
+            return Err("no goals defined".to_string());
+        }
+        let mut grid = Grid::new(self.size);
+        for (i, goal) in self.goals.iter().enumerate() {
+            let tiles = std::iter::once(*goal).chain(EnemyGoal::Heart.other_tiles(goal));
+            for tile in tiles {
+                if !tile.inside_grid_bounds(&self.size) {
+                    return Err(format!("goal {i} at {goal} is outside of the grid"));
+                }
+                grid.enemy_goals.insert(tile, Entity::PLACEHOLDER);
+            }
+            EnemyGoal::Heart.add_unbuildable_surroundings(goal, &mut grid);
+        }
+        // Terrain doesn't cover the goals, the same as when it's placed in a game
+        grid.terrain = terrain
+            .0
+            .keys()
+            .filter(|pos| {
+                !grid.enemy_goals.contains_key(*pos) && !grid.unbuildable.contains(*pos)
+            })
+            .copied()
+            .collect();
+
+        let reachable = grid.reachable_through_towers();
+        for (i, spawner) in self.spawners.iter().enumerate() {
+            if !spawner.inside_grid_bounds(&self.size) {
+                return Err(format!("spawner {i} at {spawner} is outside of the grid"));
+            }
+            if grid.terrain.contains(spawner) || grid.enemy_goals.contains_key(spawner) {
+                return Err(format!("spawner {i} at {spawner} is not on open ground"));
+            }
+            if !reachable.contains(spawner) {
+                return Err(format!("spawner {i} at {spawner} can't reach any goal"));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An invalid level is reported and the game falls back to the default layout, keeping
+/// the map size and terrain passed via the other flags
+pub(super) fn load_level(mut commands: Commands, name: Option<Res<LevelName>>) {
+    let Some(name) = name else {
+        return;
+    };
+    let path = format!("assets/levels/{}.ron", name.0);
+    let level = std::fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|source| ron::from_str::<LevelFile>(&source).map_err(|e| e.to_string()))
+        .and_then(|file| {
+            let terrain = Terrain::from_rows(&file.terrain, &file.size);
+            file.validate(&terrain)?;
+            Ok((file, terrain))
+        });
+    let (file, terrain) = match level {
+        Ok(level) => level,
+        Err(e) => {
+            error!("Failed to load the level {path}, using the default layout: {e}");
+            return;
+        }
+    };
+
+    info!(
+        "Loaded the level {path} with {} goals and {} spawners",
+        file.goals.len(),
+        file.spawners.len()
+    );
+    commands.insert_resource(file.size);
+    commands.insert_resource(terrain);
+    commands.insert_resource(Level {
+        name: name.0.clone(),
+        goals: file.goals,
+        spawners: file.spawners,
+        starting_currency: file.starting_currency,
+    });
+}
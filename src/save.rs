@@ -0,0 +1,531 @@
+use std::{path::PathBuf, time::Duration};
+
+use bevy::{
+    ecs::system::SystemParam, input::common_conditions::input_just_pressed, prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Orientation,
+    app_state::{AppState, GameState, WaveState},
+    enemy::{
+        Enemy, EnemyGoal, EnemySpawn, EnemyType, GoalLeaks, PathChangedEvent,
+        SavedSpawner, SpawnQueue, restore_spawner, spawn_enemy,
+    },
+    game_loop::{Currency, Difficulty, GameStatistics, SavedWaves, WaveInfo, Waves},
+    grid::{Grid, GridConfig, GridPos},
+    health::Health,
+    map::Level,
+    tower::{MAX_TOWER_LEVEL, TargetingMode, Tower, TowerType, spawn_tower},
+};
+
+/// Saving and loading a single run. F5 saves the current game, F9 loads the save, replacing
+/// whatever is currently being played
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SaveGame>()
+            .add_event::<LoadGame>()
+            .add_systems(
+                Update,
+                (
+                    write_save_event.run_if(input_just_pressed(KeyCode::F5)),
+                    collect_save.pipe(save_game).run_if(on_event::<SaveGame>),
+                )
+                    .chain()
+                    .run_if(in_state(AppState::Game))
+                    .run_if(not(in_state(GameState::GameOver))),
+            )
+            .add_systems(
+                Update,
+                (
+                    write_load_event.run_if(input_just_pressed(KeyCode::F9)),
+                    load_game.run_if(on_event::<LoadGame>),
+                )
+                    .chain()
+                    .run_if(not(in_state(AppState::Loading))),
+            )
+            .add_systems(
+                OnEnter(AppState::Menu),
+                continue_to_game.run_if(resource_exists::<PendingLoad>),
+            )
+            .add_systems(
+                Update,
+                apply_pending_load
+                    .run_if(in_state(AppState::Game))
+                    .run_if(resource_exists::<PendingLoad>),
+            );
+    }
+}
+
+#[derive(Event)]
+pub struct SaveGame;
+
+#[derive(Event)]
+pub struct LoadGame;
+
+/// Everything needed to continue a run. Only what can't be derived from the rest is stored,
+/// timers like tower cooldowns and enemy attacks start over when loading
+#[derive(Serialize, Deserialize, Clone)]
+struct SaveFile {
+    /// Loading is rejected if the board size doesn't match the current one
+    grid: GridConfig,
+    /// Loading is rejected if a different level, or none at all, is being played
+    #[serde(default)]
+    level: Option<String>,
+    /// Replaces the difficulty picked in the menu when loading
+    #[serde(default)]
+    difficulty: Difficulty,
+    currency: i32,
+    stats: SavedStatistics,
+    waves: SavedWaves,
+    goals: Vec<SavedGoal>,
+    towers: Vec<SavedTower>,
+    enemies: Vec<SavedEnemy>,
+    spawners: Vec<SavedSpawner>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct SavedStatistics {
+    enemies_killed: usize,
+    /// Seconds played
+    time: f32,
+    money_earned: i32,
+    money_spend: i32,
+    #[serde(default)]
+    towers_built: usize,
+    despawned_no_path: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct SavedTower {
+    variant: TowerType,
+    orientation: Orientation,
+    #[serde(default)]
+    targeting: TargetingMode,
+    level: u8,
+    origin: GridPos,
+    health: isize,
+    max_health: isize,
+}
+
+/// Goals are told apart by their tiles, the level places them at the same spots again
+#[derive(Serialize, Deserialize, Clone)]
+struct SavedGoal {
+    /// Bottom left tile of the goal
+    tile: GridPos,
+    health: isize,
+    leaks: usize,
+}
+
+/// Enemies continue from the tile they were standing on, without their rage
+#[derive(Serialize, Deserialize, Clone)]
+struct SavedEnemy {
+    variant: EnemyType,
+    tile: GridPos,
+    health: isize,
+    max_health: isize,
+}
+
+/// A save read from disk, waiting for a freshly entered game to be restored into
+#[derive(Resource)]
+struct PendingLoad(SaveFile);
+
+/// `roadblock-td/{file}` in the config directory of the OS
+pub fn config_path(file: &str) -> Option<PathBuf> {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .or_else(|| std::env::var_os("APPDATA"))
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config"))
+        })?;
+    Some(config_dir.join("roadblock-td").join(file))
+}
+
+fn save_path() -> Option<PathBuf> {
+    config_path("save.ron")
+}
+
+fn write_save_event(mut events: EventWriter<SaveGame>) {
+    events.write(SaveGame);
+}
+
+fn write_load_event(mut events: EventWriter<LoadGame>) {
+    events.write(LoadGame);
+}
+
+/// Money, statistics and wave of the running game, written to and restored from the save
+#[derive(SystemParam)]
+struct Progress<'w> {
+    currency: ResMut<'w, Currency>,
+    stats: ResMut<'w, GameStatistics>,
+    wave_info: ResMut<'w, WaveInfo>,
+    wave_state: Res<'w, State<WaveState>>,
+    next_wave_state: ResMut<'w, NextState<WaveState>>,
+}
+
+/// Piped into `save_game`, which writes the collected save to disk
+fn collect_save(
+    mut events: EventReader<SaveGame>,
+    grid: Res<Grid>,
+    progress: Progress,
+    difficulty: Res<Difficulty>,
+    level: Option<Res<Level>>,
+    goals: Query<(Entity, &Health, &GoalLeaks), With<EnemyGoal>>,
+    towers: Query<(Entity, &Tower, &Health)>,
+    enemies: Query<(&Enemy, &Health)>,
+    spawners: Query<(&EnemySpawn, Option<&SpawnQueue>)>,
+) -> SaveFile {
+    events.clear();
+    SaveFile {
+        grid: grid.config(),
+        level: level.map(|level| level.name.clone()),
+        difficulty: *difficulty,
+        currency: **progress.currency,
+        stats: SavedStatistics {
+            enemies_killed: progress.stats.enemies_killed,
+            time: progress.stats.time.elapsed_secs(),
+            money_earned: progress.stats.money_earned,
+            money_spend: progress.stats.money_spend,
+            towers_built: progress.stats.towers_built,
+            despawned_no_path: progress.stats.despawned_no_path,
+        },
+        waves: progress.wave_info.save(*progress.wave_state.get()),
+        goals: goals
+            .iter()
+            .filter_map(|(entity, health, leaks)| {
+                Some(SavedGoal {
+                    tile: *grid
+                        .enemy_goals
+                        .iter()
+                        .filter(|(_, goal)| **goal == entity)
+                        .map(|(tile, _)| tile)
+                        .min_by_key(|tile| (tile.row, tile.col))?,
+                    health: health.current,
+                    leaks: **leaks,
+                })
+            })
+            .collect(),
+        towers: towers
+            .iter()
+            .filter_map(|(entity, tower, health)| {
+                Some(SavedTower {
+                    variant: tower.variant,
+                    orientation: tower.orientation,
+                    targeting: tower.targeting,
+                    level: tower.level,
+                    origin: *grid.tower_origins.get(&entity)?,
+                    health: health.current,
+                    max_health: health.max,
+                })
+            })
+            .collect(),
+        enemies: enemies
+            .iter()
+            .map(|(enemy, health)| SavedEnemy {
+                variant: **enemy,
+                tile: enemy.current,
+                health: health.current,
+                max_health: health.max,
+            })
+            .collect(),
+        spawners: spawners
+            .iter()
+            .map(|(spawner, queue)| spawner.save(queue))
+            .collect(),
+    }
+}
+
+fn save_game(In(save): In<SaveFile>) {
+    let Some(path) = save_path() else {
+        warn!("Couldn't find a config directory to save the game to");
+        return;
+    };
+    let written = ron::ser::to_string_pretty(&save, default())
+        .map_err(|e| e.to_string())
+        .and_then(|content| {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+            }
+            std::fs::write(&path, content).map_err(|e| e.to_string())
+        });
+    match written {
+        Ok(()) => info!("Saved the game to {path:?}"),
+        Err(e) => warn!("Failed to save the game to {path:?}: {e}"),
+    }
+}
+
+/// Reads and checks the save. Loading during a game goes through the menu, so the current
+/// game gets cleared like it would when quitting
+fn load_game(
+    mut commands: Commands,
+    mut events: EventReader<LoadGame>,
+    config: Res<GridConfig>,
+    level: Option<Res<Level>>,
+    state: Res<State<AppState>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    events.clear();
+    let Some(path) = save_path() else {
+        warn!("Couldn't find a config directory to load the game from");
+        return;
+    };
+    let save = match std::fs::read_to_string(&path) {
+        Ok(content) => match ron::from_str::<SaveFile>(&content) {
+            Ok(save) => save,
+            Err(e) => {
+                warn!("Failed to parse the save file {path:?}: {e}");
+                return;
+            }
+        },
+        Err(e) => {
+            warn!("Failed to read the save file {path:?}: {e}");
+            return;
+        }
+    };
+    if save.grid != *config {
+        warn!(
+            "The save is for a {}x{} grid, but the current one is {}x{}. Not loading it",
+            save.grid.rows, save.grid.columns, config.rows, config.columns
+        );
+        return;
+    }
+    let level = level.map(|level| level.name.clone());
+    if save.level != level {
+        warn!(
+            "The save is for the level {}, but the current one is {}. Not loading it",
+            save.level.as_deref().unwrap_or("<none>"),
+            level.as_deref().unwrap_or("<none>")
+        );
+        return;
+    }
+
+    commands.insert_resource(PendingLoad(save));
+    match state.get() {
+        AppState::Game => next_state.set(AppState::Menu),
+        _ => next_state.set(AppState::Game),
+    }
+}
+
+fn continue_to_game(mut next_state: ResMut<NextState<AppState>>) {
+    next_state.set(AppState::Game);
+}
+
+/// Runs once the new game has set up its grid, goal and resources
+fn apply_pending_load(
+    mut commands: Commands,
+    pending: Res<PendingLoad>,
+    mut grid: ResMut<Grid>,
+    asset_server: Res<AssetServer>,
+    mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+    waves: Res<Waves>,
+    mut progress: Progress,
+    mut goals: Query<(&mut Health, &mut GoalLeaks), With<EnemyGoal>>,
+    mut path_events: EventWriter<PathChangedEvent>,
+) {
+    let save = pending.0.clone();
+    commands.remove_resource::<PendingLoad>();
+    let config = grid.config();
+    commands.insert_resource(save.difficulty);
+
+    let mut blocked = vec![];
+    for saved in save.towers {
+        let mut tower = Tower::new(saved.variant, saved.orientation);
+        tower.targeting = saved.targeting;
+        tower.level = saved.level.clamp(1, MAX_TOWER_LEVEL);
+        let (entity, tiles) = spawn_tower(&mut commands, &tower, saved.origin, &mut grid);
+        commands
+            .entity(entity)
+            .entry::<Health>()
+            .and_modify(move |mut health| {
+                health.current = saved.health;
+                health.max = saved.max_health;
+            });
+        blocked.extend(tiles);
+    }
+    path_events.write(PathChangedEvent::now_blocked(blocked));
+
+    for saved in save.spawners {
+        restore_spawner(
+            &mut commands,
+            saved,
+            waves.spawn_mode,
+            &mut grid,
+            &asset_server,
+            &config,
+        );
+    }
+
+    for saved in save.enemies {
+        spawn_enemy(
+            &mut commands,
+            saved.variant,
+            saved.tile,
+            1.,
+            save.difficulty,
+            &asset_server,
+            &mut layouts,
+            &config,
+        )
+        .entry::<Health>()
+        .and_modify(move |mut health| {
+            health.current = saved.health;
+            health.max = saved.max_health;
+        });
+    }
+
+    for saved in save.goals {
+        let Some((mut health, mut leaks)) = grid
+            .enemy_goals
+            .get(&saved.tile)
+            .and_then(|entity| goals.get_mut(*entity).ok())
+        else {
+            warn!("The save has a goal at {}, but there is none", saved.tile);
+            continue;
+        };
+        health.current = saved.health;
+        leaks.0 = saved.leaks;
+    }
+    **progress.currency = save.currency;
+    *progress.stats = GameStatistics {
+        enemies_killed: save.stats.enemies_killed,
+        money_earned: save.stats.money_earned,
+        money_spend: save.stats.money_spend,
+        towers_built: save.stats.towers_built,
+        despawned_no_path: save.stats.despawned_no_path,
+        ..default()
+    };
+    progress
+        .stats
+        .time
+        .set_elapsed(Duration::from_secs_f32(save.stats.time));
+    let wave_state = progress.wave_info.restore(save.waves);
+    progress.next_wave_state.set(wave_state);
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use crate::test_app;
+
+    use super::*;
+
+    /// A game on the small grid with a goal at every tile of `goals`, enough for saving and
+    /// loading
+    fn game_app(goals: &[GridPos]) -> App {
+        let mut app = test_app();
+        app.insert_resource(Waves::built_in(None))
+            .insert_resource(State::new(WaveState::Starting))
+            .init_resource::<NextState<WaveState>>()
+            .add_event::<SaveGame>();
+        for tile in goals {
+            let goal = app
+                .world_mut()
+                .spawn((
+                    EnemyGoal::Heart,
+                    Health::new(200, Vec2::ZERO),
+                    GoalLeaks::default(),
+                ))
+                .id();
+            app.world_mut()
+                .resource_mut::<Grid>()
+                .enemy_goals
+                .insert(*tile, goal);
+        }
+        app
+    }
+
+    /// Variant, origin and health of every tower, sorted by origin
+    fn towers(app: &mut App) -> Vec<(TowerType, GridPos, isize)> {
+        let world = app.world_mut();
+        let origins = world.resource::<Grid>().tower_origins.clone();
+        let mut towers: Vec<_> = world
+            .query::<(Entity, &Tower, &Health)>()
+            .iter(world)
+            .map(|(entity, tower, health)| {
+                (
+                    tower.variant,
+                    *origins.get(&entity).unwrap(),
+                    health.current,
+                )
+            })
+            .collect();
+        towers.sort_by_key(|(_, origin, _)| (origin.row, origin.col));
+        towers
+    }
+
+    fn goal_at(app: &App, tile: GridPos) -> Entity {
+        *app.world()
+            .resource::<Grid>()
+            .enemy_goals
+            .get(&tile)
+            .unwrap()
+    }
+
+    /// Health and leaks of the goal at `tile`
+    fn goal(app: &App, tile: GridPos) -> (isize, usize) {
+        let goal = app.world().entity(goal_at(app, tile));
+        (
+            goal.get::<Health>().unwrap().current,
+            **goal.get::<GoalLeaks>().unwrap(),
+        )
+    }
+
+    #[test]
+    fn loading_a_save_restores_towers_enemies_and_goals() {
+        let goals = [GridPos::new(5, 30), GridPos::new(15, 30)];
+        let mut app = game_app(&goals);
+        app.world_mut()
+            .run_system_once(|mut commands: Commands, mut grid: ResMut<Grid>| {
+                for (variant, origin) in [
+                    (TowerType::Canon, GridPos::new(2, 2)),
+                    (TowerType::Bunker, GridPos::new(10, 10)),
+                ] {
+                    let tower = Tower::new(variant, Orientation::Up);
+                    spawn_tower(&mut commands, &tower, origin, &mut grid);
+                }
+            })
+            .unwrap();
+        let mut damaged = app.world_mut().query::<&mut Health>();
+        for mut health in damaged.iter_mut(app.world_mut()) {
+            health.current -= 20;
+        }
+        app.world_mut().spawn((
+            Enemy::new(GridPos::new(8, 4), EnemyType::Orc, Difficulty::Normal),
+            Health::new(30, Vec2::ZERO),
+        ));
+        let entity = goal_at(&app, goals[1]);
+        let mut goal = app.world_mut().entity_mut(entity);
+        goal.insert(GoalLeaks(3));
+        goal.get_mut::<Health>().unwrap().current = 120;
+
+        let save = app.world_mut().run_system_once(collect_save).unwrap();
+        let save = ron::ser::to_string_pretty(&save, default()).unwrap();
+        let save: SaveFile = ron::from_str(&save).unwrap();
+
+        let mut loaded = game_app(&goals);
+        loaded.insert_resource(PendingLoad(save));
+        loaded
+            .world_mut()
+            .run_system_once(apply_pending_load)
+            .unwrap();
+
+        assert_eq!(towers(&mut loaded), towers(&mut app));
+        assert_eq!(
+            loaded.world().resource::<Grid>().towers.len(),
+            app.world().resource::<Grid>().towers.len()
+        );
+        let world = loaded.world_mut();
+        let enemies: Vec<_> = world
+            .query::<(&Enemy, &Health)>()
+            .iter(world)
+            .map(|(enemy, health)| (**enemy, enemy.current, health.current))
+            .collect();
+        assert_eq!(enemies, [(EnemyType::Orc, GridPos::new(8, 4), 30)]);
+        // Every goal keeps its own health
+        assert_eq!(goal(&loaded, goals[0]), (180, 0));
+        assert_eq!(goal(&loaded, goals[1]), (120, 3));
+    }
+}
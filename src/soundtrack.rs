@@ -1,8 +1,15 @@
 use bevy::{audio::Volume, prelude::*};
 
-use crate::{Settings, app_state::AppState};
+use crate::{
+    Settings,
+    app_state::{AppState, GameState},
+    enemy::EnemyKilled,
+    tower::{TowerDestroyed, TowerFired},
+};
 
 const FADE_TIME: f32 = 2.0;
+/// Upper limit of sound effects playing at once, further ones are skipped
+const MAX_SFX: usize = 16;
 
 pub struct SoundtrackPlugin;
 
@@ -11,6 +18,8 @@ impl Plugin for SoundtrackPlugin {
         app.register_type::<SoundtrackHandles>()
             .register_type::<FadeIn>()
             .register_type::<FadeOut>()
+            .register_type::<Sfx>()
+            .register_type::<Soundtrack>()
             .register_type::<AudioPlayer>()
             .add_event::<SoundtrackToggled>()
             .add_systems(Startup, setup)
@@ -20,8 +29,15 @@ impl Plugin for SoundtrackPlugin {
                     fade_in,
                     fade_out,
                     handle_soundtrack_toggle.run_if(on_event::<SoundtrackToggled>),
+                    apply_music_volume.run_if(resource_changed::<Settings>),
                 ),
             )
+            .add_systems(
+                Update,
+                play_game_sfx
+                    .run_if(in_state(GameState::Running))
+                    .run_if(|settings: Res<Settings>| settings.sfx_enabled),
+            )
             .add_systems(OnEnter(AppState::Menu), play_menu_soundtrack)
             .add_systems(OnEnter(AppState::Game), play_game_soundtrack);
     }
@@ -41,9 +57,14 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
     });
 }
 
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct Soundtrack;
+
 fn spawn_track(commands: &mut Commands, track: Handle<AudioSource>) {
     commands.spawn((
         Name::new("Soundtrack"),
+        Soundtrack,
         AudioPlayer(track),
         PlaybackSettings {
             mode: bevy::audio::PlaybackMode::Loop,
@@ -56,7 +77,7 @@ fn spawn_track(commands: &mut Commands, track: Handle<AudioSource>) {
 
 fn play_menu_soundtrack(
     mut commands: Commands,
-    tracks: Query<Entity, With<AudioSink>>,
+    tracks: Query<Entity, With<Soundtrack>>,
     track_handles: Res<SoundtrackHandles>,
     settings: Res<Settings>,
 ) {
@@ -70,7 +91,7 @@ fn play_menu_soundtrack(
 
 fn play_game_soundtrack(
     mut commands: Commands,
-    tracks: Query<Entity, With<AudioSink>>,
+    tracks: Query<Entity, With<Soundtrack>>,
     track_handles: Res<SoundtrackHandles>,
     settings: Res<Settings>,
 ) {
@@ -89,25 +110,40 @@ struct FadeIn;
 #[reflect(Component)]
 struct FadeOut;
 
+/// Fades towards the music volume of the settings
 fn fade_in(
     mut commands: Commands,
     mut audio_sink: Query<(&mut AudioSink, Entity), With<FadeIn>>,
-    time: Res<Time>,
+    time: Res<Time<Real>>,
+    settings: Res<Settings>,
 ) {
     for (mut audio, entity) in audio_sink.iter_mut() {
         let volume = audio.volume().to_linear();
         audio.set_volume(Volume::Linear(volume + time.delta_secs() / FADE_TIME));
-        if audio.volume().to_linear() >= 1.0 {
-            audio.set_volume(Volume::Linear(1.));
+        if audio.volume().to_linear() >= settings.music_volume {
+            audio.set_volume(Volume::Linear(settings.music_volume));
             commands.entity(entity).remove::<FadeIn>();
         }
     }
 }
 
+/// Fading tracks are left alone, `fade_in` already heads for the new volume
+fn apply_music_volume(
+    mut tracks: Query<
+        &mut AudioSink,
+        (With<Soundtrack>, Without<FadeIn>, Without<FadeOut>),
+    >,
+    settings: Res<Settings>,
+) {
+    for mut audio in &mut tracks {
+        audio.set_volume(Volume::Linear(settings.music_volume));
+    }
+}
+
 fn fade_out(
     mut commands: Commands,
     mut audio_sink: Query<(&mut AudioSink, Entity), With<FadeOut>>,
-    time: Res<Time>,
+    time: Res<Time<Real>>,
 ) {
     for (mut audio, entity) in audio_sink.iter_mut() {
         let volume = audio.volume().to_linear();
@@ -123,7 +159,7 @@ pub struct SoundtrackToggled;
 
 fn handle_soundtrack_toggle(
     mut commands: Commands,
-    tracks: Query<Entity, With<AudioSink>>,
+    tracks: Query<Entity, With<Soundtrack>>,
     track_handles: Res<SoundtrackHandles>,
     app_state: Res<State<AppState>>,
     settings: Res<Settings>,
@@ -143,3 +179,70 @@ fn handle_soundtrack_toggle(
         }
     }
 }
+
+/// A one-shot sound, despawned once it finished playing
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Sfx;
+
+/// Plays `sound` once if sound effects are enabled, scaled by the sfx volume. `playing` is
+/// the amount of `Sfx` currently alive and gets increased, nothing is played once it
+/// reaches `MAX_SFX`
+pub fn play_sfx(
+    commands: &mut Commands,
+    settings: &Settings,
+    playing: &mut usize,
+    sound: Handle<AudioSource>,
+    mut playback: PlaybackSettings,
+) {
+    if !settings.sfx_enabled || *playing >= MAX_SFX {
+        return;
+    }
+    *playing += 1;
+    playback.volume = Volume::Linear(playback.volume.to_linear() * settings.sfx_volume);
+    commands.spawn((Name::new("Sfx"), Sfx, AudioPlayer::new(sound), playback));
+}
+
+/// At most one sound per kind of event and frame, so a whole row of towers firing at once
+/// sounds like a single shot
+fn play_game_sfx(
+    mut commands: Commands,
+    mut fired: EventReader<TowerFired>,
+    mut killed: EventReader<EnemyKilled>,
+    mut destroyed: EventReader<TowerDestroyed>,
+    sfx: Query<(), With<Sfx>>,
+    settings: Res<Settings>,
+    asset_server: Res<AssetServer>,
+) {
+    let mut playing = sfx.iter().count();
+    let sounds = [
+        (
+            fired.read().count() > 0,
+            "sfx/Toom Click.ogg",
+            PlaybackSettings::DESPAWN
+                .with_speed(1.6)
+                .with_volume(Volume::Linear(0.4)),
+        ),
+        (
+            killed.read().count() > 0,
+            "sfx/Cloud Click.ogg",
+            PlaybackSettings::DESPAWN.with_speed(0.75),
+        ),
+        (
+            destroyed.read().count() > 0,
+            "sfx/Toom Click.ogg",
+            PlaybackSettings::DESPAWN.with_speed(0.5),
+        ),
+    ];
+    for (happened, path, playback) in sounds {
+        if happened {
+            play_sfx(
+                &mut commands,
+                &settings,
+                &mut playing,
+                asset_server.load(path),
+                playback,
+            );
+        }
+    }
+}
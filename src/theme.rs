@@ -0,0 +1,142 @@
+use bevy::{platform::collections::HashMap, prelude::*};
+
+use crate::tower::TowerType;
+
+pub struct ThemePlugin;
+
+impl Plugin for ThemePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Theme>()
+            .register_type::<ThemeColor>()
+            .init_resource::<Theme>()
+            // After all Update systems, so newly spawned sprites never show up uncolored
+            .add_systems(PostUpdate, apply_theme);
+    }
+}
+
+#[derive(Reflect, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum Palette {
+    #[default]
+    Classic,
+    /// Based on the Okabe-Ito palette, readable with common color vision deficiencies
+    Colorblind,
+}
+
+/// Colors used for everything that doesn't have a real sprite yet
+#[derive(Resource, Reflect, Clone)]
+#[reflect(Resource)]
+pub struct Theme {
+    pub palette: Palette,
+    towers: HashMap<TowerType, Color>,
+    tower_offline: Color,
+    pub preview_valid: Color,
+    pub preview_invalid: Color,
+    /// The tiles are free, but the tower would cut off a spawner from every goal
+    pub preview_blocks_path: Color,
+    goal: Color,
+    spawner: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::new(Palette::default())
+    }
+}
+
+impl Theme {
+    pub const VARIANTS: &[&str] = &["Theme: Classic", "Theme: Colorblind"];
+
+    pub fn new(palette: Palette) -> Self {
+        match palette {
+            Palette::Classic => Self {
+                palette,
+                towers: HashMap::from_iter([
+                    (TowerType::Wall, Color::srgb(0.0, 0.5, 1.0)),
+                    (TowerType::SpikedWall, Color::srgb(0.0, 0.5, 1.0)),
+                    (TowerType::Canon, Color::srgb(0.0, 0.5, 1.0)),
+                    (TowerType::Mage, Color::srgb(0.6, 0.2, 1.0)),
+                    (TowerType::Generator, Color::srgb(1.0, 0.8, 0.0)),
+                    (TowerType::Watchtower, Color::srgb(0.2, 0.8, 0.4)),
+                    (TowerType::Bunker, Color::srgb(0.0, 0.5, 1.0)),
+                    (TowerType::Mortar, Color::srgb(0.9, 0.3, 0.1)),
+                    (TowerType::Frost, Color::srgb(0.6, 0.9, 1.0)),
+                    (TowerType::Beacon, Color::srgb(1.0, 0.55, 0.75)),
+                    (TowerType::Laser, Color::srgb(1.0, 0.2, 0.2)),
+                ]),
+                tower_offline: Color::srgb(0.4, 0.4, 0.4),
+                preview_valid: Color::srgb(0.0, 1.0, 0.0),
+                preview_invalid: Color::srgb(1.0, 0.0, 0.0),
+                preview_blocks_path: Color::srgb(1.0, 0.0, 0.8),
+                goal: Color::WHITE,
+                spawner: Color::WHITE,
+            },
+            Palette::Colorblind => Self {
+                palette,
+                towers: HashMap::from_iter([
+                    (TowerType::Wall, Color::srgb(0.0, 0.45, 0.7)),
+                    (TowerType::SpikedWall, Color::srgb(0.34, 0.71, 0.91)),
+                    (TowerType::Canon, Color::srgb(0.0, 0.62, 0.45)),
+                    (TowerType::Mage, Color::srgb(0.8, 0.47, 0.65)),
+                    (TowerType::Generator, Color::srgb(0.94, 0.89, 0.26)),
+                    (TowerType::Watchtower, Color::srgb(0.9, 0.6, 0.0)),
+                    (TowerType::Bunker, Color::srgb(0.0, 0.45, 0.7)),
+                    (TowerType::Mortar, Color::srgb(0.15, 0.15, 0.15)),
+                    (TowerType::Frost, Color::srgb(0.34, 0.71, 0.91)),
+                    (TowerType::Beacon, Color::srgb(0.8, 0.47, 0.65)),
+                    (TowerType::Laser, Color::srgb(0.84, 0.37, 0.0)),
+                ]),
+                tower_offline: Color::srgb(0.4, 0.4, 0.4),
+                preview_valid: Color::srgb(0.34, 0.71, 0.91),
+                preview_invalid: Color::srgb(0.84, 0.37, 0.0),
+                preview_blocks_path: Color::srgb(0.94, 0.89, 0.26),
+                goal: Color::WHITE,
+                spawner: Color::WHITE,
+            },
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self.palette {
+            Palette::Classic => Self::VARIANTS[0],
+            Palette::Colorblind => Self::VARIANTS[1],
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        Self::new(match self.palette {
+            Palette::Classic => Palette::Colorblind,
+            Palette::Colorblind => Palette::Classic,
+        })
+    }
+
+    pub fn tower(&self, tower: TowerType) -> Color {
+        self.towers.get(&tower).copied().unwrap_or(Color::WHITE)
+    }
+
+    pub fn color(&self, source: ThemeColor) -> Color {
+        match source {
+            ThemeColor::Tower(tower) => self.tower(tower),
+            ThemeColor::TowerOffline => self.tower_offline,
+            ThemeColor::Goal => self.goal,
+            ThemeColor::Spawner => self.spawner,
+        }
+    }
+}
+
+/// Where the color of a sprite comes from, so it can be updated when the theme changes
+#[derive(Component, Reflect, Clone, Copy, PartialEq, Debug)]
+#[reflect(Component)]
+pub enum ThemeColor {
+    Tower(TowerType),
+    TowerOffline,
+    Goal,
+    Spawner,
+}
+
+fn apply_theme(theme: Res<Theme>, mut sprites: Query<(Ref<ThemeColor>, &mut Sprite)>) {
+    for (source, mut sprite) in &mut sprites {
+        if theme.is_changed() || source.is_changed() {
+            sprite.color = theme.color(*source);
+        }
+    }
+}
@@ -1,23 +1,28 @@
-use bevy::{color::palettes::css::RED, prelude::*};
+use bevy::{color::palettes::css::RED, ecs::system::SystemParam, prelude::*};
 
 use crate::{
+    Settings,
     app_state::GameState,
-    enemy::{Enemy, EnemyGoal, PathChangedEvent},
-    game_loop::{Currency, GameStatistics},
-    grid::{Grid, TILE_SIZE},
+    enemy::{
+        Enemy, EnemyDamaged, EnemyGoal, EnemyKilled, EnemyPath, Knockback,
+        PathChangedEvent, Shield, Slowed,
+    },
+    game_loop::Wallet,
+    grid::{Grid, TILE_SIZE, VisibleTiles, world_to_grid_coords, world_to_tiles},
     health::Health,
 };
 
-use super::Tower;
+use super::{DamageType, TargetingMode, Tower, aura::AuraBuff};
 
 pub struct TowerAttackPlugin;
 
 impl Plugin for TowerAttackPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<Projectile>();
+        app.add_event::<TowerFired>();
         app.add_systems(
             Update,
-            (shoot, move_projectile, projectile_damage)
+            (shoot, move_projectile, projectile_damage, slow_enemies)
                 .run_if(in_state(GameState::Running)),
         );
     }
@@ -29,75 +34,163 @@ impl Plugin for TowerAttackPlugin {
 pub struct Projectile {
     speed: f32,
     damage: isize,
+    damage_type: DamageType,
     target: Entity,
+    /// See `TowerType::splash_radius`
+    splash: f32,
+}
+
+/// Seconds a slow lasts after the enemy was last pulsed by a slowing tower
+const SLOW_DURATION: f32 = 1.5;
+
+/// Fired whenever a tower shoots a projectile
+#[derive(Event)]
+pub struct TowerFired {
+    pub tower: Entity,
+    /// Where the projectile left the tower
+    pub muzzle: Vec3,
+    /// Normalized direction from the muzzle towards the target
+    pub direction: Vec2,
+    pub target: Entity,
 }
 
 fn shoot(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
-    mut tower: Query<(&mut Tower, &Transform)>,
-    goal: Single<&Transform, With<EnemyGoal>>,
-    enemy: Query<(&Transform, Entity), With<Enemy>>,
+    mut tower: Query<(Entity, &mut Tower, &Transform, Option<&AuraBuff>)>,
+    goals: Query<&Transform, With<EnemyGoal>>,
+    enemy: Query<(&Transform, Entity, &Enemy, Option<&EnemyPath>, &Health)>,
     time: Res<Time>,
+    settings: Res<Settings>,
+    visible: Option<Res<VisibleTiles>>,
+    mut fired: EventWriter<TowerFired>,
 ) {
-    for (mut tower, tower_transform) in tower.iter_mut() {
-        tower.attack_timer.tick(time.delta());
-        if !tower.attack_timer.finished() {
+    // Fog is purely visual unless explicitly configured otherwise
+    let visible = visible.filter(|_| settings.fog_targeting);
+
+    for (tower_entity, mut tower, tower_transform, buff) in tower.iter_mut() {
+        // Slowing towers and beams don't fire projectiles, see `slow_enemies` and `laser`
+        if !tower.powered || tower.slow_factor().is_some() || tower.is_beam() {
             continue;
         }
 
-        let mut closest_dist = None;
-        let mut closest_enemy = None;
+        // Ready towers keep waiting for a target instead of cycling through cooldowns
+        if !tower.attack_timer.finished() {
+            let fire_rate = buff.map_or(1., |buff| buff.fire_rate);
+            tower.attack_timer.tick(time.delta().mul_f32(fire_rate));
+            if !tower.attack_timer.finished() {
+                continue;
+            }
+        }
 
-        for (enemy_transform, entity) in enemy.iter() {
-            let goal_dist = goal
-                .translation
-                .distance_squared(enemy_transform.translation);
+        // Lowest score wins
+        let mut best_score = None;
+        let mut best_enemy = None;
 
-            let tower_dist = tower_transform
-                .translation
-                .distance(enemy_transform.translation);
+        for (enemy_transform, entity, enemy, path, health) in enemy.iter() {
+            // Don't waste shots on enemies this tower can't hurt
+            if enemy.immune_to(tower.damage_type()) {
+                continue;
+            }
+            if visible
+                .as_ref()
+                .is_some_and(|v| !v.contains(&enemy.current))
+            {
+                continue;
+            }
 
-            if tower_dist > tower.range() {
+            if !tower.covers(tower_transform.translation, enemy_transform.translation) {
                 continue;
             }
 
-            if closest_dist.is_none_or(|x| x > goal_dist) {
-                closest_dist = Some(goal_dist);
-                closest_enemy = Some(entity);
+            // Enemies still waiting for a path count as being at the very start
+            let steps_left = path.map_or(f32::MAX, |path| path.steps.len() as f32);
+            let score = match tower.targeting {
+                TargetingMode::First => steps_left,
+                TargetingMode::Last => -steps_left,
+                TargetingMode::Closest => goals
+                    .iter()
+                    .map(|goal| {
+                        goal.translation
+                            .distance_squared(enemy_transform.translation)
+                    })
+                    .fold(f32::MAX, f32::min),
+                TargetingMode::Strongest => -(health.current as f32),
+            };
+
+            if best_score.is_none_or(|x| x > score) {
+                best_score = Some(score);
+                best_enemy = Some((entity, enemy_transform.translation));
             }
         }
 
-        if let Some(closest) = closest_enemy {
+        if let Some((target_entity, target)) = best_enemy {
             tower.attack_timer.reset();
+            let muzzle = Vec3 {
+                //will need to handle the rotation here at some point but I am lazy and the only attacking tower we have rn is symmetrical
+                x: tower_transform.translation.x
+                    + tower.offset().0 as f32 * TILE_SIZE
+                    + TILE_SIZE * 0.5,
+                y: tower_transform.translation.y
+                    + tower.offset().1 as f32 * TILE_SIZE
+                    + TILE_SIZE * 0.5,
+                z: 3.0,
+            };
+            fired.write(TowerFired {
+                tower: tower_entity,
+                muzzle,
+                direction: (target - muzzle).truncate().normalize_or_zero(),
+                target: target_entity,
+            });
             commands.spawn((
                 Name::new("Projectile"),
                 Mesh2d(meshes.add(Circle::new(5.0))),
                 MeshMaterial2d(materials.add(ColorMaterial::from_color(RED))),
                 Projectile {
                     speed: 500.0,
-                    damage: tower.strength(),
-                    target: closest,
-                },
-                Transform {
-                    translation: Vec3 {
-                        //will need to handle the rotation here at some point but I am lazy and the only attacking tower we have rn is symmetrical
-                        x: tower_transform.translation.x
-                            + tower.offset().0 as f32 * TILE_SIZE
-                            + TILE_SIZE * 0.5,
-                        y: tower_transform.translation.y
-                            + tower.offset().1 as f32 * TILE_SIZE
-                            + TILE_SIZE * 0.5,
-                        z: 3.0,
-                    },
-                    ..default()
+                    damage: tower.buffed_strength(buff),
+                    damage_type: tower.damage_type(),
+                    target: target_entity,
+                    splash: tower.splash_radius(),
                 },
+                Transform::from_translation(muzzle),
             ));
         }
     }
 }
 
+/// Slowing towers pulse on their fire cooldown, refreshing the slow of every enemy in range
+fn slow_enemies(
+    mut commands: Commands,
+    mut towers: Query<(&mut Tower, &Transform)>,
+    enemies: Query<(Entity, &Transform), With<Enemy>>,
+    time: Res<Time>,
+) {
+    for (mut tower, tower_transform) in &mut towers {
+        let Some(factor) = tower.slow_factor() else {
+            continue;
+        };
+        if !tower.powered {
+            continue;
+        }
+        tower.attack_timer.tick(time.delta());
+        if !tower.attack_timer.finished() {
+            continue;
+        }
+        tower.attack_timer.reset();
+
+        for (entity, enemy_transform) in &enemies {
+            if tower.covers(tower_transform.translation, enemy_transform.translation) {
+                // The enemy might have been killed earlier this frame
+                commands
+                    .entity(entity)
+                    .try_insert(Slowed::new(factor, SLOW_DURATION));
+            }
+        }
+    }
+}
+
 fn move_projectile(
     mut commands: Commands,
     mut projectile: Query<(&mut Transform, &Projectile, Entity)>,
@@ -110,47 +203,124 @@ fn move_projectile(
                 .translation
                 .move_towards(target.translation, projectile.speed * time.delta_secs());
         } else {
-            warn!("target enemy no longer exists, despawning projectile");
+            debug!("target enemy no longer exists, despawning projectile");
             commands.entity(entity).despawn();
         }
     }
 }
 
+/// Bookkeeping shared by every way a tower can hurt an enemy
+#[derive(SystemParam)]
+pub struct EnemyHits<'w, 's> {
+    commands: Commands<'w, 's>,
+    wallet: Wallet<'w>,
+    /// Public so the tower systems can read it, a second `Grid` param would conflict
+    pub grid: ResMut<'w, Grid>,
+    path_change: EventWriter<'w, PathChangedEvent>,
+    killed: EventWriter<'w, EnemyKilled>,
+    damaged: EventWriter<'w, EnemyDamaged>,
+}
+
+impl EnemyHits<'_, '_> {
+    /// Deals `damage` after the resistances of the enemy. A killed enemy is despawned and
+    /// rewarded, and its tile counts towards the tiles enemies avoid
+    pub fn hit(
+        &mut self,
+        entity: Entity,
+        enemy: &Enemy,
+        health: &mut Health,
+        translation: Vec3,
+        damage: isize,
+        damage_type: DamageType,
+    ) {
+        // Killed earlier this frame, the despawn is still pending
+        if **health <= 0 {
+            return;
+        }
+        let damage = enemy.damage_taken(damage, damage_type);
+        **health -= damage;
+        self.damaged.write(EnemyDamaged {
+            damage,
+            translation,
+        });
+        if **health > 0 {
+            return;
+        }
+
+        self.commands.entity(entity).despawn();
+        self.killed.write(EnemyKilled {
+            variant: **enemy,
+            translation,
+        });
+        self.wallet.reward_kill(enemy.reward());
+        match self.grid.death_count.get_mut(&enemy.current) {
+            Some(count) => {
+                *count += 1;
+                self.path_change
+                    .write(PathChangedEvent::now_blocked(vec![enemy.current]));
+            }
+            None => {
+                self.grid.death_count.insert(enemy.current, 1);
+            }
+        }
+    }
+}
+
 pub fn projectile_damage(
     mut commands: Commands,
     projectile: Query<(&Transform, &Projectile, Entity)>,
-    mut enemy: Query<(&Transform, &mut Health, Entity, &Enemy)>,
-    mut stats: ResMut<GameStatistics>,
-    mut currency: ResMut<Currency>,
-    mut grid: ResMut<Grid>,
-    mut path_change: EventWriter<PathChangedEvent>,
+    mut enemy: Query<(&Transform, &mut Health, Entity, &Enemy, Option<&Shield>)>,
+    mut hits: EnemyHits,
 ) {
     for (projectile_transform, projectile, projectile_entity) in projectile.iter() {
-        for (enemy_transform, mut health, enemy_entity, enemy) in enemy.iter_mut() {
-            if projectile_transform
-                .translation
-                .distance(enemy_transform.translation)
-                < TILE_SIZE * 0.5
-            {
-                **health -= projectile.damage;
-                if **health <= 0 {
-                    commands.entity(enemy_entity).despawn();
-                    **currency += enemy.reward();
-                    stats.enemies_killed += 1;
-                    stats.money_earned += enemy.reward();
-                    match grid.death_count.get_mut(&enemy.current) {
-                        Some(count) => {
-                            *count += 1;
-                            path_change.write(PathChangedEvent::now_blocked(vec![
-                                enemy.current,
-                            ]));
-                        }
-                        None => {
-                            grid.death_count.insert(enemy.current, 1);
-                        }
-                    }
+        let impact = projectile_transform.translation;
+        let Some(hit) = enemy
+            .iter()
+            .find(|(enemy_transform, health, _, enemy, _)| {
+                ***health > 0
+                    && impact.distance(enemy_transform.translation) < TILE_SIZE * 0.5
+                    && !enemy.immune_to(projectile.damage_type)
+            })
+            .map(|(_, _, entity, _, _)| entity)
+        else {
+            continue;
+        };
+        commands.entity(projectile_entity).despawn();
+        // Splash is measured in tiles, from the tile of the impact to the tile of the enemy
+        let impact_tile = world_to_grid_coords(impact.truncate(), &hits.grid.config());
+        let splash = world_to_tiles(projectile.splash);
+
+        // Only enemies are queried, so splash damage never hurts towers
+        for (enemy_transform, mut health, enemy_entity, enemy, shield) in enemy.iter_mut()
+        {
+            let affected = match projectile.splash > 0. {
+                true => {
+                    impact_tile
+                        .is_some_and(|tile| tile.tile_distance(&enemy.current) <= splash)
+                        && !enemy.immune_to(projectile.damage_type)
                 }
-                commands.entity(projectile_entity).despawn();
+                false => enemy_entity == hit,
+            };
+            // The projectile fizzles if the shield came up while it was in flight
+            if !affected || Shield::blocks(shield) {
+                continue;
+            }
+
+            hits.hit(
+                enemy_entity,
+                enemy,
+                &mut health,
+                enemy_transform.translation,
+                projectile.damage,
+                projectile.damage_type,
+            );
+            // Explosions shove the survivors away from the impact
+            if **health > 0 && projectile.splash > 0. {
+                commands.entity(enemy_entity).try_insert(Knockback::new(
+                    impact,
+                    enemy,
+                    enemy_transform.translation,
+                ));
             }
         }
     }
@@ -0,0 +1,158 @@
+use bevy::{platform::collections::HashMap, prelude::*};
+
+use crate::{
+    app_state::AppState,
+    grid::{Grid, world_to_tiles},
+};
+
+use super::{Tower, power::update_power};
+
+pub struct TowerAuraPlugin;
+
+impl Plugin for TowerAuraPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<AuraBuff>().add_systems(
+            Update,
+            update_auras
+                .after(update_power)
+                .run_if(in_state(AppState::Game)),
+        );
+    }
+}
+
+/// Buff granted by a tower to the towers around it, see `TowerType::aura`
+#[derive(Clone, Copy, Debug)]
+pub struct Aura {
+    /// Towers with any tile this close to a tile of the aura's tower are affected
+    pub radius: f32,
+    /// Multiplier for the damage of every projectile
+    pub strength: f32,
+    /// Multiplier for how fast the attack timer runs
+    pub fire_rate: f32,
+}
+
+/// Strongest aura affecting this tower. Overlapping auras don't stack, every multiplier is
+/// taken from whichever aura grants the most
+#[derive(Reflect, Component, Clone, Copy, Debug)]
+#[reflect(Component)]
+pub struct AuraBuff {
+    pub strength: f32,
+    pub fire_rate: f32,
+}
+
+impl AuraBuff {
+    /// Adds `aura` to the buff, keeping the larger of both multipliers
+    fn with(buff: Option<Self>, aura: &Aura) -> Self {
+        match buff {
+            Some(buff) => AuraBuff {
+                strength: buff.strength.max(aura.strength),
+                fire_rate: buff.fire_rate.max(aura.fire_rate),
+            },
+            None => AuraBuff {
+                strength: aura.strength,
+                fire_rate: aura.fire_rate,
+            },
+        }
+    }
+}
+
+/// Only recomputed when towers are placed or removed, which is also the only time their
+/// power can change
+fn update_auras(
+    mut commands: Commands,
+    towers: Query<(Entity, &Tower)>,
+    new_towers: Query<(), Added<Tower>>,
+    mut removed: RemovedComponents<Tower>,
+    grid: Res<Grid>,
+) {
+    if new_towers.is_empty() && removed.read().count() == 0 {
+        return;
+    }
+
+    let mut buffs: HashMap<Entity, AuraBuff> = HashMap::new();
+    for (entity, tower) in &towers {
+        let Some(aura) = tower.aura().filter(|_| tower.powered) else {
+            continue;
+        };
+        let Some(origin) = grid.tower_origins.get(&entity) else {
+            continue;
+        };
+        for tile in tower.footprint(*origin) {
+            for (_, target) in grid.towers_in_radius(tile, world_to_tiles(aura.radius)) {
+                let buff = AuraBuff::with(buffs.get(&target).copied(), &aura);
+                buffs.insert(target, buff);
+            }
+        }
+    }
+
+    for (entity, tower) in &towers {
+        if !tower.receives_aura() {
+            continue;
+        }
+        // Towers despawned earlier this frame are still in the query
+        match buffs.get(&entity) {
+            Some(buff) => commands.entity(entity).try_insert(*buff),
+            None => commands.entity(entity).try_remove::<AuraBuff>(),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Orientation,
+        grid::{GridConfig, GridPos},
+        test_app,
+        tower::TowerType,
+    };
+
+    use super::*;
+
+    /// Buff of a Canon at (5, 5) with Beacons at `beacons`
+    fn canon_buff(beacons: &[GridPos]) -> (Tower, Option<AuraBuff>) {
+        let mut app = test_app();
+        app.add_systems(Update, update_auras);
+        let mut grid = Grid::new(GridConfig::SMALL);
+        let canon = Tower::new(TowerType::Canon, Orientation::Up);
+        let entity = app.world_mut().spawn(canon.clone()).id();
+        canon.fill_grid(&GridPos::new(5, 5), &mut grid, entity);
+        for origin in beacons {
+            let beacon = Tower::new(TowerType::Beacon, Orientation::Up);
+            let beacon_entity = app.world_mut().spawn(beacon.clone()).id();
+            beacon.fill_grid(origin, &mut grid, beacon_entity);
+        }
+        app.insert_resource(grid);
+        app.update();
+        (canon, app.world().get::<AuraBuff>(entity).copied())
+    }
+
+    #[test]
+    fn canon_next_to_a_beacon_hits_harder() {
+        let (canon, buff) = canon_buff(&[GridPos::new(5, 8)]);
+        assert!(canon.buffed_strength(buff.as_ref()) > canon.buffed_strength(None));
+
+        let (_, buff) = canon_buff(&[GridPos::new(15, 30)]);
+        assert!(buff.is_none());
+    }
+
+    #[test]
+    fn overlapping_auras_keep_the_larger_buff() {
+        let (_, buff) = canon_buff(&[GridPos::new(5, 8), GridPos::new(2, 5)]);
+        let aura = TowerType::Beacon.aura().unwrap();
+        let buff = buff.unwrap();
+        assert_eq!(buff.strength, aura.strength);
+        assert_eq!(buff.fire_rate, aura.fire_rate);
+
+        let strong = Aura {
+            strength: 1.5,
+            ..aura
+        };
+        let fast = Aura {
+            fire_rate: 2.,
+            ..aura
+        };
+        let buff = AuraBuff::with(Some(AuraBuff::with(None, &strong)), &fast);
+        assert_eq!(buff.strength, 1.5);
+        assert_eq!(buff.fire_rate, 2.);
+    }
+}
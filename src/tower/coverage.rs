@@ -0,0 +1,145 @@
+use bevy::{
+    asset::RenderAssetUsages,
+    image::ImageSampler,
+    input::common_conditions::input_just_pressed,
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+
+use crate::{
+    app_state::AppState,
+    grid::{Grid, GridConfig, GridPos, TILE_SIZE, grid_to_world_coords, world_to_tiles},
+};
+
+use super::{Tower, UpgradeTower};
+
+/// Amount of covering towers at which a tile is shown fully red
+const MAX_HEAT: f32 = 4.;
+
+pub struct TowerCoveragePlugin;
+
+impl Plugin for TowerCoveragePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::Game), spawn_overlay)
+            .add_systems(
+                Update,
+                (
+                    toggle_overlay.run_if(input_just_pressed(KeyCode::KeyC)),
+                    update_overlay,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::Game)),
+            );
+    }
+}
+
+/// Shades every tile by the amount of towers able to shoot at it, toggled with `C`
+#[derive(Component)]
+struct CoverageOverlay {
+    /// Towers changed since the overlay was last drawn
+    outdated: bool,
+}
+
+fn spawn_overlay(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    config: Res<GridConfig>,
+) {
+    // One pixel per tile, stretched over the whole grid
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: config.columns as u32,
+            height: config.rows as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0, 0, 0, 0],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    image.sampler = ImageSampler::nearest();
+
+    commands.spawn((
+        Name::new("Coverage overlay"),
+        CoverageOverlay { outdated: true },
+        Sprite {
+            image: images.add(image),
+            custom_size: Some(Vec2::new(
+                config.columns as f32 * TILE_SIZE,
+                config.rows as f32 * TILE_SIZE,
+            )),
+            ..default()
+        },
+        Transform::from_xyz(-TILE_SIZE * 0.5, -TILE_SIZE * 0.5, 1.8),
+        Visibility::Hidden,
+    ));
+}
+
+fn toggle_overlay(mut overlay: Single<&mut Visibility, With<CoverageOverlay>>) {
+    **overlay = match **overlay {
+        Visibility::Hidden => Visibility::Inherited,
+        _ => Visibility::Hidden,
+    };
+}
+
+/// Only redraws when towers were placed, upgraded or removed, and only while the overlay is
+/// shown
+fn update_overlay(
+    overlay: Single<(&mut CoverageOverlay, &Sprite, &Visibility)>,
+    towers: Query<(&Tower, &Transform)>,
+    new_towers: Query<(), Added<Tower>>,
+    mut removed: RemovedComponents<Tower>,
+    mut upgraded: EventReader<UpgradeTower>,
+    mut images: ResMut<Assets<Image>>,
+    grid: Res<Grid>,
+    config: Res<GridConfig>,
+) {
+    let (mut overlay, sprite, visibility) = overlay.into_inner();
+    if !new_towers.is_empty() || removed.read().count() > 0 || upgraded.read().count() > 0
+    {
+        overlay.outdated = true;
+    }
+    if !overlay.outdated || *visibility == Visibility::Hidden {
+        return;
+    }
+    let Some(image) = images.get_mut(&sprite.image) else {
+        return;
+    };
+
+    // Ranges start at the corner of the tower, which is less than a tile away from the
+    // center of its origin tile
+    let reach = towers
+        .iter()
+        .map(|(tower, _)| world_to_tiles(tower.range()) + 1.)
+        .fold(0., f32::max);
+    for row in 0..config.rows {
+        for col in 0..config.columns {
+            let tile = GridPos::new(row, col);
+            let center = grid_to_world_coords(tile, &config).extend(0.);
+            let coverage = grid
+                .towers_in_radius(tile, reach)
+                .into_iter()
+                .filter_map(|(_, entity)| towers.get(entity).ok())
+                .filter(|(tower, transform)| {
+                    tower.range() > 0. && tower.covers(transform.translation, center)
+                })
+                .count();
+
+            let color = match coverage {
+                0 => Color::NONE,
+                count => {
+                    let heat = (count as f32 / MAX_HEAT).min(1.);
+                    Color::srgba(1., 1. - heat, 0., 0.45)
+                }
+            };
+            // Image rows start at the top, grid rows at the bottom
+            if let Err(e) =
+                image.set_color_at(col as u32, (config.rows - 1 - row) as u32, color)
+            {
+                error!("Failed to update coverage overlay: {e}");
+                return;
+            }
+        }
+    }
+    overlay.outdated = false;
+}
@@ -0,0 +1,191 @@
+use bevy::prelude::*;
+
+use crate::{
+    Settings,
+    app_state::GameState,
+    enemy::{Enemy, Shield},
+    grid::{TILE_SIZE, VisibleTiles},
+    health::Health,
+};
+
+use super::{
+    Tower,
+    attack::{EnemyHits, projectile_damage},
+};
+
+pub struct TowerLaserPlugin;
+
+impl Plugin for TowerLaserPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Beam>().add_systems(
+            Update,
+            (add_beams, fire_beams.after(projectile_damage))
+                .chain()
+                .run_if(in_state(GameState::Running)),
+        );
+    }
+}
+
+const BEAM_COLOR: Color = Color::srgba(1., 0.2, 0.2, 0.9);
+/// Least damage a beam deals at once
+const BEAM_CHUNK: f32 = 5.;
+
+/// State of a tower with `TowerType::is_beam`
+#[derive(Reflect, Component, Default)]
+#[reflect(Component)]
+struct Beam {
+    /// Fraction of a damage point left over from the previous frames, health is whole
+    /// numbers only
+    carry: f32,
+}
+
+fn add_beams(mut commands: Commands, towers: Query<(Entity, &Tower), Added<Tower>>) {
+    for (entity, tower) in &towers {
+        if tower.is_beam() {
+            commands.entity(entity).insert(Beam::default());
+        }
+    }
+}
+
+/// The beam is on while any enemy stands on one of its tiles, and hurts all of them at once
+fn fire_beams(
+    mut gizmos: Gizmos,
+    mut towers: Query<(Entity, &Tower, &Transform, &mut Beam)>,
+    mut enemies: Query<(Entity, &Enemy, &mut Health, &Transform, Option<&Shield>)>,
+    mut hits: EnemyHits,
+    time: Res<Time>,
+    settings: Res<Settings>,
+    visible: Option<Res<VisibleTiles>>,
+) {
+    // Fog is purely visual unless explicitly configured otherwise
+    let visible = visible.filter(|_| settings.fog_targeting);
+
+    for (entity, tower, transform, mut beam) in &mut towers {
+        let Some(origin) = hits.grid.tower_origins.get(&entity).copied() else {
+            continue;
+        };
+        let tiles = tower.beam_tiles(origin);
+        let targets: Vec<Entity> = enemies
+            .iter()
+            .filter(|(_, enemy, health, _, _)| {
+                // Killed earlier this frame
+                ***health > 0
+                    && tiles.contains(&enemy.current)
+                    && !enemy.immune_to(tower.damage_type())
+                    && visible
+                        .as_ref()
+                        .is_none_or(|visible| visible.contains(&enemy.current))
+            })
+            .map(|(entity, ..)| entity)
+            .collect();
+        if !tower.powered || targets.is_empty() {
+            beam.carry = 0.;
+            continue;
+        }
+
+        let start = transform.translation.truncate() + TILE_SIZE * 0.5;
+        let direction = Vec2::from_angle(tower.orientation.angle());
+        gizmos.line_2d(start, start + direction * tower.range(), BEAM_COLOR);
+
+        // Dealt in chunks, a damage number every frame would be unreadable
+        beam.carry += tower.strength() as f32 * time.delta_secs();
+        if beam.carry < BEAM_CHUNK {
+            continue;
+        }
+        let damage = beam.carry.floor();
+        beam.carry -= damage;
+
+        let mut iter = enemies.iter_many_mut(&targets);
+        while let Some((enemy_entity, enemy, mut health, enemy_transform, shield)) =
+            iter.fetch_next()
+        {
+            if Shield::blocks(shield) {
+                continue;
+            }
+            hits.hit(
+                enemy_entity,
+                enemy,
+                &mut health,
+                enemy_transform.translation,
+                damage as isize,
+                tower.damage_type(),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        Orientation,
+        enemy::EnemyType,
+        game_loop::Difficulty,
+        grid::{Grid, GridConfig, GridPos, grid_to_world_coords},
+        test_app,
+        tower::TowerType,
+    };
+
+    use super::*;
+
+    #[test]
+    fn beam_hits_three_enemies_in_a_row() {
+        let config = GridConfig::SMALL;
+        let mut app = test_app();
+        app.init_asset::<GizmoAsset>()
+            .init_gizmo_group::<DefaultGizmoConfigGroup>()
+            .add_systems(Update, fire_beams);
+
+        let origin = GridPos::new(5, 5);
+        let laser = app
+            .world_mut()
+            .spawn((
+                Tower::new(TowerType::Laser, Orientation::Right),
+                // Towers are placed by their bottom left corner
+                Transform::from_translation(
+                    (grid_to_world_coords(origin, &config) - TILE_SIZE * 0.5).extend(1.),
+                ),
+                Beam::default(),
+            ))
+            .id();
+        app.world_mut()
+            .resource_mut::<Grid>()
+            .tower_origins
+            .insert(laser, origin);
+
+        let mut spawn_enemy = |tile: GridPos| {
+            app.world_mut()
+                .spawn((
+                    Enemy::new(tile, EnemyType::Skeleton, Difficulty::Normal),
+                    Health::new(100, Vec2::ZERO),
+                    Transform::from_translation(
+                        grid_to_world_coords(tile, &config).extend(2.),
+                    ),
+                ))
+                .id()
+        };
+        let in_line: Vec<Entity> = (6..9)
+            .map(|col| spawn_enemy(GridPos::new(5, col)))
+            .collect();
+        // Neither behind the tower nor on the next row
+        let missed = [
+            spawn_enemy(GridPos::new(5, 4)),
+            spawn_enemy(GridPos::new(6, 6)),
+        ];
+
+        // Long enough for a full chunk of damage
+        app.world_mut()
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_millis(500));
+        app.update();
+
+        let health = |entity: Entity| app.world().get::<Health>(entity).unwrap().current;
+        for enemy in in_line {
+            assert_eq!(health(enemy), 90);
+        }
+        for enemy in missed {
+            assert_eq!(health(enemy), 100);
+        }
+    }
+}
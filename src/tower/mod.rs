@@ -1,43 +1,185 @@
 use std::time::Duration;
 
 use attack::TowerAttackPlugin;
-use bevy::prelude::*;
+use aura::{Aura, AuraBuff, TowerAuraPlugin};
+use bevy::{
+    input::common_conditions::{input_just_pressed, input_pressed},
+    prelude::*,
+};
+use coverage::TowerCoveragePlugin;
+use laser::TowerLaserPlugin;
 use placing::TowerPlacingPlugin;
+use power::TowerPowerPlugin;
+use recoil::TowerRecoilPlugin;
+use regen::{Regen, TowerRegenPlugin};
+use serde::{Deserialize, Serialize};
+use turret::TowerTurretPlugin;
 
-pub use attack::projectile_damage;
-pub use placing::{SelectedTower, place_tower};
+pub use attack::{TowerFired, projectile_damage};
+pub use placing::{SelectedTower, place_tower, spawn_tower};
 
 use crate::{
-    Orientation,
+    Orientation, Settings,
+    app_state::GameState,
+    enemy::PathChangedEvent,
+    game_loop::Wallet,
     grid::{Grid, GridPos, TILE_SIZE},
+    health::Health,
 };
 
 mod attack;
+mod aura;
+mod coverage;
+mod laser;
 mod placing;
+mod power;
+mod recoil;
+mod regen;
+mod turret;
 
 pub struct TowerPlugin;
 
 impl Plugin for TowerPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<Tower>();
-        app.add_plugins((TowerPlacingPlugin, TowerAttackPlugin));
+        app.add_event::<SellTower>();
+        app.add_event::<RepairTower>();
+        app.add_event::<UpgradeTower>();
+        app.add_event::<TowerDestroyed>();
+        app.add_plugins((
+            TowerPlacingPlugin,
+            TowerAttackPlugin,
+            TowerPowerPlugin,
+            TowerCoveragePlugin,
+            TowerRecoilPlugin,
+            TowerRegenPlugin,
+            TowerTurretPlugin,
+            TowerAuraPlugin,
+            TowerLaserPlugin,
+        ));
+        app.add_systems(Update, sell_tower.run_if(on_event::<SellTower>));
+        app.add_systems(Update, repair_tower.run_if(on_event::<RepairTower>));
+        app.add_systems(Update, upgrade_tower.run_if(on_event::<UpgradeTower>));
+    }
+}
+
+/// Removes every tower with Ctrl+Delete, without any refund. Only added when running with
+/// `--egui`, it's meant for trying out layouts
+pub struct ClearTowersPlugin;
+
+impl Plugin for ClearTowersPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            clear_towers
+                .run_if(input_pressed(KeyCode::ControlLeft))
+                .run_if(input_just_pressed(KeyCode::Delete))
+                .run_if(in_state(GameState::Running)),
+        );
     }
 }
 
+/// Attackers of the removed towers are released by `release_dangling_attackers`, and the
+/// single path change lets every enemy look for a new path
+fn clear_towers(
+    mut commands: Commands,
+    towers: Query<(Entity, &Tower)>,
+    mut grid: ResMut<Grid>,
+    mut path_change: EventWriter<PathChangedEvent>,
+) {
+    let mut freed = vec![];
+    for (entity, tower) in &towers {
+        freed.extend(tower.clear_grid(&mut grid, entity));
+        commands.entity(entity).despawn();
+    }
+    info!("Cleared {} towers", towers.iter().count());
+    path_change.write(PathChangedEvent::now_free(freed));
+}
+
 #[derive(Reflect, Component, Clone, Deref, DerefMut)]
 #[reflect(Component)]
 pub struct Tower {
     #[deref]
     pub variant: TowerType,
+    /// Non-repeating on purpose: once finished the tower stays ready to fire until it
+    /// finds a target, and the timer only gets reset when a shot was actually fired
     attack_timer: Timer,
     pub orientation: Orientation,
+    /// Offline towers don't fire, see the `power` module
+    pub powered: bool,
+    /// Increases with every placed tower, the newest towers are the first to go offline
+    placement: u32,
+    /// Which enemy in range gets shot, only changeable for towers with `has_targeting`
+    pub targeting: TargetingMode,
+    /// Starts at 1, raised by upgrading up to `MAX_TOWER_LEVEL`
+    pub level: u8,
 }
 
-#[derive(Reflect, Clone, Copy, Debug)]
+pub const MAX_TOWER_LEVEL: u8 = 3;
+
+/// Factors applied to the stats of a tower once per level above the first
+struct LevelScaling {
+    health: f32,
+    strength: f32,
+    range: f32,
+    /// Below 1 to fire faster
+    fire_cooldown: f32,
+}
+
+/// Serialized as its snake_case name, unknown names fail to deserialize with an error
+/// listing the known ones
+#[derive(Reflect, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
 pub enum TowerType {
     Wall,
     SpikedWall,
     Canon,
+    Mage,
+    Generator,
+    /// Cheap tower with a large vision radius, useful when playing with fog
+    Watchtower,
+    /// L-shaped wall, covering three tiles in a row and one more above the first
+    Bunker,
+    /// Slow, long ranged tower damaging every enemy around the impact
+    Mortar,
+    /// Deals no damage, slows down every enemy in range instead
+    Frost,
+    /// Deals no damage, makes the Canons around it hit harder and fire faster
+    Beacon,
+    /// Burns every enemy in a straight line in front of it, for as long as there is one
+    Laser,
+}
+
+/// How a tower picks its target among the enemies in range
+#[derive(Reflect, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetingMode {
+    /// Fewest steps left on its path to a goal
+    First,
+    /// Most steps left on its path to a goal
+    Last,
+    /// Closest to the goal in a straight line, regardless of walls in between
+    #[default]
+    Closest,
+    /// Highest current health
+    Strongest,
+}
+
+impl TargetingMode {
+    pub fn next(self) -> Self {
+        match self {
+            TargetingMode::First => TargetingMode::Last,
+            TargetingMode::Last => TargetingMode::Closest,
+            TargetingMode::Closest => TargetingMode::Strongest,
+            TargetingMode::Strongest => TargetingMode::First,
+        }
+    }
+}
+
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DamageType {
+    Physical,
+    Magic,
 }
 
 impl Tower {
@@ -46,9 +188,52 @@ impl Tower {
             variant,
             orientation,
             attack_timer: Timer::new(variant.fire_cooldown(), TimerMode::Once),
+            powered: true,
+            placement: 0,
+            targeting: default(),
+            level: 1,
         }
     }
 
+    /// `factor` applied once per level above the first
+    fn level_factor(&self, factor: f32) -> f32 {
+        factor.powi(self.level as i32 - 1)
+    }
+
+    fn max_hp(&self) -> isize {
+        (self.variant.max_hp() as f32 * self.level_factor(self.level_scaling().health))
+            .round() as isize
+    }
+
+    fn strength(&self) -> isize {
+        (self.variant.strength() as f32
+            * self.level_factor(self.level_scaling().strength))
+        .round() as isize
+    }
+
+    /// Damage of a single projectile, including the aura affecting the tower
+    fn buffed_strength(&self, buff: Option<&AuraBuff>) -> isize {
+        let strength = self.strength();
+        buff.map_or(strength, |buff| {
+            (strength as f32 * buff.strength).round() as isize
+        })
+    }
+
+    fn range(&self) -> f32 {
+        self.variant.range() * self.level_factor(self.level_scaling().range)
+    }
+
+    fn fire_cooldown(&self) -> Duration {
+        self.variant
+            .fire_cooldown()
+            .mul_f32(self.level_factor(self.level_scaling().fire_cooldown))
+    }
+
+    /// Cost of raising the tower to the next level, None once it reached the max level
+    pub fn upgrade_cost(&self) -> Option<i32> {
+        (self.level < MAX_TOWER_LEVEL).then(|| self.cost() * self.level as i32)
+    }
+
     fn fill_grid(
         &self,
         origin: &GridPos,
@@ -58,13 +243,9 @@ impl Tower {
         let mut blocked = vec![];
         grid.tower_origins.insert(entity, *origin);
         // Add entity to every coordinate it covers
-        let (rows, cols) = self.size();
-        for i in 0..rows {
-            for j in 0..cols {
-                let pos = GridPos::new(origin.row + j, origin.col + i);
-                grid.towers.insert(pos, entity);
-                blocked.push(pos);
-            }
+        for pos in self.footprint(*origin) {
+            grid.towers.insert(pos, entity);
+            blocked.push(pos);
         }
         blocked
     }
@@ -74,17 +255,38 @@ impl Tower {
         let Some(origin) = grid.tower_origins.remove(&entity) else {
             return vec![];
         };
-        let (rows, cols) = self.size();
-        for i in 0..rows {
-            for j in 0..cols {
-                let pos = GridPos::new(origin.row + j, origin.col + i);
-                grid.towers.remove(&pos);
-                freed.push(pos);
-            }
+        for pos in self.footprint(origin) {
+            grid.towers.remove(&pos);
+            freed.push(pos);
         }
         freed
     }
 
+    /// Whether `target` is within the range of the tower placed at `tower_translation`.
+    /// Towers with a non-circular range have to be handled in here.
+    pub fn covers(&self, tower_translation: Vec3, target: Vec3) -> bool {
+        if !self.is_beam() {
+            return tower_translation.distance(target) <= self.range();
+        }
+        // Beams start at the center of the (single tile) tower and are one tile wide
+        let direction = Vec2::from_angle(self.orientation.angle());
+        let offset =
+            (target - tower_translation).truncate() - Vec2::splat(TILE_SIZE * 0.5);
+        let along = offset.dot(direction);
+        along > 0.
+            && along <= self.range() + TILE_SIZE * 0.5
+            && offset.perp_dot(direction).abs() <= TILE_SIZE * 0.5
+    }
+
+    /// Tiles hit by the beam of a tower placed at `origin`, ordered by their distance to it
+    pub fn beam_tiles(&self, origin: GridPos) -> Vec<GridPos> {
+        let step = self.orientation.step();
+        (1..=(self.range() / TILE_SIZE).round() as isize)
+            .map(|distance| origin + [step.row * distance, step.col * distance])
+            .collect()
+    }
+
+    /// Size of the bounding box of the tower, as (width, height)
     pub fn size(&self) -> (isize, isize) {
         let size = self.variant.size();
         // Flip Dimensions of the tower in case of rotation
@@ -94,6 +296,40 @@ impl Tower {
         }
     }
 
+    /// Every tile covered by the tower, relative to the bottom left corner of its bounding box
+    pub fn tiles(&self) -> Vec<GridPos> {
+        self.variant
+            .shape()
+            .into_iter()
+            .map(|tile| self.rotate(tile))
+            .collect()
+    }
+
+    /// Every tile covered by the tower when its bounding box starts at `origin`
+    pub fn footprint(&self, origin: GridPos) -> Vec<GridPos> {
+        self.tiles().into_iter().map(|tile| origin + tile).collect()
+    }
+
+    /// Whether the tower covers its whole bounding box
+    pub fn is_rectangular(&self) -> bool {
+        let (width, height) = self.variant.size();
+        self.variant.shape().len() == (width * height) as usize
+    }
+
+    /// Rotates a tile of the upright tower (clockwise, in the order `R` cycles through)
+    /// and moves it back into the bounding box
+    pub fn rotate(&self, tile: GridPos) -> GridPos {
+        let (width, height) = self.variant.size();
+        match self.orientation {
+            Orientation::Up => tile,
+            Orientation::Right => GridPos::new(width - 1 - tile.col, tile.row),
+            Orientation::Down => {
+                GridPos::new(height - 1 - tile.row, width - 1 - tile.col)
+            }
+            Orientation::Left => GridPos::new(tile.col, height - 1 - tile.row),
+        }
+    }
+
     fn health_bar_offset(&self) -> Vec2 {
         match self.variant {
             TowerType::Wall | TowerType::SpikedWall => {
@@ -102,26 +338,94 @@ impl Tower {
                     false => Vec2::new(50., 13.),
                 }
             }
-            TowerType::Canon => Vec2::splat(38.),
+            TowerType::Canon | TowerType::Mage | TowerType::Mortar => Vec2::splat(38.),
+            TowerType::Generator | TowerType::Frost | TowerType::Beacon => {
+                Vec2::splat(25.)
+            }
+            TowerType::Watchtower | TowerType::Laser => Vec2::new(13., 35.),
+            TowerType::Bunker => Vec2::new(38., 55.),
         }
     }
 }
 
 impl TowerType {
+    /// Every tower in the order of the bottom bar, the number keys followed by minus select
+    /// them in this order as well
+    // I would have automated this but I don't think it is possible :/
+    pub const ALL: [TowerType; 11] = [
+        TowerType::Wall,
+        TowerType::SpikedWall,
+        TowerType::Bunker,
+        TowerType::Canon,
+        TowerType::Mage,
+        TowerType::Mortar,
+        TowerType::Frost,
+        TowerType::Beacon,
+        TowerType::Laser,
+        TowerType::Generator,
+        TowerType::Watchtower,
+    ];
+
+    /// Whether the tower can be built, generators and watchtowers are useless without their
+    /// game mode
+    pub fn available(&self, settings: &Settings) -> bool {
+        match self {
+            TowerType::Generator => settings.power,
+            TowerType::Watchtower => settings.fog,
+            _ => true,
+        }
+    }
+
     //temp values as balancing cannot happen until a basic gameplay loop is in place
     fn max_hp(&self) -> isize {
         match self {
             TowerType::Wall => 100,
             TowerType::SpikedWall => 100,
             TowerType::Canon => 80,
+            TowerType::Mage => 60,
+            TowerType::Generator => 80,
+            TowerType::Watchtower => 40,
+            TowerType::Bunker => 250,
+            TowerType::Mortar => 70,
+            TowerType::Frost => 60,
+            TowerType::Beacon => 50,
+            TowerType::Laser => 60,
         }
     }
 
+    /// Size of the bounding box, as (width, height)
     pub fn size(&self) -> (isize, isize) {
         match self {
             TowerType::Wall => (1, 1),
             TowerType::SpikedWall => (1, 1),
             TowerType::Canon => (3, 3),
+            TowerType::Mage => (3, 3),
+            TowerType::Generator => (2, 2),
+            TowerType::Watchtower => (1, 1),
+            TowerType::Bunker => (3, 2),
+            TowerType::Mortar => (3, 3),
+            TowerType::Frost => (2, 2),
+            TowerType::Beacon => (2, 2),
+            TowerType::Laser => (1, 1),
+        }
+    }
+
+    /// Tiles covered by the upright tower, relative to the bottom left corner of its
+    /// bounding box. Most towers simply fill their whole bounding box.
+    fn shape(&self) -> Vec<GridPos> {
+        match self {
+            TowerType::Bunker => vec![
+                GridPos::new(0, 0),
+                GridPos::new(0, 1),
+                GridPos::new(0, 2),
+                GridPos::new(1, 0),
+            ],
+            _ => {
+                let (width, height) = self.size();
+                (0..height)
+                    .flat_map(|row| (0..width).map(move |col| GridPos::new(row, col)))
+                    .collect()
+            }
         }
     }
 
@@ -130,20 +434,81 @@ impl TowerType {
             TowerType::Wall => (0, 0),
             TowerType::SpikedWall => (0, 0),
             TowerType::Canon => (1, 1),
+            TowerType::Mage => (1, 1),
+            TowerType::Generator => (0, 0),
+            TowerType::Watchtower => (0, 0),
+            TowerType::Bunker => (0, 0),
+            TowerType::Mortar => (1, 1),
+            TowerType::Frost => (0, 0),
+            TowerType::Beacon => (0, 0),
+            TowerType::Laser => (0, 0),
         }
     }
 
-    fn cost(&self) -> i32 {
+    pub fn cost(&self) -> i32 {
         match self {
             TowerType::Wall => 2,
             TowerType::SpikedWall => 5,
             TowerType::Canon => 50,
+            TowerType::Mage => 70,
+            TowerType::Generator => 30,
+            TowerType::Watchtower => 15,
+            TowerType::Bunker => 12,
+            TowerType::Mortar => 90,
+            TowerType::Frost => 40,
+            TowerType::Beacon => 60,
+            TowerType::Laser => 80,
+        }
+    }
+
+    /// Rounded down, so selling never refunds more than the configured fraction
+    pub fn sell_value(&self, refund: f32) -> i32 {
+        (self.cost() as f32 * refund) as i32
+    }
+
+    /// Restoring the full health costs as much as building the tower, rounded up so every
+    /// repair costs something
+    pub fn repair_cost(&self, health: &Health) -> i32 {
+        let missing = (health.max - health.current).max(0);
+        if missing == 0 {
+            return 0;
+        }
+        ((missing as f32 / health.max as f32 * self.cost() as f32).ceil() as i32).max(1)
+    }
+
+    /// Whether the player can choose a `TargetingMode` for this tower
+    pub fn has_targeting(&self) -> bool {
+        matches!(self, TowerType::Canon)
+    }
+
+    fn level_scaling(&self) -> LevelScaling {
+        match self {
+            TowerType::Canon
+            | TowerType::Mage
+            | TowerType::Mortar
+            | TowerType::Frost
+            | TowerType::Laser => LevelScaling {
+                health: 1.25,
+                strength: 1.4,
+                range: 1.1,
+                fire_cooldown: 0.9,
+            },
+            _ => LevelScaling {
+                health: 1.5,
+                strength: 1.5,
+                range: 1.,
+                fire_cooldown: 1.,
+            },
         }
     }
 
     fn range(&self) -> f32 {
         match self {
             TowerType::Canon => TILE_SIZE * 10.0,
+            TowerType::Mage => TILE_SIZE * 8.0,
+            TowerType::Mortar => TILE_SIZE * 16.0,
+            TowerType::Frost => TILE_SIZE * 6.0,
+            TowerType::Laser => TILE_SIZE * 7.0,
             _ => 0.0,
         }
     }
@@ -151,6 +516,10 @@ impl TowerType {
     fn strength(&self) -> isize {
         match self {
             TowerType::Canon => 15,
+            TowerType::Mage => 10,
+            TowerType::Mortar => 12,
+            // Per second, see `is_beam`
+            TowerType::Laser => 20,
             _ => 0,
         }
     }
@@ -158,14 +527,336 @@ impl TowerType {
     fn fire_cooldown(&self) -> Duration {
         match self {
             TowerType::Canon => Duration::from_secs_f32(0.8),
+            TowerType::Mage => Duration::from_secs_f32(1.0),
+            TowerType::Mortar => Duration::from_secs_f32(3.0),
+            // Interval between two pulses, each refreshing the slow of every enemy in range
+            TowerType::Frost => Duration::from_secs_f32(0.5),
             _ => Duration::ZERO,
         }
     }
 
+    /// Beams damage every enemy on their tiles continuously, `strength` per second,
+    /// instead of firing projectiles
+    pub fn is_beam(&self) -> bool {
+        matches!(self, TowerType::Laser)
+    }
+
+    /// Every enemy whose tile is within this distance of the tile of the impact takes the
+    /// damage, 0 for towers hitting a single target
+    pub fn splash_radius(&self) -> f32 {
+        match self {
+            TowerType::Mortar => TILE_SIZE * 2.5,
+            _ => 0.,
+        }
+    }
+
+    /// Walls slowly heal once they haven't been attacked for a while, other towers need to
+    /// be repaired
+    fn regen(&self) -> Option<Regen> {
+        match self {
+            TowerType::Wall | TowerType::SpikedWall => Some(Regen {
+                rate: 2.,
+                delay_after_hit: 4.,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Velocity multiplier applied to enemies in range, None for towers that don't slow
+    pub fn slow_factor(&self) -> Option<f32> {
+        match self {
+            TowerType::Frost => Some(0.5),
+            _ => None,
+        }
+    }
+
+    /// Buff granted to the towers around it, None for towers without an aura
+    pub fn aura(&self) -> Option<Aura> {
+        match self {
+            TowerType::Beacon => Some(Aura {
+                radius: TILE_SIZE * 5.,
+                strength: 1.25,
+                fire_rate: 1.2,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Whether auras of nearby towers have an effect on this tower
+    pub fn receives_aura(&self) -> bool {
+        matches!(self, TowerType::Canon)
+    }
+
+    /// Vision radius in tiles, only relevant when playing with fog
+    pub fn vision(&self) -> f32 {
+        match self {
+            TowerType::Wall | TowerType::SpikedWall | TowerType::Bunker => 2.,
+            TowerType::Canon => 10.,
+            TowerType::Mage => 8.,
+            TowerType::Generator => 3.,
+            TowerType::Watchtower => 14.,
+            TowerType::Mortar => 6.,
+            TowerType::Frost => 6.,
+            TowerType::Beacon => 4.,
+            TowerType::Laser => 7.,
+        }
+    }
+
+    pub fn damage_type(&self) -> DamageType {
+        match self {
+            TowerType::Mage | TowerType::Laser => DamageType::Magic,
+            _ => DamageType::Physical,
+        }
+    }
+
+    /// Power produced by this tower, negative values are consumed
+    pub fn power(&self) -> i32 {
+        match self {
+            TowerType::Generator => 10,
+            TowerType::Canon => -4,
+            TowerType::Mage => -5,
+            TowerType::Mortar => -6,
+            TowerType::Frost => -3,
+            TowerType::Beacon => -3,
+            TowerType::Laser => -5,
+            _ => 0,
+        }
+    }
+
     pub fn contact_damage(&self) -> isize {
         match self {
             TowerType::SpikedWall => 5,
             _ => 0,
         }
     }
+
+    /// Interval at which an attacking enemy takes the contact damage, towers without
+    /// contact damage have none
+    pub fn contact_damage_cooldown(&self) -> Duration {
+        match self {
+            TowerType::SpikedWall => Duration::from_secs(1),
+            _ => Duration::ZERO,
+        }
+    }
+
+    /// Label and value of every stat that matters for this type at level 1, stats a tower
+    /// doesn't have (like the range of a wall) are left out
+    pub fn stat_lines(&self) -> Vec<(&'static str, String)> {
+        let mut lines = vec![
+            ("Cost", self.cost().to_string()),
+            ("Health", self.max_hp().to_string()),
+        ];
+        if self.range() > 0. {
+            lines.push(("Range", format!("{} tiles", self.range() / TILE_SIZE)));
+        }
+        if self.strength() > 0 {
+            lines.push(match self.is_beam() {
+                true => ("Damage", format!("{}/s", self.strength())),
+                false => ("Damage", self.strength().to_string()),
+            });
+        }
+        if self.splash_radius() > 0. {
+            lines.push((
+                "Splash",
+                format!("{} tiles", self.splash_radius() / TILE_SIZE),
+            ));
+        }
+        if let Some(factor) = self.slow_factor() {
+            lines.push(("Slow", format!("{}%", ((1. - factor) * 100.).round())));
+        }
+        if !self.fire_cooldown().is_zero() {
+            lines.push((
+                "Cooldown",
+                format!("{}s", self.fire_cooldown().as_secs_f32()),
+            ));
+        }
+        if self.contact_damage() > 0 {
+            lines.push((
+                "Contact damage",
+                format!(
+                    "{} every {}s",
+                    self.contact_damage(),
+                    self.contact_damage_cooldown().as_secs_f32()
+                ),
+            ));
+        }
+        if let Some(aura) = self.aura() {
+            lines.push(("Aura", format!("{} tiles", aura.radius / TILE_SIZE)));
+            lines.push((
+                "Canon damage",
+                format!("+{}%", ((aura.strength - 1.) * 100.).round()),
+            ));
+            lines.push((
+                "Canon fire rate",
+                format!("+{}%", ((aura.fire_rate - 1.) * 100.).round()),
+            ));
+        }
+        if self.power() != 0 {
+            lines.push(("Power", format!("{:+}", self.power())));
+        }
+        lines
+    }
+}
+
+/// Fired when enemies bring the health of a tower down to zero, not when it is sold
+#[derive(Event)]
+pub struct TowerDestroyed;
+
+/// Removes the tower from the grid and refunds part of its cost
+#[derive(Event)]
+pub struct SellTower(pub Entity);
+
+fn sell_tower(
+    mut commands: Commands,
+    mut events: EventReader<SellTower>,
+    towers: Query<&Tower>,
+    mut grid: ResMut<Grid>,
+    mut wallet: Wallet,
+    mut path_change: EventWriter<PathChangedEvent>,
+    settings: Res<Settings>,
+) {
+    for SellTower(entity) in events.read() {
+        let Ok(tower) = towers.get(*entity) else {
+            continue;
+        };
+        // Towers are free in sandbox mode, selling them doesn't pay out either
+        wallet.refund(tower.sell_value(settings.sell_refund));
+        path_change.write(PathChangedEvent::now_free(
+            tower.clear_grid(&mut grid, *entity),
+        ));
+        commands.entity(*entity).despawn();
+    }
+}
+
+/// Restores the tower to full health, if the player can afford it
+#[derive(Event)]
+pub struct RepairTower(pub Entity);
+
+fn repair_tower(
+    mut events: EventReader<RepairTower>,
+    mut towers: Query<(&Tower, &mut Health)>,
+    mut wallet: Wallet,
+) {
+    for RepairTower(entity) in events.read() {
+        let Ok((tower, mut health)) = towers.get_mut(*entity) else {
+            continue;
+        };
+        // Computed now, the tower might have taken damage since the button was drawn
+        let cost = tower.repair_cost(&health);
+        if cost == 0 || !wallet.affords(cost) {
+            continue;
+        }
+        wallet.spend(cost);
+        health.current = health.max;
+    }
+}
+
+/// Raises the tower by one level, if the player can afford it. The footprint stays the same
+#[derive(Event)]
+pub struct UpgradeTower(pub Entity);
+
+fn upgrade_tower(
+    mut commands: Commands,
+    mut events: EventReader<UpgradeTower>,
+    mut towers: Query<(&mut Tower, &mut Health)>,
+    mut wallet: Wallet,
+) {
+    for UpgradeTower(entity) in events.read() {
+        let Ok((mut tower, mut health)) = towers.get_mut(*entity) else {
+            continue;
+        };
+        let Some(cost) = tower.upgrade_cost() else {
+            continue;
+        };
+        if !wallet.affords(cost) {
+            continue;
+        }
+        wallet.spend(cost);
+
+        tower.level += 1;
+        // Keeps the share of health the tower had before
+        let max = tower.max_hp();
+        health.current =
+            (health.current as f32 * max as f32 / health.max as f32).round() as isize;
+        health.max = max;
+        let cooldown = tower.fire_cooldown();
+        tower.attack_timer.set_duration(cooldown);
+
+        commands.entity(*entity).with_child(level_pip(tower.level));
+    }
+}
+
+/// Small marker in the bottom left corner of the tower, one per level above the first
+fn level_pip(level: u8) -> impl Bundle {
+    (
+        Name::new(format!("Level pip {level}")),
+        Sprite::from_color(LEVEL_PIP_COLOR, Vec2::splat(5.)),
+        Transform::from_xyz(5. + (level - 2) as f32 * 7., 5., 2.),
+    )
+}
+
+const LEVEL_PIP_COLOR: Color = Color::srgb(1., 0.8, 0.1);
+
+#[cfg(test)]
+mod tests {
+    use bevy::platform::collections::HashSet;
+
+    use crate::{
+        game_loop::{Currency, GameStatistics, Sandbox},
+        test_app,
+    };
+
+    use super::*;
+
+    #[test]
+    fn bunker_footprint_follows_the_rotation() {
+        let origin = GridPos::new(4, 6);
+        // Bounding box and covered tiles, relative to the bottom left corner
+        let orientations = [
+            (Orientation::Up, (3, 2), [(0, 0), (0, 1), (0, 2), (1, 0)]),
+            (Orientation::Right, (2, 3), [(0, 0), (1, 0), (2, 0), (2, 1)]),
+            (Orientation::Down, (3, 2), [(1, 0), (1, 1), (1, 2), (0, 2)]),
+            (Orientation::Left, (2, 3), [(0, 0), (0, 1), (1, 1), (2, 1)]),
+        ];
+
+        for (orientation, size, tiles) in orientations {
+            let bunker = Tower::new(TowerType::Bunker, orientation);
+            assert_eq!(bunker.size(), size, "{orientation:?}");
+
+            let footprint: HashSet<GridPos> =
+                bunker.footprint(origin).into_iter().collect();
+            let expected: HashSet<GridPos> = tiles
+                .into_iter()
+                .map(|(row, col)| origin + GridPos::new(row, col))
+                .collect();
+            assert_eq!(footprint, expected, "{orientation:?}");
+
+            let (width, height) = size;
+            assert!(footprint.iter().all(|tile| {
+                (origin.row..origin.row + height).contains(&tile.row)
+                    && (origin.col..origin.col + width).contains(&tile.col)
+            }));
+        }
+    }
+
+    #[test]
+    fn selling_in_sandbox_mode_pays_nothing() {
+        let mut app = test_app();
+        app.insert_resource(Sandbox(true))
+            .add_event::<SellTower>()
+            .add_systems(Update, sell_tower);
+        let tower = Tower::new(TowerType::Canon, Orientation::Up);
+        let value = tower.sell_value(app.world().resource::<Settings>().sell_refund);
+        let entity = app.world_mut().spawn(tower.clone()).id();
+        let mut grid = app.world_mut().resource_mut::<Grid>();
+        tower.fill_grid(&GridPos::new(4, 4), &mut grid, entity);
+
+        app.world_mut().send_event(SellTower(entity));
+        app.update();
+
+        assert_eq!(**app.world().resource::<Currency>(), 0);
+        assert_eq!(app.world().resource::<GameStatistics>().money_spend, -value);
+        assert!(app.world().resource::<Grid>().towers.is_empty());
+        assert!(app.world().get_entity(entity).is_err());
+    }
 }
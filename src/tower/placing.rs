@@ -1,60 +1,185 @@
-use std::ops::{Deref, DerefMut};
+use std::{
+    collections::VecDeque,
+    ops::{Deref, DerefMut},
+};
 
 use bevy::{
-    input::common_conditions::{input_just_pressed, input_pressed},
+    input::{
+        common_conditions::{input_just_pressed, input_pressed},
+        mouse::{AccumulatedMouseScroll, MouseScrollUnit},
+    },
     prelude::*,
     window::PrimaryWindow,
 };
 
 use crate::{
-    Orientation,
-    app_state::{GameState, TowerPlacingState, UiHoverState},
+    Orientation, Settings,
+    app_state::{AppState, GameState, TowerPlacingState, UiHoverState, escape_game},
     enemy::PathChangedEvent,
-    game_loop::{Currency, GameStatistics},
+    game_loop::{Currency, Sandbox, Wallet},
     grid::{
-        COLUMNS, Grid, GridPos, ROWS, TILE_SIZE, grid_to_world_coords,
+        Grid, GridConfig, GridPos, TILE_SIZE, Vision, grid_to_world_coords,
         world_to_grid_coords,
     },
     health::Health,
+    soundtrack::{Sfx, play_sfx},
+    theme::{Theme, ThemeColor},
 };
 
-use super::{Tower, TowerType};
+use super::{SellTower, Tower, TowerType, level_pip, turret::turret};
 
 pub struct TowerPlacingPlugin;
 
 impl Plugin for TowerPlacingPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<TowerPreview>()
+            .register_type::<PreviewMarker>()
+            .register_type::<SellMode>()
+            .init_resource::<SellMode>()
+            .init_resource::<PlacementCursor>()
+            .register_type::<KeyboardCursor>()
+            .add_systems(
+                OnEnter(AppState::Game),
+                (reset_keyboard_cursor, reset_placement_history),
+            )
+            .add_systems(
+                Update,
+                undo_placement
+                    .run_if(input_pressed(KeyCode::ControlLeft))
+                    .run_if(input_just_pressed(KeyCode::KeyZ))
+                    .run_if(in_state(GameState::Running)),
+            )
             .insert_resource(SelectedTower(Tower::new(TowerType::Wall, Orientation::Up)))
             .add_systems(OnEnter(TowerPlacingState::Placing), spawn_preview)
-            .add_systems(OnExit(TowerPlacingState::Placing), despawn_preview)
+            .add_systems(
+                OnExit(TowerPlacingState::Placing),
+                (despawn_preview, reset_sell_mode),
+            )
+            .add_event::<PlacementOutcome>()
             .add_systems(OnEnter(GameState::GameOver), exit_tower_place_state)
+            .add_systems(
+                Update,
+                draw_hovered_range.run_if(
+                    in_state(GameState::Running).and(in_state(TowerPlacingState::None)),
+                ),
+            )
+            .add_systems(
+                Update,
+                select_tower_hotkey.run_if(
+                    in_state(GameState::Running)
+                        .and(not(in_state(UiHoverState::Hovering))),
+                ),
+            )
+            .add_systems(
+                Update,
+                play_placement_sfx.run_if(on_event::<PlacementOutcome>),
+            )
+            .add_systems(
+                Update,
+                (
+                    move_keyboard_cursor.run_if(keyboard_placement),
+                    update_placement_cursor,
+                )
+                    .chain()
+                    .before(place_tower)
+                    .before(update_preview)
+                    .run_if(in_state(TowerPlacingState::Placing)),
+            )
             .add_systems(
                 Update,
                 (
                     place_tower
                         .run_if(
-                            input_just_pressed(MouseButton::Left)
-                                .or(input_pressed(KeyCode::ShiftLeft)
-                                    .and(input_pressed(MouseButton::Left))),
+                            not(keyboard_placement)
+                                .and(not(in_state(UiHoverState::Hovering)))
+                                .and(
+                                    input_just_pressed(MouseButton::Left)
+                                        .or(input_pressed(KeyCode::ShiftLeft)
+                                            .and(input_pressed(MouseButton::Left))),
+                                )
+                                .or(keyboard_placement
+                                    .and(input_just_pressed(KeyCode::Enter))),
                         )
+                        .run_if(in_state(GameState::Running).and(not(sell_mode_active)))
+                        .run_if(cursor_entered_new_tile),
+                    sell_hovered_tower
+                        .run_if(input_just_pressed(MouseButton::Left))
                         .run_if(
                             in_state(GameState::Running)
-                                .and(not(in_state(UiHoverState::Hovering))),
+                                .and(not(in_state(UiHoverState::Hovering)))
+                                .and(sell_mode_active),
                         ),
-                    change_rotation.run_if(input_just_pressed(KeyCode::KeyR)),
+                    toggle_sell_mode.run_if(input_just_pressed(KeyCode::KeyX)),
+                    (
+                        change_rotation.run_if(input_just_pressed(KeyCode::KeyR)),
+                        scroll_rotation.run_if(not(in_state(UiHoverState::Hovering))),
+                    )
+                        .before(update_preview),
                     update_preview,
+                    draw_preview_range.after(update_preview),
                     exit_tower_place_state.run_if(input_just_pressed(KeyCode::KeyQ)),
+                    cancel_placing
+                        .run_if(input_just_pressed(KeyCode::Escape))
+                        .run_if(in_state(GameState::Running))
+                        .before(escape_game),
                 )
                     .run_if(in_state(TowerPlacingState::Placing)),
             );
     }
 }
 
+/// Number keys select the available towers in the order of the bottom bar, 0 comes last
+const TOWER_HOTKEYS: [KeyCode; 11] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+    KeyCode::Digit0,
+    KeyCode::Minus,
+];
+
+/// Works like clicking the tower buttons, but keeps the selection untouched when the tower
+/// is already being placed
+fn select_tower_hotkey(
+    input: Res<ButtonInput<KeyCode>>,
+    settings: Res<Settings>,
+    mut selection: ResMut<SelectedTower>,
+    state: Res<State<TowerPlacingState>>,
+    mut next_state: ResMut<NextState<TowerPlacingState>>,
+) {
+    let pressed = TowerType::ALL
+        .into_iter()
+        .filter(|tower| tower.available(&settings))
+        .zip(TOWER_HOTKEYS)
+        .find(|(_, key)| input.just_pressed(*key));
+    let Some((tower, _)) = pressed else {
+        return;
+    };
+    if *state.get() == TowerPlacingState::Placing && selection.variant == tower {
+        return;
+    }
+    selection.0 = Tower::new(tower, selection.orientation);
+    next_state.set(TowerPlacingState::Placing);
+}
+
 fn exit_tower_place_state(mut next_state: ResMut<NextState<TowerPlacingState>>) {
     next_state.set(TowerPlacingState::None);
 }
 
+fn cancel_placing(
+    mut input: ResMut<ButtonInput<KeyCode>>,
+    next_state: ResMut<NextState<TowerPlacingState>>,
+) {
+    // Escape should only stop placing, not pause the game as well
+    input.clear_just_pressed(KeyCode::Escape);
+    exit_tower_place_state(next_state);
+}
+
 #[derive(Reflect, Resource)]
 #[reflect(Resource)]
 pub struct SelectedTower(pub Tower);
@@ -74,106 +199,450 @@ impl DerefMut for SelectedTower {
 #[derive(Reflect, Component)]
 #[reflect(Component)]
 struct TowerPreview;
+
+/// While active, left clicks sell the tower under the cursor instead of placing one. Only
+/// lasts as long as the placing state, leaving it turns sell mode off again.
+#[derive(Reflect, Resource, Default)]
+#[reflect(Resource)]
+struct SellMode(bool);
+
+/// Shift-dragging only attempts a placement once the cursor enters another tile, instead of
+/// retrying the same one every frame the mouse is held still. A fresh click or Enter
+/// always counts
+fn cursor_entered_new_tile(
+    mouse: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    cursor: Res<PlacementCursor>,
+    mut last: Local<Option<GridPos>>,
+) -> bool {
+    let entered = mouse.just_pressed(MouseButton::Left)
+        || keys.just_pressed(KeyCode::Enter)
+        || cursor.0 != *last;
+    *last = cursor.0;
+    entered
+}
+
+/// The tile a tower would be placed on, under the mouse or under the keyboard cursor with
+/// `keyboard_placement`. None while the mouse is outside the board
+#[derive(Resource, Default)]
+pub struct PlacementCursor(Option<GridPos>);
+
+/// Moved with the arrow keys, only used with `keyboard_placement`. Stays where it was
+/// between placements, so walls can be lined up quickly
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+struct KeyboardCursor(GridPos);
+
+fn keyboard_placement(settings: Res<Settings>) -> bool {
+    settings.keyboard_placement
+}
+
+fn reset_keyboard_cursor(mut commands: Commands, config: Res<GridConfig>) {
+    commands.insert_resource(KeyboardCursor(GridPos::new(
+        config.rows / 2,
+        config.columns / 2,
+    )));
+}
+
+fn move_keyboard_cursor(
+    mut cursor: ResMut<KeyboardCursor>,
+    keys: Res<ButtonInput<KeyCode>>,
+    config: Res<GridConfig>,
+) {
+    let moves = [
+        (KeyCode::ArrowUp, [1, 0]),
+        (KeyCode::ArrowDown, [-1, 0]),
+        (KeyCode::ArrowLeft, [0, -1]),
+        (KeyCode::ArrowRight, [0, 1]),
+    ];
+    for (key, step) in moves {
+        let moved = cursor.0 + step;
+        if keys.just_pressed(key) && moved.inside_grid_bounds(&config) {
+            cursor.0 = moved;
+        }
+    }
+}
+
+fn update_placement_cursor(
+    mut cursor: ResMut<PlacementCursor>,
+    keyboard: Res<KeyboardCursor>,
+    settings: Res<Settings>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    cam: Single<(&Camera, &GlobalTransform), With<Camera>>,
+    config: Res<GridConfig>,
+) {
+    if settings.keyboard_placement {
+        cursor.0 = Some(keyboard.0);
+        return;
+    }
+    let (camera, cam_transform) = *cam;
+    cursor.0 = window
+        .cursor_position()
+        .and_then(|pos| camera.viewport_to_world_2d(cam_transform, pos).ok())
+        .and_then(|pos| world_to_grid_coords(pos, &config));
+}
+
+fn sell_mode_active(sell_mode: Res<SellMode>) -> bool {
+    sell_mode.0
+}
+
+fn toggle_sell_mode(mut sell_mode: ResMut<SellMode>) {
+    sell_mode.0 = !sell_mode.0;
+}
+
+fn reset_sell_mode(mut sell_mode: ResMut<SellMode>) {
+    sell_mode.0 = false;
+}
+
+/// Shows whether a single tile of the previewed footprint is free. Markers are children of
+/// the preview and get reused, the ones not needed for the current footprint are hidden.
+#[derive(Reflect, Component)]
+#[reflect(Component)]
+struct PreviewMarker(usize);
+
+const MARKER_SIZE: f32 = TILE_SIZE * 0.4;
+
+/// Outcome of trying to place a tower, used for audio feedback
+#[derive(Event, Clone, Copy, PartialEq, Eq)]
+pub enum PlacementOutcome {
+    Placed,
+    Rejected,
+}
+
 pub fn place_tower(
     mut commands: Commands,
     mut event_writer: EventWriter<PathChangedEvent>,
-    window: Single<&Window, With<PrimaryWindow>>,
-    cam: Single<(&Camera, &GlobalTransform), With<Camera>>,
+    mut placement_events: EventWriter<PlacementOutcome>,
+    cursor: Res<PlacementCursor>,
     input: Res<ButtonInput<KeyCode>>,
     mut next_state: ResMut<NextState<TowerPlacingState>>,
     mut grid: ResMut<Grid>,
     tower: Res<SelectedTower>,
-    mut currency: ResMut<Currency>,
-    mut stats: ResMut<GameStatistics>,
+    mut wallet: Wallet,
+    mut history: ResMut<PlacementHistory>,
 ) {
-    let mouse_pos = window.cursor_position();
+    let Some(grid_pos) = cursor.0 else {
+        return;
+    };
+    if !wallet.affords(tower.cost()) {
+        placement_events.write(PlacementOutcome::Rejected);
+        return;
+    }
+
+    let grid_pos = apply_offset(grid_pos, &tower);
+    if !placement_valid(&grid, &tower.footprint(grid_pos)) {
+        placement_events.write(PlacementOutcome::Rejected);
+        return;
+    }
+
+    wallet.spend(tower.cost());
+    wallet.stats.towers_built += 1;
 
-    if let Some(mouse_pos) = mouse_pos {
-        if **currency < tower.cost() {
+    let (entity, blocked) = spawn_tower(&mut commands, &tower, grid_pos, &mut grid);
+    event_writer.write(PathChangedEvent::now_blocked(blocked));
+    history.push(entity, tower.cost());
+    placement_events.write(PlacementOutcome::Placed);
+
+    if !input.pressed(KeyCode::ShiftLeft) {
+        next_state.set(TowerPlacingState::None);
+    }
+}
+
+const MAX_UNDO_STEPS: usize = 10;
+
+/// The latest placed towers with what they cost, the newest one last
+#[derive(Resource, Default)]
+pub struct PlacementHistory(VecDeque<(Entity, i32)>);
+
+impl PlacementHistory {
+    fn push(&mut self, tower: Entity, cost: i32) {
+        if self.0.len() == MAX_UNDO_STEPS {
+            self.0.pop_front();
+        }
+        self.0.push_back((tower, cost));
+    }
+}
+
+fn reset_placement_history(mut commands: Commands) {
+    commands.insert_resource(PlacementHistory::default());
+}
+
+/// Ctrl+Z removes the latest placed tower for a full refund. Towers that were destroyed or
+/// sold in the meantime are skipped, towers that already took damage stay, so undoing can't
+/// be used as a free repair
+fn undo_placement(
+    mut commands: Commands,
+    mut history: ResMut<PlacementHistory>,
+    towers: Query<(&Tower, &Health)>,
+    mut grid: ResMut<Grid>,
+    mut wallet: Wallet,
+    mut path_change: EventWriter<PathChangedEvent>,
+) {
+    while let Some((entity, cost)) = history.0.back().copied() {
+        let Ok((tower, health)) = towers.get(entity) else {
+            history.0.pop_back();
+            continue;
+        };
+        if health.current < health.max {
+            info!(
+                "Can't undo placing the {:?}, it was already damaged",
+                tower.variant
+            );
             return;
         }
+        history.0.pop_back();
+        wallet.refund(cost);
+        wallet.stats.towers_built = wallet.stats.towers_built.saturating_sub(1);
+        path_change.write(PathChangedEvent::now_free(
+            tower.clear_grid(&mut grid, entity),
+        ));
+        commands.entity(entity).despawn();
+        return;
+    }
+}
 
-        let (camera, cam_transform) = *cam;
-
-        let world_pos = camera.viewport_to_world_2d(cam_transform, mouse_pos);
-        if let Ok(world_pos) = world_pos {
-            if let Some(grid_pos) = world_to_grid_coords(world_pos) {
-                let grid_pos =
-                    apply_offset(grid_pos, tower.0.variant, tower.0.orientation);
-
-                let tower_size = tower.size();
-
-                // Check if tiles are free
-                for i in 0..tower_size.0 {
-                    for j in 0..tower_size.1 {
-                        let pos = GridPos {
-                            col: grid_pos.col + i,
-                            row: grid_pos.row + j,
-                        };
-                        if !grid.is_free(&pos) {
-                            return;
-                        }
-
-                        if pos.col > COLUMNS - 1
-                            || pos.col < 0
-                            || pos.row > ROWS - 1
-                            || pos.row < 0
-                        {
-                            return;
-                        }
-                    }
-                }
-
-                let entity = commands
-                    .spawn((
-                        Name::new(format!(
-                            "Tower: {:?} ({:?})",
-                            tower.variant, tower.orientation
-                        )),
-                        Health::new(tower.max_hp(), tower.health_bar_offset()),
-                        tower.0.clone(),
-                        Sprite {
-                            color: Color::srgb(0.0, 0.5, 1.0),
-                            custom_size: Some(Vec2 {
-                                x: tower_size.0 as f32 * TILE_SIZE,
-                                y: tower_size.1 as f32 * TILE_SIZE,
-                            }),
-                            anchor: bevy::sprite::Anchor::BottomLeft,
-                            ..default()
-                        },
-                        Transform {
-                            translation: (grid_to_world_coords(grid_pos)
-                                - (TILE_SIZE * 0.5))
-                                .extend(1.0),
-                            ..default()
-                        },
-                    ))
-                    .id();
-
-                **currency -= tower.cost();
-                stats.money_spend += tower.cost();
-
-                event_writer.write(PathChangedEvent::now_blocked(
-                    tower.fill_grid(&grid_pos, &mut grid, entity),
-                ));
+/// Freeing the tiles and refunding is done by `sell_tower`, so selling works the same as
+/// through the tower menu
+fn sell_hovered_tower(
+    window: Single<&Window, With<PrimaryWindow>>,
+    cam: Single<(&Camera, &GlobalTransform), With<Camera>>,
+    grid: Res<Grid>,
+    config: Res<GridConfig>,
+    mut events: EventWriter<SellTower>,
+) {
+    let Some(mouse_pos) = window.cursor_position() else {
+        return;
+    };
+    let (camera, cam_transform) = *cam;
+    let tower = camera
+        .viewport_to_world_2d(cam_transform, mouse_pos)
+        .ok()
+        .and_then(|pos| world_to_grid_coords(pos, &config))
+        .and_then(|pos| grid.towers.get(&pos).copied());
+    if let Some(tower) = tower {
+        events.write(SellTower(tower));
+    }
+}
+
+/// Spawns the tower with the bottom left corner of its bounding box at `origin` and fills
+/// the grid, returns the tower and the tiles it now blocks. Whether they were free isn't
+/// checked.
+pub fn spawn_tower(
+    commands: &mut Commands,
+    tower: &Tower,
+    origin: GridPos,
+    grid: &mut Grid,
+) -> (Entity, Vec<GridPos>) {
+    let mut entity_cmds = commands.spawn((
+        Name::new(format!(
+            "Tower: {:?} ({:?})",
+            tower.variant, tower.orientation
+        )),
+        Health::new(tower.max_hp(), tower.health_bar_offset()),
+        tower.clone(),
+        Vision(tower.vision()),
+        Transform {
+            translation: (grid_to_world_coords(origin, &grid.config())
+                - (TILE_SIZE * 0.5))
+                .extend(1.0),
+            ..default()
+        },
+    ));
+    insert_tower_sprite(&mut entity_cmds, tower);
+    for level in 2..=tower.level {
+        entity_cmds.with_child(level_pip(level));
+    }
+    if let Some(turret) = turret(tower) {
+        entity_cmds.with_child(turret);
+    }
+    let entity = entity_cmds.id();
+    (entity, tower.fill_grid(&origin, grid, entity))
+}
 
-                if !input.pressed(KeyCode::ShiftLeft) {
-                    next_state.set(TowerPlacingState::None);
-                }
+/// Rectangular towers are a single sprite, other shapes get one sprite per covered tile
+fn insert_tower_sprite(entity_cmds: &mut EntityCommands, tower: &Tower) {
+    if tower.is_rectangular() {
+        let (width, height) = tower.size();
+        entity_cmds.insert((
+            ThemeColor::Tower(tower.variant),
+            Sprite {
+                custom_size: Some(Vec2::new(
+                    width as f32 * TILE_SIZE,
+                    height as f32 * TILE_SIZE,
+                )),
+                anchor: bevy::sprite::Anchor::BottomLeft,
+                ..default()
+            },
+        ));
+        return;
+    }
+
+    entity_cmds
+        .insert(Visibility::default())
+        .with_children(|p| {
+            for tile in tower.tiles() {
+                p.spawn((
+                    ThemeColor::Tower(tower.variant),
+                    Sprite {
+                        custom_size: Some(Vec2::splat(TILE_SIZE)),
+                        anchor: bevy::sprite::Anchor::BottomLeft,
+                        ..default()
+                    },
+                    Transform::from_xyz(
+                        tile.col as f32 * TILE_SIZE,
+                        tile.row as f32 * TILE_SIZE,
+                        0.,
+                    ),
+                ));
             }
-        } else {
-            warn!("Unable to get Cursor Position {:?}", world_pos.unwrap_err())
+        });
+}
+
+/// Minimum time between two placement sounds, so shift-dragging doesn't stack them up
+const PLACE_SFX_INTERVAL: f32 = 0.1;
+
+fn play_placement_sfx(
+    mut commands: Commands,
+    mut events: EventReader<PlacementOutcome>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    sfx: Query<(), With<Sfx>>,
+    settings: Res<Settings>,
+    asset_server: Res<AssetServer>,
+    time: Res<Time>,
+    mut last_placed: Local<Option<f32>>,
+) {
+    let (mut placed, mut rejected) = (false, false);
+    for outcome in events.read() {
+        match outcome {
+            PlacementOutcome::Placed => placed = true,
+            PlacementOutcome::Rejected => rejected = true,
         }
     }
+    if !settings.sfx_enabled {
+        return;
+    }
+
+    let now = time.elapsed_secs();
+    let mut playing = sfx.iter().count();
+    if placed {
+        if last_placed.is_none_or(|last| now - last >= PLACE_SFX_INTERVAL) {
+            *last_placed = Some(now);
+            play_sfx(
+                &mut commands,
+                &settings,
+                &mut playing,
+                asset_server.load("sfx/Toom Click.ogg"),
+                PlaybackSettings::DESPAWN,
+            );
+        }
+    // Only complain about actual clicks, not every frame of a drag over blocked tiles
+    } else if rejected && mouse.just_pressed(MouseButton::Left) {
+        play_sfx(
+            &mut commands,
+            &settings,
+            &mut playing,
+            asset_server.load("sfx/Cloud Click.ogg"),
+            PlaybackSettings::DESPAWN.with_speed(0.5),
+        );
+    }
+}
+
+const RANGE_RING_COLOR: Color = Color::srgba(1., 1., 1., 0.4);
+
+/// Ranges are measured from the bottom left corner of the tower, like in `Tower::covers`
+fn draw_preview_range(
+    mut gizmos: Gizmos,
+    preview: Single<(&Transform, &Visibility), With<TowerPreview>>,
+    tower: Res<SelectedTower>,
+    sell_mode: Res<SellMode>,
+) {
+    let (transform, visibility) = *preview;
+    if sell_mode.0 || *visibility == Visibility::Hidden || tower.range() <= 0. {
+        return;
+    }
+    draw_range(&mut gizmos, &tower, transform.translation.truncate());
+}
+
+fn draw_hovered_range(
+    mut gizmos: Gizmos,
+    window: Single<&Window, With<PrimaryWindow>>,
+    cam: Single<(&Camera, &GlobalTransform), With<Camera>>,
+    grid: Res<Grid>,
+    config: Res<GridConfig>,
+    towers: Query<(&Tower, &Transform)>,
+) {
+    let Some(mouse_pos) = window.cursor_position() else {
+        return;
+    };
+    let (camera, cam_transform) = *cam;
+    let hovered = camera
+        .viewport_to_world_2d(cam_transform, mouse_pos)
+        .ok()
+        .and_then(|pos| world_to_grid_coords(pos, &config))
+        .and_then(|pos| grid.towers.get(&pos))
+        .and_then(|entity| towers.get(*entity).ok());
+    if let Some((tower, transform)) = hovered.filter(|(tower, _)| tower.range() > 0.) {
+        draw_range(&mut gizmos, tower, transform.translation.truncate());
+    }
+}
+
+/// Beams are drawn as the line they hit, every other range as a ring
+fn draw_range(gizmos: &mut Gizmos, tower: &Tower, corner: Vec2) {
+    if !tower.is_beam() {
+        gizmos.circle_2d(corner, tower.range(), RANGE_RING_COLOR);
+        return;
+    }
+    let start = corner + TILE_SIZE * 0.5;
+    let direction = Vec2::from_angle(tower.orientation.angle());
+    gizmos.line_2d(start, start + direction * tower.range(), RANGE_RING_COLOR);
 }
 
 fn change_rotation(mut selection: ResMut<SelectedTower>) {
-    selection.orientation = match selection.orientation {
+    selection.orientation = rotate_clockwise(selection.orientation);
+}
+
+fn rotate_clockwise(orientation: Orientation) -> Orientation {
+    match orientation {
         Orientation::Up => Orientation::Right,
         Orientation::Right => Orientation::Down,
         Orientation::Down => Orientation::Left,
         Orientation::Left => Orientation::Up,
+    }
+}
+
+fn rotate_counter_clockwise(orientation: Orientation) -> Orientation {
+    match orientation {
+        Orientation::Up => Orientation::Left,
+        Orientation::Left => Orientation::Down,
+        Orientation::Down => Orientation::Right,
+        Orientation::Right => Orientation::Up,
+    }
+}
+
+/// Pixels a touchpad has to scroll for a single rotation step
+const SCROLL_PIXELS_PER_STEP: f32 = 50.;
+
+/// Scrolling up rotates clockwise like `R`, scrolling down the other way. Camera zoom is
+/// disabled while placing, so the wheel is free for this
+fn scroll_rotation(
+    mut selection: ResMut<SelectedTower>,
+    scroll: Res<AccumulatedMouseScroll>,
+    mut accumulated: Local<f32>,
+) {
+    if scroll.delta.y == 0. {
+        return;
+    }
+    *accumulated += match scroll.unit {
+        MouseScrollUnit::Line => scroll.delta.y,
+        MouseScrollUnit::Pixel => scroll.delta.y / SCROLL_PIXELS_PER_STEP,
     };
+    while accumulated.abs() >= 1. {
+        selection.orientation = match *accumulated > 0. {
+            true => rotate_clockwise(selection.orientation),
+            false => rotate_counter_clockwise(selection.orientation),
+        };
+        *accumulated -= accumulated.signum();
+    }
 }
 
 fn spawn_preview(mut commands: Commands) {
@@ -181,7 +650,6 @@ fn spawn_preview(mut commands: Commands) {
         Name::new("TowerPreview"),
         TowerPreview,
         Sprite {
-            color: Color::srgb(0.0, 0.5, 1.0),
             anchor: bevy::sprite::Anchor::BottomLeft,
             ..default()
         },
@@ -196,98 +664,184 @@ fn despawn_preview(mut commands: Commands, preview: Query<Entity, With<TowerPrev
 }
 
 fn update_preview(
-    window: Single<&Window, With<PrimaryWindow>>,
-    cam: Single<(&Camera, &GlobalTransform), With<Camera>>,
+    mut commands: Commands,
+    cursor: Res<PlacementCursor>,
     grid: Res<Grid>,
     tower: Res<SelectedTower>,
     currency: Res<Currency>,
+    sandbox: Res<Sandbox>,
+    theme: Res<Theme>,
+    sell_mode: Res<SellMode>,
     mut preview: Query<
-        (&mut Sprite, &mut Transform, &mut Visibility),
+        (Entity, &mut Sprite, &mut Transform, &mut Visibility),
         With<TowerPreview>,
     >,
+    mut markers: Query<
+        (&PreviewMarker, &mut Sprite, &mut Transform, &mut Visibility),
+        Without<TowerPreview>,
+    >,
 ) -> Result {
-    let (mut sprite, mut transform, mut visibility) = preview.single_mut()?;
-
-    let mouse_pos = window.cursor_position();
-
-    if let Some(mouse_pos) = mouse_pos {
-        let (camera, cam_transform) = *cam;
-
-        let world_pos = camera.viewport_to_world_2d(cam_transform, mouse_pos);
-        if let Ok(world_pos) = world_pos {
-            if let Some(grid_pos) = world_to_grid_coords(world_pos) {
-                let grid_pos = apply_offset(grid_pos, tower.0.variant, tower.orientation);
-
-                let tower_size = tower.size();
-
-                sprite.color = Color::srgb(0.0, 0.5, 1.0);
-
-                if **currency < tower.cost() {
-                    sprite.color = Color::srgb(1.0, 0.0, 0.0);
-                } else {
-                    // Check if tiles are free
-                    for i in 0..tower_size.0 {
-                        for j in 0..tower_size.1 {
-                            let pos = GridPos {
-                                col: grid_pos.col + i,
-                                row: grid_pos.row + j,
-                            };
-                            if !grid.is_free(&pos) {
-                                sprite.color = Color::srgb(1.0, 0.0, 0.0);
-                            }
-
-                            if pos.col > COLUMNS - 1
-                                || pos.col < 0
-                                || pos.row > ROWS - 1
-                                || pos.row < 0
-                            {
-                                sprite.color = Color::srgb(1.0, 0.0, 0.0);
-                            }
-                        }
-                    }
-                }
-
-                sprite.custom_size = Some(Vec2 {
-                    x: tower_size.0 as f32 * TILE_SIZE,
-                    y: tower_size.1 as f32 * TILE_SIZE,
-                });
-
-                transform.translation =
-                    (grid_to_world_coords(grid_pos) - (TILE_SIZE * 0.5)).extend(2.0);
-
-                *visibility = Visibility::Inherited;
-            } else {
-                *visibility = Visibility::Hidden;
-            }
-        } else {
-            warn!("Unable to get Cursor Position {:?}", world_pos.unwrap_err())
+    let (preview, mut sprite, mut transform, mut visibility) = preview.single_mut()?;
+    let config = grid.config();
+
+    let Some(grid_pos) = cursor.0 else {
+        *visibility = Visibility::Hidden;
+        return Ok(());
+    };
+
+    // Only the hovered tile is highlighted, selling removes the whole tower
+    if sell_mode.0 {
+        sprite.color = theme.preview_invalid.with_alpha(0.5);
+        sprite.custom_size = Some(Vec2::splat(TILE_SIZE));
+        transform.translation =
+            (grid_to_world_coords(grid_pos, &config) - (TILE_SIZE * 0.5)).extend(2.0);
+        for (_, _, _, mut m_visibility) in &mut markers {
+            *m_visibility = Visibility::Hidden;
         }
+        *visibility = Visibility::Inherited;
+        return Ok(());
+    }
+
+    let grid_pos = apply_offset(grid_pos, &tower);
+
+    let tower_size = tower.size();
+    let tiles = tower.footprint(grid_pos);
+
+    sprite.color = theme.tower(tower.variant);
+
+    if !sandbox.affords(&currency, tower.cost())
+        || !tiles.iter().all(|pos| tile_free(&grid, pos))
+    {
+        // Translucent, so the markers of the blocked tiles still stand out
+        sprite.color = theme.preview_invalid.with_alpha(0.5);
+    } else if blocks_path(&grid, &tiles) {
+        sprite.color = theme.preview_blocks_path.with_alpha(0.5);
     }
 
+    // The bounding box only hints at the shape, the markers show the real one
+    if !tower.is_rectangular() {
+        let alpha = sprite.color.alpha();
+        sprite.color.set_alpha(alpha * 0.3);
+    }
+
+    sprite.custom_size = Some(Vec2 {
+        x: tower_size.0 as f32 * TILE_SIZE,
+        y: tower_size.1 as f32 * TILE_SIZE,
+    });
+
+    transform.translation =
+        (grid_to_world_coords(grid_pos, &config) - (TILE_SIZE * 0.5)).extend(2.0);
+
+    let marker = |index: usize| {
+        let tile = tiles[index];
+        let color = match tile_free(&grid, &tile) {
+            true => theme.preview_valid,
+            false => theme.preview_invalid,
+        };
+        // Relative to the bottom left corner of the preview
+        let offset = Vec2::new(
+            (tile.col - grid_pos.col) as f32 + 0.5,
+            (tile.row - grid_pos.row) as f32 + 0.5,
+        ) * TILE_SIZE;
+        (color, Transform::from_translation(offset.extend(0.1)))
+    };
+
+    let mut existing = 0;
+    for (PreviewMarker(index), mut m_sprite, mut m_transform, mut m_visibility) in
+        &mut markers
+    {
+        existing = existing.max(index + 1);
+        if *index >= tiles.len() {
+            *m_visibility = Visibility::Hidden;
+            continue;
+        }
+        (m_sprite.color, *m_transform) = marker(*index);
+        *m_visibility = Visibility::Inherited;
+    }
+    for index in existing..tiles.len() {
+        let (color, m_transform) = marker(index);
+        commands.spawn((
+            Name::new(format!("PreviewMarker {index}")),
+            PreviewMarker(index),
+            Sprite::from_color(color, Vec2::splat(MARKER_SIZE)),
+            m_transform,
+            ChildOf(preview),
+        ));
+    }
+
+    *visibility = Visibility::Inherited;
+
     Ok(())
 }
 
-fn apply_offset(
-    grid_pos: GridPos,
-    tower: TowerType,
-    orientation: Orientation,
-) -> GridPos {
-    match orientation {
-        Orientation::Up => GridPos {
-            col: grid_pos.col - tower.offset().0,
-            row: grid_pos.row - tower.offset().1,
-        },
-        Orientation::Down => GridPos {
-            col: grid_pos.col - (tower.size().0 - 1 - tower.offset().0),
-            row: grid_pos.row - (tower.size().1 - 1 - tower.offset().1),
-        },
-        Orientation::Left => GridPos {
-            col: grid_pos.col - tower.offset().1,
-            row: grid_pos.row - tower.offset().0,
-        },
-        Orientation::Right => GridPos {
-            col: grid_pos.col - (tower.size().1 - 1 - tower.offset().1),
-            row: grid_pos.row - (tower.size().0 - 1 - tower.offset().0),
-        },
+fn tile_free(grid: &Grid, pos: &GridPos) -> bool {
+    pos.inside_grid_bounds(&grid.config()) && grid.is_free(pos)
+}
+
+fn placement_valid(grid: &Grid, tiles: &[GridPos]) -> bool {
+    tiles.iter().all(|pos| tile_free(grid, pos)) && !blocks_path(grid, tiles)
+}
+
+/// Placements cutting a spawner off from every goal are always rejected. The tower is not
+/// inserted yet, so the grid is checked as it would look like afterwards
+fn blocks_path(grid: &Grid, tiles: &[GridPos]) -> bool {
+    !grid.spawners_reach_goal(tiles)
+}
+
+/// Returns the origin of the tower's bounding box, so the tile the cursor is on ends up
+/// being the rotated `offset` tile of the tower
+fn apply_offset(grid_pos: GridPos, tower: &Tower) -> GridPos {
+    let (col, row) = tower.offset();
+    let offset = tower.rotate(GridPos::new(row, col));
+    GridPos::new(grid_pos.row - offset.row, grid_pos.col - offset.col)
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use crate::{game_loop::GameStatistics, test_app};
+
+    use super::*;
+
+    #[test]
+    fn undoing_a_placement_refunds_and_frees_the_grid() {
+        let mut app = test_app();
+        app.init_resource::<PlacementHistory>();
+        **app.world_mut().resource_mut::<Currency>() = 500;
+
+        // What `place_tower` does on a click
+        let tower = Tower::new(TowerType::Canon, Orientation::Up);
+        let cost = tower.cost();
+        let entity = app
+            .world_mut()
+            .run_system_once(
+                move |mut commands: Commands,
+                      mut grid: ResMut<Grid>,
+                      mut wallet: Wallet,
+                      mut history: ResMut<PlacementHistory>| {
+                    let (entity, _) =
+                        spawn_tower(&mut commands, &tower, GridPos::new(4, 4), &mut grid);
+                    wallet.spend(cost);
+                    wallet.stats.towers_built += 1;
+                    history.push(entity, cost);
+                    entity
+                },
+            )
+            .unwrap();
+        assert_eq!(**app.world().resource::<Currency>(), 500 - cost);
+
+        app.world_mut().run_system_once(undo_placement).unwrap();
+
+        let world = app.world();
+        assert_eq!(**world.resource::<Currency>(), 500);
+        let stats = world.resource::<GameStatistics>();
+        assert_eq!(stats.money_spend, 0);
+        assert_eq!(stats.towers_built, 0);
+        let grid = world.resource::<Grid>();
+        assert!(grid.towers.is_empty());
+        assert!(grid.tower_origins.is_empty());
+        assert!(world.get_entity(entity).is_err());
+        assert!(world.resource::<PlacementHistory>().0.is_empty());
     }
 }
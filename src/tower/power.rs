@@ -0,0 +1,96 @@
+use bevy::prelude::*;
+
+use crate::{Settings, app_state::AppState, theme::ThemeColor};
+
+use super::Tower;
+
+pub struct TowerPowerPlugin;
+
+impl Plugin for TowerPowerPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Power>()
+            .add_systems(OnEnter(AppState::Game), insert_power)
+            .add_systems(OnExit(AppState::Game), remove_power)
+            .add_systems(
+                Update,
+                update_power
+                    .run_if(in_state(AppState::Game))
+                    .run_if(|settings: Res<Settings>| settings.power),
+            );
+    }
+}
+
+/// Power produced and consumed by all towers. Only used when running with `--power`.
+#[derive(Resource, Reflect, Default, Debug)]
+#[reflect(Resource)]
+pub struct Power {
+    pub supply: i32,
+    pub demand: i32,
+}
+
+fn insert_power(mut commands: Commands) {
+    commands.insert_resource(Power::default());
+}
+
+fn remove_power(mut commands: Commands) {
+    commands.remove_resource::<Power>();
+}
+
+/// Towers are powered in the order they were placed, as soon as one can't be supplied
+/// anymore, it and every tower placed after it go offline
+pub(super) fn update_power(
+    mut power: ResMut<Power>,
+    mut towers: Query<(Entity, &mut Tower, &mut ThemeColor)>,
+    new_towers: Query<Entity, Added<Tower>>,
+    mut removed: RemovedComponents<Tower>,
+    mut next_placement: Local<u32>,
+) {
+    if new_towers.is_empty() && removed.read().count() == 0 {
+        return;
+    }
+
+    let mut new_towers: Vec<Entity> = new_towers.iter().collect();
+    new_towers.sort();
+    for entity in new_towers {
+        if let Ok((_, mut tower, _)) = towers.get_mut(entity) {
+            *next_placement += 1;
+            tower.placement = *next_placement;
+        }
+    }
+
+    let mut consumers = vec![];
+    power.supply = 0;
+    power.demand = 0;
+    for (entity, tower, _) in &towers {
+        match tower.power() {
+            p if p > 0 => power.supply += p,
+            p if p < 0 => {
+                power.demand -= p;
+                consumers.push((tower.placement, entity));
+            }
+            _ => {}
+        }
+    }
+    consumers.sort();
+
+    let mut available = power.supply;
+    for (_, entity) in consumers {
+        let Ok((_, mut tower, mut color)) = towers.get_mut(entity) else {
+            continue;
+        };
+        let powered = available + tower.power() >= 0;
+        // Keep newer towers from grabbing power an older one couldn't get
+        available = match powered {
+            true => available + tower.power(),
+            false => -1,
+        };
+
+        if tower.powered != powered {
+            tower.powered = powered;
+            *color = match powered {
+                true => ThemeColor::Tower(tower.variant),
+                false => ThemeColor::TowerOffline,
+            };
+        }
+    }
+}
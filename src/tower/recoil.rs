@@ -0,0 +1,131 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{Settings, app_state::GameState};
+
+use super::attack::TowerFired;
+
+const RECOIL_DURATION: Duration = Duration::from_millis(120);
+/// How far the tower gets pushed back from its target, in pixels
+const RECOIL_DISTANCE: f32 = 3.;
+/// Relative shrink of the tower at the peak of the recoil
+const RECOIL_SQUASH: f32 = 0.06;
+const FLASH_DURATION: Duration = Duration::from_millis(80);
+const FLASH_COLOR: Color = Color::srgb(1., 0.9, 0.4);
+/// Distance of the flash from the muzzle towards the target
+const FLASH_DISTANCE: f32 = 10.;
+
+pub struct TowerRecoilPlugin;
+
+impl Plugin for TowerRecoilPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Recoil>()
+            .register_type::<MuzzleFlash>()
+            .add_systems(
+                Update,
+                (
+                    start_recoil.run_if(on_event::<TowerFired>),
+                    animate_recoil,
+                    fade_muzzle_flashes,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Running)),
+            );
+    }
+}
+
+/// Pushes the tower away from its target right after firing and eases it back
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct Recoil {
+    /// Transform of the tower before it started recoiling
+    rest: Transform,
+    direction: Vec2,
+    timer: Timer,
+}
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct MuzzleFlash(Timer);
+
+fn start_recoil(
+    mut commands: Commands,
+    mut events: EventReader<TowerFired>,
+    mut towers: Query<(&Transform, Option<&mut Recoil>)>,
+    settings: Res<Settings>,
+) {
+    if settings.reduced_motion {
+        events.clear();
+        return;
+    }
+    for fired in events.read() {
+        let Ok((transform, recoil)) = towers.get_mut(fired.tower) else {
+            continue;
+        };
+        match recoil {
+            // Firing mid-animation restarts it from the original resting transform
+            Some(mut recoil) => {
+                recoil.direction = fired.direction;
+                recoil.timer.reset();
+            }
+            None => {
+                commands.entity(fired.tower).insert(Recoil {
+                    rest: *transform,
+                    direction: fired.direction,
+                    timer: Timer::new(RECOIL_DURATION, TimerMode::Once),
+                });
+            }
+        }
+
+        commands.spawn((
+            Name::new("Muzzle flash"),
+            MuzzleFlash(Timer::new(FLASH_DURATION, TimerMode::Once)),
+            Sprite::from_color(FLASH_COLOR, Vec2::splat(10.)),
+            Transform {
+                translation: fired.muzzle + (fired.direction * FLASH_DISTANCE).extend(0.),
+                rotation: Quat::from_rotation_z(fired.direction.to_angle()),
+                ..default()
+            },
+        ));
+    }
+}
+
+fn animate_recoil(
+    mut commands: Commands,
+    mut towers: Query<(Entity, &mut Transform, &mut Recoil)>,
+    time: Res<Time>,
+    settings: Res<Settings>,
+) {
+    for (entity, mut transform, mut recoil) in &mut towers {
+        recoil.timer.tick(time.delta());
+        // Reduced motion may have been turned on mid-animation
+        if recoil.timer.finished() || settings.reduced_motion {
+            *transform = recoil.rest;
+            commands.entity(entity).remove::<Recoil>();
+            continue;
+        }
+
+        // Kicks back immediately, then settles
+        let strength = recoil.timer.fraction_remaining();
+        transform.translation = recoil.rest.translation
+            - (recoil.direction * RECOIL_DISTANCE * strength).extend(0.);
+        transform.scale = recoil.rest.scale * (1. - RECOIL_SQUASH * strength);
+    }
+}
+
+fn fade_muzzle_flashes(
+    mut commands: Commands,
+    mut flashes: Query<(Entity, &mut MuzzleFlash, &mut Sprite)>,
+    time: Res<Time>,
+    settings: Res<Settings>,
+) {
+    for (entity, mut flash, mut sprite) in &mut flashes {
+        flash.0.tick(time.delta());
+        if flash.0.finished() || settings.reduced_motion {
+            commands.entity(entity).despawn();
+        } else {
+            sprite.color.set_alpha(flash.0.fraction_remaining());
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use bevy::{platform::collections::HashSet, prelude::*, time::Stopwatch};
+
+use crate::{app_state::GameState, enemy::Attacking, health::Health};
+
+use super::Tower;
+
+pub struct TowerRegenPlugin;
+
+impl Plugin for TowerRegenPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Regenerating>().add_systems(
+            Update,
+            (add_regeneration, regenerate)
+                .chain()
+                .run_if(in_state(GameState::Running)),
+        );
+    }
+}
+
+/// How a tower heals itself, see `TowerType::regen`
+#[derive(Reflect, Clone, Copy, Debug)]
+pub struct Regen {
+    /// Health per second
+    pub rate: f32,
+    /// Seconds without taking damage before the tower starts healing
+    pub delay_after_hit: f32,
+}
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct Regenerating {
+    regen: Regen,
+    since_hit: Stopwatch,
+    /// Health from the last frame, any drop counts as a hit no matter where it came from
+    last_health: isize,
+    /// Fractional health healed so far, as `Health` only holds whole points
+    pending: f32,
+}
+
+fn add_regeneration(
+    mut commands: Commands,
+    towers: Query<(Entity, &Tower, &Health), Added<Tower>>,
+) {
+    for (entity, tower, health) in &towers {
+        let Some(regen) = tower.regen() else {
+            continue;
+        };
+        commands.entity(entity).insert(Regenerating {
+            regen,
+            since_hit: Stopwatch::new(),
+            last_health: health.current,
+            pending: 0.,
+        });
+    }
+}
+
+fn regenerate(
+    mut towers: Query<(Entity, &mut Regenerating, &mut Health)>,
+    attackers: Query<&Attacking>,
+    time: Res<Time>,
+) {
+    let attacked: HashSet<Entity> = attackers.iter().map(Attacking::target).collect();
+
+    for (entity, mut regen, mut health) in &mut towers {
+        if health.current < regen.last_health || attacked.contains(&entity) {
+            regen.since_hit.reset();
+            regen.pending = 0.;
+        } else {
+            regen.since_hit.tick(time.delta());
+        }
+
+        if regen.since_hit.elapsed_secs() >= regen.regen.delay_after_hit
+            && health.current < health.max
+        {
+            regen.pending += regen.regen.rate * time.delta_secs();
+            let healed = regen.pending.floor();
+            regen.pending -= healed;
+            // Only touch the health when it actually changes, health bars react to that
+            if healed > 0. {
+                health.current = (health.current + healed as isize).min(health.max);
+            }
+        }
+        regen.last_health = health.current;
+    }
+}
@@ -0,0 +1,109 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+
+use crate::{Settings, app_state::GameState, grid::TILE_SIZE};
+
+use super::{Tower, TowerType, attack::TowerFired};
+
+/// Radians per second a turret turns towards its target
+const TURRET_TURN_SPEED: f32 = 2. * PI;
+const TURRET_COLOR: Color = Color::srgb(0.25, 0.25, 0.3);
+
+pub struct TowerTurretPlugin;
+
+impl Plugin for TowerTurretPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Turret>().add_systems(
+            Update,
+            (track_targets.run_if(on_event::<TowerFired>), aim_turrets)
+                .chain()
+                .run_if(in_state(GameState::Running)),
+        );
+    }
+}
+
+/// Barrel on top of a Canon, pointing at the enemy it last fired at while that one is in
+/// range, and in the direction the Canon was placed in otherwise
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+struct Turret {
+    target: Option<Entity>,
+}
+
+/// Only Canons have a turret, spawned as a child in their center
+pub(super) fn turret(tower: &Tower) -> Option<impl Bundle> {
+    if tower.variant != TowerType::Canon {
+        return None;
+    }
+    let (width, height) = tower.size();
+    Some((
+        Name::new("Turret"),
+        Turret::default(),
+        Sprite::from_color(TURRET_COLOR, Vec2::splat(TILE_SIZE)),
+        Transform {
+            translation: Vec3::new(
+                width as f32 * TILE_SIZE * 0.5,
+                height as f32 * TILE_SIZE * 0.5,
+                1.,
+            ),
+            rotation: Quat::from_rotation_z(tower.orientation.angle()),
+            ..default()
+        },
+        children![(
+            Name::new("Barrel"),
+            Sprite {
+                color: TURRET_COLOR,
+                custom_size: Some(Vec2::new(TILE_SIZE * 1.3, TILE_SIZE * 0.4)),
+                anchor: bevy::sprite::Anchor::CenterLeft,
+                ..default()
+            },
+        )],
+    ))
+}
+
+fn track_targets(
+    mut events: EventReader<TowerFired>,
+    mut turrets: Query<(&mut Turret, &ChildOf)>,
+) {
+    for fired in events.read() {
+        for (mut turret, child_of) in &mut turrets {
+            if child_of.parent() == fired.tower {
+                turret.target = Some(fired.target);
+            }
+        }
+    }
+}
+
+fn aim_turrets(
+    mut turrets: Query<(&mut Turret, &mut Transform, &GlobalTransform, &ChildOf)>,
+    towers: Query<(&Tower, &Transform), Without<Turret>>,
+    enemies: Query<&GlobalTransform, Without<Turret>>,
+    time: Res<Time>,
+    settings: Res<Settings>,
+) {
+    for (mut turret, mut transform, global, child_of) in &mut turrets {
+        let Ok((tower, tower_transform)) = towers.get(child_of.parent()) else {
+            continue;
+        };
+        // Targets that died or left the range are dropped, the turret returns to idle
+        let aim = turret.target.and_then(|target| {
+            let target = enemies.get(target).ok()?.translation();
+            tower
+                .covers(tower_transform.translation, target)
+                .then(|| (target - global.translation()).truncate())
+        });
+        if aim.is_none() {
+            turret.target = None;
+        }
+        let angle = aim.map_or(tower.orientation.angle(), |aim| aim.to_angle());
+
+        let goal = Quat::from_rotation_z(angle);
+        transform.rotation = match settings.reduced_motion {
+            true => goal,
+            false => transform
+                .rotation
+                .rotate_towards(goal, TURRET_TURN_SPEED * time.delta_secs()),
+        };
+    }
+}
@@ -5,7 +5,10 @@ use bevy_lunex::{
     prelude::Anchor,
 };
 
-use crate::Settings;
+use crate::{
+    Settings,
+    soundtrack::{Sfx, play_sfx},
+};
 
 pub fn build_menu<T: Component, F>(
     commands: &mut Commands,
@@ -139,12 +142,15 @@ fn build_button<F>(
             |_: Trigger<Pointer<Click>>,
              settings: Res<Settings>,
              asset_server: Res<AssetServer>,
+             sfx: Query<(), With<Sfx>>,
              mut commands: Commands| {
-                if settings.sfx_enabled {
-                    commands.spawn(AudioPlayer::new(
-                        asset_server.load("sfx/Cloud Click.ogg"),
-                    ));
-                }
+                play_sfx(
+                    &mut commands,
+                    &settings,
+                    &mut sfx.iter().count(),
+                    asset_server.load("sfx/Cloud Click.ogg"),
+                    PlaybackSettings::DESPAWN,
+                );
             },
         );
 }
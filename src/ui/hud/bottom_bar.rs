@@ -2,7 +2,7 @@ use bevy::prelude::*;
 use bevy_lunex::{Ab, Align, Rl, UiFetchFromCamera, UiLayout, UiLayoutRoot};
 use player_health::update_player_health;
 
-use crate::app_state::AppState;
+use crate::{Settings, app_state::AppState, theme::Theme};
 
 pub struct BottomBarPlugin;
 
@@ -23,6 +23,8 @@ fn build_ui(
     mut commands: Commands,
     mut materials: ResMut<Assets<ColorMaterial>>,
     mut meshes: ResMut<Assets<Mesh>>,
+    settings: Res<Settings>,
+    theme: Res<Theme>,
 ) {
     commands
         .spawn((
@@ -49,7 +51,9 @@ fn build_ui(
                         .align_x(Align::LEFT)
                         .pack(),
                 ))
-                .with_children(|p| tower_selection::build(p, &mut materials));
+                .with_children(|p| {
+                    tower_selection::build(p, &mut materials, &settings, &theme)
+                });
                 ui.spawn((Name::new("Player health bar"), UiLayout::solid().pack()))
                     .with_children(|p| {
                         player_health::build(p, &mut materials, &mut meshes)
@@ -68,13 +72,12 @@ mod tower_selection {
     use crate::{
         Settings,
         app_state::TowerPlacingState,
+        soundtrack::{Sfx, play_sfx},
+        theme::Theme,
         tower::{SelectedTower, Tower, TowerType},
         ui::helpers::ui_hover_state,
     };
 
-    // I would have automated this but I don't think it is possible :/
-    const TYPES: [TowerType; 3] =
-        [TowerType::Wall, TowerType::SpikedWall, TowerType::Canon];
     const TILE_SIZE_PX: f32 = 30.0;
 
     const BUTTON_COLOR: Color = Color::srgb(0.3, 0.3, 0.3);
@@ -83,8 +86,13 @@ mod tower_selection {
     pub fn build(
         builder: &mut ChildSpawnerCommands,
         materials: &mut Assets<ColorMaterial>,
+        settings: &Settings,
+        theme: &Theme,
     ) {
-        for (index, tower) in TYPES.iter().enumerate() {
+        let types = TowerType::ALL
+            .iter()
+            .filter(|tower| tower.available(settings));
+        for (index, tower) in types.enumerate() {
             builder
                 .spawn((
                     Name::new(format!("TowerButton: {tower:?}")),
@@ -133,7 +141,7 @@ mod tower_selection {
                             .size((Ab(icon_width), Ab(icon_height)))
                             .pack(),
                         UiMeshPlane2d,
-                        MeshMaterial2d(materials.add(Color::srgb(0., 0.5, 1.))),
+                        MeshMaterial2d(materials.add(theme.tower(*tower))),
                         Pickable::IGNORE,
                     ));
                 })
@@ -147,12 +155,15 @@ mod tower_selection {
                      mut next_state: ResMut<NextState<TowerPlacingState>>,
                      asset_server: Res<AssetServer>,
                      mut commands: Commands,
+                     sfx: Query<(), With<Sfx>>,
                      settings: Res<Settings>| {
-                        if settings.sfx_enabled {
-                            commands.spawn(AudioPlayer::new(
-                                asset_server.load("sfx/Cloud Click.ogg"),
-                            ));
-                        }
+                        play_sfx(
+                            &mut commands,
+                            &settings,
+                            &mut sfx.iter().count(),
+                            asset_server.load("sfx/Cloud Click.ogg"),
+                            PlaybackSettings::DESPAWN,
+                        );
                         selection.0 = Tower::new(*tower, selection.orientation);
                         next_state.set(TowerPlacingState::Placing);
                     },
@@ -219,14 +230,22 @@ mod player_health {
             .observe(ui_hover_state::<Pointer<Out>, false>);
     }
 
+    /// Levels can have several goals, the most damaged one is shown
     pub fn update_player_health(
-        query: Query<&Health, (With<EnemyGoal>, Changed<Health>)>,
+        goals: Query<&Health, With<EnemyGoal>>,
+        changed: Query<(), (With<EnemyGoal>, Changed<Health>)>,
         mut meshes: ResMut<Assets<Mesh>>,
         mut hp_circle: Single<(Entity, &PlayerHpCircle, &mut Transform)>,
         mut hp_text: Single<&mut Text2d, With<PlayerHpTextMarker>>,
         mut commands: Commands,
     ) {
-        if let Ok(health) = query.single() {
+        if changed.is_empty() {
+            return;
+        }
+        let most_damaged = goals
+            .iter()
+            .min_by(|a, b| a.percentage().total_cmp(&b.percentage()));
+        if let Some(health) = most_damaged {
             commands.entity(hp_circle.0).insert(Mesh2d(
                 meshes.add(CircularSector::new(hp_circle.1.0, health.percentage() * PI)),
             ));
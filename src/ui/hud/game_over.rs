@@ -1,18 +1,26 @@
 use bevy::prelude::*;
 
 use crate::{
-    app_state::GameState,
-    game_loop::GameStatistics,
+    app_state::{AppState, GameState, WaveState},
+    game_loop::{GameStatistics, WaveInfo},
     ui::{TEXT_COLOR, despawn_menu, helpers::ui_hover_state},
 };
 
+use super::tower_menu::menu_button;
+
+const BUTTON_COLUMN_WIDTH: f32 = 200.;
+
 pub struct GameOverPlugin;
 
 impl Plugin for GameOverPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<GameOverMarker>()
             .add_systems(OnEnter(GameState::GameOver), build_game_over_info)
-            .add_systems(OnExit(GameState::GameOver), despawn_menu::<GameOverMarker>);
+            .add_systems(OnExit(GameState::GameOver), despawn_menu::<GameOverMarker>)
+            .add_systems(
+                OnEnter(AppState::Menu),
+                restart_game.run_if(resource_exists::<PendingRestart>),
+            );
     }
 }
 
@@ -20,6 +28,16 @@ impl Plugin for GameOverPlugin {
 #[reflect(Component)]
 struct GameOverMarker;
 
+/// Restarting goes through the menu like loading a save does, so the old game is cleared
+/// by leaving `AppState::Game` and the new one is set up from scratch when entering it
+#[derive(Resource)]
+struct PendingRestart;
+
+fn restart_game(mut commands: Commands, mut next_state: ResMut<NextState<AppState>>) {
+    commands.remove_resource::<PendingRestart>();
+    next_state.set(AppState::Game);
+}
+
 fn stat_row(p: &mut ChildSpawnerCommands, key: impl ToString, value: impl ToString) {
     p.spawn(Node {
         width: Val::Percent(100.),
@@ -47,7 +65,13 @@ fn stat_row(p: &mut ChildSpawnerCommands, key: impl ToString, value: impl ToStri
     });
 }
 
-fn build_game_over_info(mut commands: Commands, stats: Res<GameStatistics>) {
+fn build_game_over_info(
+    mut commands: Commands,
+    stats: Res<GameStatistics>,
+    wave: Res<WaveInfo>,
+    wave_state: Res<State<WaveState>>,
+) {
+    let seconds = stats.time.elapsed_secs() as u32;
     commands
         .spawn((
             Node {
@@ -81,20 +105,92 @@ fn build_game_over_info(mut commands: Commands, stats: Res<GameStatistics>) {
                     TextColor(TEXT_COLOR),
                 ));
 
+                stat_row(
+                    p,
+                    "Waves survived:",
+                    format!("{}/{}", wave.survived(*wave_state.get()), wave.last),
+                );
                 stat_row(
                     p,
                     "Time elapsed:",
-                    format!(
-                        "{}min {}sec",
-                        (stats.time.elapsed_secs() / 60.).round(),
-                        (stats.time.elapsed_secs() % 60.).round()
-                    ),
+                    format!("{}min {}sec", seconds / 60, seconds % 60),
                 );
                 stat_row(p, "Enemies killed:", stats.enemies_killed);
+                stat_row(p, "Towers built:", stats.towers_built);
                 stat_row(p, "Money earned:", stats.money_earned);
                 stat_row(p, "Money spend:", stats.money_spend);
+                p.spawn(Node {
+                    width: Val::Px(BUTTON_COLUMN_WIDTH),
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(10.),
+                    margin: UiRect::top(Val::Px(20.)),
+                    ..Default::default()
+                })
+                .with_children(|p| {
+                    menu_button(p, "Restart".to_string()).observe(
+                        |_: Trigger<Pointer<Click>>,
+                         mut commands: Commands,
+                         mut next_state: ResMut<NextState<AppState>>| {
+                            commands.insert_resource(PendingRestart);
+                            next_state.set(AppState::Menu);
+                        },
+                    );
+                    menu_button(p, "Quit to menu".to_string()).observe(
+                        |_: Trigger<Pointer<Click>>,
+                         mut next_state: ResMut<NextState<AppState>>| {
+                            next_state.set(AppState::Menu);
+                        },
+                    );
+                });
             })
             .observe(ui_hover_state::<Pointer<Over>, true>)
             .observe(ui_hover_state::<Pointer<Out>, false>);
         });
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use crate::{
+        Orientation,
+        grid::{Grid, GridPlugin, GridPos},
+        test_app,
+        tower::{Tower, TowerType, spawn_tower},
+    };
+
+    use super::*;
+
+    #[test]
+    fn restarting_starts_with_an_empty_grid() {
+        let mut app = test_app();
+        app.add_plugins((GridPlugin, GameOverPlugin));
+        app.update();
+
+        app.world_mut()
+            .run_system_once(|mut commands: Commands, mut grid: ResMut<Grid>| {
+                let tower = Tower::new(TowerType::Canon, Orientation::Up);
+                spawn_tower(&mut commands, &tower, GridPos::new(4, 4), &mut grid);
+            })
+            .unwrap();
+        assert!(!app.world().resource::<Grid>().towers.is_empty());
+
+        // What the restart button does
+        app.insert_resource(PendingRestart);
+        app.world_mut()
+            .resource_mut::<NextState<AppState>>()
+            .set(AppState::Menu);
+        // Through the menu and back into the game
+        app.update();
+        app.update();
+
+        assert_eq!(
+            *app.world().resource::<State<AppState>>().get(),
+            AppState::Game
+        );
+        assert!(!app.world().contains_resource::<PendingRestart>());
+        let grid = app.world().resource::<Grid>();
+        assert!(grid.towers.is_empty());
+        assert!(grid.tower_origins.is_empty());
+    }
+}
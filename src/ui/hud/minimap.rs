@@ -0,0 +1,145 @@
+use bevy::{
+    asset::RenderAssetUsages,
+    image::ImageSampler,
+    input::common_conditions::input_just_pressed,
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+
+use crate::{
+    app_state::{AppState, UiHoverState},
+    enemy::Enemy,
+    grid::{Grid, GridConfig, world_to_grid_coords},
+    ui::helpers::ui_hover_state,
+};
+
+pub struct MinimapPlugin;
+
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Minimap>()
+            .add_systems(OnEnter(AppState::Game), spawn_minimap)
+            .add_systems(
+                Update,
+                (
+                    toggle_minimap.run_if(input_just_pressed(KeyCode::KeyM)),
+                    draw_minimap,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::Game)),
+            );
+    }
+}
+
+/// Screen pixels per tile
+const MINIMAP_SCALE: f32 = 3.;
+const BACKGROUND_COLOR: Color = Color::srgba(0., 0., 0., 0.75);
+const TOWER_COLOR: Color = Color::srgb(0.5, 0.6, 0.8);
+const SPAWNER_COLOR: Color = Color::srgb(1., 0.2, 0.2);
+const GOAL_COLOR: Color = Color::WHITE;
+const ENEMY_COLOR: Color = Color::srgb(1., 0.9, 0.);
+const TERRAIN_COLOR: Color = Color::srgb(0.3, 0.3, 0.3);
+
+/// Overview of the whole board in the top right corner, toggled with `M`. One pixel per
+/// tile, redrawn every frame while shown
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct Minimap;
+
+fn spawn_minimap(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    config: Res<GridConfig>,
+) {
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: config.columns as u32,
+            height: config.rows as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0, 0, 0, 0],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    image.sampler = ImageSampler::nearest();
+
+    commands
+        .spawn((
+            Name::new("Minimap"),
+            Minimap,
+            ImageNode::new(images.add(image)),
+            Node {
+                position_type: PositionType::Absolute,
+                right: Val::Px(10.),
+                top: Val::Px(80.),
+                width: Val::Px(config.columns as f32 * MINIMAP_SCALE),
+                height: Val::Px(config.rows as f32 * MINIMAP_SCALE),
+                ..default()
+            },
+            Visibility::Hidden,
+        ))
+        .observe(ui_hover_state::<Pointer<Over>, true>)
+        .observe(ui_hover_state::<Pointer<Out>, false>);
+}
+
+fn toggle_minimap(
+    mut minimap: Single<&mut Visibility, With<Minimap>>,
+    mut next_state: ResMut<NextState<UiHoverState>>,
+) {
+    **minimap = match **minimap {
+        Visibility::Hidden => Visibility::Inherited,
+        _ => {
+            // Hiding it under the cursor doesn't trigger `Pointer<Out>`
+            next_state.set(UiHoverState::None);
+            Visibility::Hidden
+        }
+    };
+}
+
+fn draw_minimap(
+    minimap: Single<(&ImageNode, &Visibility), With<Minimap>>,
+    grid: Res<Grid>,
+    enemies: Query<&Transform, With<Enemy>>,
+    mut images: ResMut<Assets<Image>>,
+    config: Res<GridConfig>,
+) {
+    let (node, visibility) = *minimap;
+    if *visibility == Visibility::Hidden {
+        return;
+    }
+    let Some(image) = images.get_mut(&node.image) else {
+        return;
+    };
+
+    let columns = config.columns as usize;
+    let mut pixels = vec![BACKGROUND_COLOR; columns * config.rows as usize];
+    let marked = grid
+        .terrain
+        .iter()
+        .map(|pos| (*pos, TERRAIN_COLOR))
+        .chain(grid.towers.keys().map(|pos| (*pos, TOWER_COLOR)))
+        .chain(grid.enemy_spawners.keys().map(|pos| (*pos, SPAWNER_COLOR)))
+        .chain(grid.enemy_goals.keys().map(|pos| (*pos, GOAL_COLOR)))
+        .chain(
+            enemies
+                .iter()
+                .filter_map(|transform| {
+                    world_to_grid_coords(transform.translation.truncate(), &config)
+                })
+                .map(|pos| (pos, ENEMY_COLOR)),
+        );
+    for (pos, color) in marked.filter(|(pos, _)| pos.inside_grid_bounds(&config)) {
+        // Image rows start at the top, grid rows at the bottom
+        let row = (config.rows - 1 - pos.row) as usize;
+        pixels[row * columns + pos.col as usize] = color;
+    }
+
+    for (index, color) in pixels.into_iter().enumerate() {
+        let (x, y) = ((index % columns) as u32, (index / columns) as u32);
+        if let Err(e) = image.set_color_at(x, y, color) {
+            error!("Failed to update the minimap: {e}");
+            return;
+        }
+    }
+}
@@ -1,14 +1,20 @@
 use bevy::prelude::*;
 use bottom_bar::BottomBarPlugin;
 use game_over::GameOverPlugin;
+use minimap::MinimapPlugin;
 use paused::PausedPlugin;
 use top_bar::TopBarPlugin;
+use tower_info::TowerInfoPlugin;
+use tower_menu::TowerMenuPlugin;
 use wave_start::WaveStartPlugin;
 
 mod bottom_bar;
 mod game_over;
+mod minimap;
 mod paused;
 mod top_bar;
+mod tower_info;
+mod tower_menu;
 mod wave_start;
 
 pub struct HUDPlugin;
@@ -21,6 +27,9 @@ impl Plugin for HUDPlugin {
             WaveStartPlugin,
             GameOverPlugin,
             PausedPlugin,
+            TowerMenuPlugin,
+            MinimapPlugin,
+            TowerInfoPlugin,
         ));
     }
 }
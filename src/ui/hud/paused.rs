@@ -1,10 +1,18 @@
-use bevy::{input::common_conditions::input_just_pressed, prelude::*};
+use bevy::{
+    input::common_conditions::input_just_pressed, prelude::*, window::WindowFocused,
+};
 
 use crate::{
+    Settings,
     app_state::{AppState, GameState},
+    save::SaveGame,
     ui::{TEXT_COLOR, despawn_menu},
 };
 
+use super::tower_menu::menu_button;
+
+const BUTTON_COLUMN_WIDTH: f32 = 200.;
+
 pub struct PausedPlugin;
 
 impl Plugin for PausedPlugin {
@@ -14,9 +22,14 @@ impl Plugin for PausedPlugin {
             .add_systems(OnExit(GameState::Paused), despawn_menu::<PausedMarker>)
             .add_systems(
                 Update,
-                toggle_paused_state
-                    .run_if(in_state(AppState::Game))
-                    .run_if(input_just_pressed(KeyCode::KeyP)),
+                (
+                    toggle_paused_state
+                        .run_if(in_state(AppState::Game))
+                        .run_if(input_just_pressed(KeyCode::KeyP)),
+                    pause_on_focus_loss
+                        .run_if(on_event::<WindowFocused>)
+                        .run_if(|settings: Res<Settings>| settings.focus_pause),
+                ),
             );
     }
 }
@@ -32,6 +45,43 @@ fn toggle_paused_state(
     });
 }
 
+/// Only pauses a running game, menus and the game over screen are left alone. If the game
+/// got paused this way, it is resumed on focus gain unless `--focus-pause-manual` is set
+fn pause_on_focus_loss(
+    mut events: EventReader<WindowFocused>,
+    settings: Res<Settings>,
+    state: Option<Res<State<GameState>>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut time: ResMut<Time<Virtual>>,
+    mut auto_paused: Local<bool>,
+) {
+    let Some(focused) = events.read().last().map(|event| event.focused) else {
+        return;
+    };
+    let current = state.map(|state| *state.get());
+
+    match focused {
+        false => {
+            if current == Some(GameState::Running) {
+                next_state.set(GameState::Paused);
+                *auto_paused = true;
+            }
+            // Time stands still while unfocused, so the first frame after resuming
+            // doesn't catch up on everything that happened in the meantime
+            time.pause();
+        }
+        true => {
+            time.unpause();
+            if std::mem::take(&mut *auto_paused)
+                && settings.focus_resume
+                && current == Some(GameState::Paused)
+            {
+                next_state.set(GameState::Running);
+            }
+        }
+    }
+}
+
 #[derive(Component, Reflect)]
 #[reflect(Component)]
 struct PausedMarker;
@@ -69,13 +119,40 @@ fn build_paused_info(mut commands: Commands) {
                     TextColor(TEXT_COLOR),
                 ));
                 p.spawn((
-                    Text::new("Press P to unpause"),
+                    Text::new("Press Escape or P to resume"),
                     TextFont {
                         font_size: 40.,
                         ..Default::default()
                     },
                     TextColor(TEXT_COLOR),
                 ));
+                p.spawn(Node {
+                    width: Val::Px(BUTTON_COLUMN_WIDTH),
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(10.),
+                    margin: UiRect::top(Val::Px(20.)),
+                    ..Default::default()
+                })
+                .with_children(|p| {
+                    menu_button(p, "Resume".to_string()).observe(
+                        |_: Trigger<Pointer<Click>>,
+                         mut next_state: ResMut<NextState<GameState>>| {
+                            next_state.set(GameState::Running);
+                        },
+                    );
+                    menu_button(p, "Save".to_string()).observe(
+                        |_: Trigger<Pointer<Click>>,
+                         mut events: EventWriter<SaveGame>| {
+                            events.write(SaveGame);
+                        },
+                    );
+                    menu_button(p, "Quit to menu".to_string()).observe(
+                        |_: Trigger<Pointer<Click>>,
+                         mut next_state: ResMut<NextState<AppState>>| {
+                            next_state.set(AppState::Menu);
+                        },
+                    );
+                });
             });
         });
 }
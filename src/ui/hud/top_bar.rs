@@ -1,12 +1,18 @@
-use bevy::prelude::*;
+use std::time::Duration;
+
+use bevy::{color::Mix, prelude::*};
 use bevy_lunex::{
     Ab, Align, Rh, Rl, UiFetchFromCamera, UiLayout, UiLayoutRoot, UiMeshPlane2d,
     UiTextSize,
 };
 
 use crate::{
-    app_state::{AppState, GameState},
-    game_loop::{Currency, WaveInfo, WaveStart, insert_wave_info},
+    app_state::{AppState, GameState, TowerPlacingState},
+    game_loop::{
+        Currency, Difficulty, GameSpeed, GameStatistics, Sandbox, WaveInfo, WaveStart,
+        insert_wave_info,
+    },
+    tower::SelectedTower,
     ui::helpers::ui_hover_state,
 };
 
@@ -15,19 +21,29 @@ pub struct TopBarPlugin;
 impl Plugin for TopBarPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<CurrencyInfoMarker>()
+            .register_type::<CurrencyTween>()
             .register_type::<WaveInfoMarker>()
+            .register_type::<SpeedInfoMarker>()
+            .register_type::<StatsInfoMarker>()
             .add_systems(OnEnter(AppState::Game), build_ui.after(insert_wave_info))
             .add_systems(
                 Update,
                 (
                     update_wave.run_if(on_event::<WaveStart>),
                     update_currency.run_if(in_state(GameState::Running)),
+                    update_speed.run_if(resource_exists_and_changed::<GameSpeed>),
+                    update_stats.run_if(resource_exists_and_changed::<GameStatistics>),
                 ),
             );
     }
 }
 
 const UI_INFO_BACKGROUND: Color = Color::srgba(0., 0., 0., 0.85);
+const CURRENCY_TWEEN_TIME: Duration = Duration::from_millis(300);
+const CURRENCY_GAIN_COLOR: Srgba = Srgba::rgb(0., 1., 0.);
+const CURRENCY_SPEND_COLOR: Srgba = Srgba::rgb(1., 0., 0.);
+/// The currency stays in this color while it isn't enough for the tower being placed
+const CURRENCY_LACKING_COLOR: Srgba = Srgba::rgb(1., 0.3, 0.3);
 
 #[derive(Component, Reflect)]
 #[reflect(Component)]
@@ -37,11 +53,45 @@ struct WaveInfoMarker;
 #[reflect(Component)]
 struct CurrencyInfoMarker;
 
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct SpeedInfoMarker;
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct StatsInfoMarker;
+
+/// Animates the displayed currency towards the actual value of the `Currency` resource
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct CurrencyTween {
+    /// Value displayed when the tween started
+    start: f32,
+    target: i32,
+    displayed: Option<f32>,
+    /// Color the text flashes in, fading back to white
+    flash: Srgba,
+    timer: Timer,
+}
+
+impl Default for CurrencyTween {
+    fn default() -> Self {
+        Self {
+            start: 0.,
+            target: 0,
+            displayed: None,
+            flash: Srgba::WHITE,
+            timer: Timer::new(CURRENCY_TWEEN_TIME, TimerMode::Once),
+        }
+    }
+}
+
 fn build_ui(
     camera: Single<Entity, With<Camera>>,
     mut commands: Commands,
     mut materials: ResMut<Assets<ColorMaterial>>,
     wave: Res<WaveInfo>,
+    difficulty: Res<Difficulty>,
 ) {
     commands
         .spawn((
@@ -63,7 +113,7 @@ fn build_ui(
                 ui.spawn((
                     Name::new("Wave info"),
                     UiLayout::solid()
-                        .size((Ab(250.), Rl(100.)))
+                        .size((Ab(320.), Rl(100.)))
                         .align_x(Align::LEFT)
                         .pack(),
                     UiMeshPlane2d,
@@ -73,7 +123,7 @@ fn build_ui(
                     WaveInfoMarker,
                     Transform::from_translation(Vec3::Z * 5.),
                     UiTextSize::from(Rh(5.)),
-                    Text2d::new(format!("Wave 0/{}", wave.last)),
+                    Text2d::new(wave_label(0, wave.last, *difficulty)),
                 ))
                 .observe(ui_hover_state::<Pointer<Over>, true>)
                 .observe(ui_hover_state::<Pointer<Out>, false>);
@@ -89,31 +139,146 @@ fn build_ui(
                 ))
                 .with_child((
                     CurrencyInfoMarker,
+                    CurrencyTween::default(),
                     Transform::from_translation(Vec3::Z * 5.),
                     UiTextSize::from(Rh(5.)),
                     Text2d::new(""),
                 ))
                 .observe(ui_hover_state::<Pointer<Over>, true>)
                 .observe(ui_hover_state::<Pointer<Out>, false>);
+
+                ui.spawn((
+                    Name::new("Stats info"),
+                    UiLayout::solid()
+                        .size((Ab(260.), Rl(100.)))
+                        // Between the currency and the speed
+                        .align_x(Align(0.6))
+                        .pack(),
+                    UiMeshPlane2d,
+                    MeshMaterial2d(materials.add(UI_INFO_BACKGROUND)),
+                ))
+                .with_child((
+                    StatsInfoMarker,
+                    Transform::from_translation(Vec3::Z * 5.),
+                    UiTextSize::from(Rh(5.)),
+                    Text2d::new(stats_label(&GameStatistics::default())),
+                ))
+                .observe(ui_hover_state::<Pointer<Over>, true>)
+                .observe(ui_hover_state::<Pointer<Out>, false>);
+
+                ui.spawn((
+                    Name::new("Speed info"),
+                    UiLayout::solid()
+                        .size((Ab(150.), Rl(100.)))
+                        .align_x(Align::RIGHT)
+                        .pack(),
+                    UiMeshPlane2d,
+                    MeshMaterial2d(materials.add(UI_INFO_BACKGROUND)),
+                ))
+                .with_child((
+                    SpeedInfoMarker,
+                    Transform::from_translation(Vec3::Z * 5.),
+                    UiTextSize::from(Rh(5.)),
+                    Text2d::new(speed_label(GameSpeed::default())),
+                ))
+                .observe(ui_hover_state::<Pointer<Over>, true>)
+                .observe(ui_hover_state::<Pointer<Out>, false>)
+                .observe(
+                    |_: Trigger<Pointer<Click>>,
+                     state: Res<State<GameState>>,
+                     mut speed: ResMut<GameSpeed>| {
+                        if *state.get() != GameState::GameOver {
+                            *speed = speed.next();
+                        }
+                    },
+                );
             });
         });
 }
 
+fn wave_label(current: usize, last: usize, difficulty: Difficulty) -> String {
+    format!("Wave {current}/{last} ({difficulty:?})")
+}
+
 fn update_wave(
     mut wave_info: Single<&mut Text2d, With<WaveInfoMarker>>,
     mut events: EventReader<WaveStart>,
     wave: Res<WaveInfo>,
+    difficulty: Res<Difficulty>,
 ) {
     for new_wave in events.read() {
-        wave_info.0 = format!("Wave {}/{}", **new_wave, wave.last);
+        wave_info.0 = wave_label(**new_wave, wave.last, *difficulty);
     }
 }
 
+fn speed_label(speed: GameSpeed) -> String {
+    format!("Speed: {}x", speed.multiplier())
+}
+
+fn update_speed(
+    mut speed_info: Single<&mut Text2d, With<SpeedInfoMarker>>,
+    speed: Res<GameSpeed>,
+) {
+    speed_info.0 = speed_label(*speed);
+}
+
+fn stats_label(stats: &GameStatistics) -> String {
+    format!(
+        "Spent: {}  Kills: {}",
+        stats.money_spend, stats.enemies_killed
+    )
+}
+
+fn update_stats(
+    mut stats_info: Single<&mut Text2d, With<StatsInfoMarker>>,
+    stats: Res<GameStatistics>,
+) {
+    stats_info.0 = stats_label(&stats);
+}
+
 fn update_currency(
-    mut currency_info: Single<&mut Text2d, With<CurrencyInfoMarker>>,
+    mut currency_info: Single<
+        (&mut Text2d, &mut TextColor, &mut CurrencyTween),
+        With<CurrencyInfoMarker>,
+    >,
     currency: Res<Currency>,
+    sandbox: Res<Sandbox>,
+    selection: Res<SelectedTower>,
+    placing: Res<State<TowerPlacingState>>,
+    time: Res<Time<Real>>,
 ) {
-    if currency.is_changed() {
-        currency_info.0 = format!("Money: {}", **currency);
+    let (text, color, tween) = &mut *currency_info;
+    let base = match *placing.get() == TowerPlacingState::Placing
+        && !sandbox.affords(&currency, selection.cost())
+    {
+        true => CURRENCY_LACKING_COLOR,
+        false => Srgba::WHITE,
+    };
+
+    // Several changes in a row restart the tween from whatever is displayed right now,
+    // so they add up to one smooth transition towards the latest value
+    if currency.is_changed() && (tween.target != **currency || tween.displayed.is_none())
+    {
+        let displayed = tween.displayed.unwrap_or(**currency as f32);
+        tween.flash = match (**currency as f32).partial_cmp(&displayed) {
+            Some(std::cmp::Ordering::Greater) => CURRENCY_GAIN_COLOR,
+            Some(std::cmp::Ordering::Less) => CURRENCY_SPEND_COLOR,
+            _ => Srgba::WHITE,
+        };
+        tween.start = displayed;
+        tween.target = **currency;
+        tween.timer.reset();
     }
+
+    if tween.timer.finished() {
+        color.0 = base.into();
+        return;
+    }
+    tween.timer.tick(time.delta());
+
+    let progress = tween.timer.fraction();
+    let displayed = tween.start + (tween.target as f32 - tween.start) * progress;
+    tween.displayed = Some(displayed);
+    text.0 = format!("Money: {}", displayed.round());
+    color.0 = tween.flash.mix(&base, progress).into();
 }
@@ -0,0 +1,80 @@
+use bevy::prelude::*;
+
+use crate::{
+    app_state::{AppState, TowerPlacingState},
+    tower::SelectedTower,
+    ui::TEXT_COLOR,
+};
+
+pub struct TowerInfoPlugin;
+
+impl Plugin for TowerInfoPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<TowerInfo>()
+            .add_systems(OnEnter(AppState::Game), spawn_tower_info)
+            .add_systems(
+                OnEnter(TowerPlacingState::Placing),
+                (show_tower_info::<true>, update_tower_info),
+            )
+            .add_systems(OnExit(TowerPlacingState::Placing), show_tower_info::<false>)
+            .add_systems(
+                Update,
+                update_tower_info
+                    .run_if(resource_changed::<SelectedTower>)
+                    .run_if(in_state(AppState::Game)),
+            );
+    }
+}
+
+const BACKGROUND_COLOR: Color = Color::srgba(0., 0., 0., 0.75);
+
+/// Stats of the selected tower type, shown above the bottom bar while placing
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct TowerInfo;
+
+fn spawn_tower_info(mut commands: Commands) {
+    commands.spawn((
+        Name::new("Tower info"),
+        TowerInfo,
+        Text::default(),
+        TextFont {
+            font_size: 16.,
+            ..default()
+        },
+        TextColor(TEXT_COLOR),
+        Node {
+            position_type: PositionType::Absolute,
+            left: Val::Px(10.),
+            bottom: Val::Px(160.),
+            padding: UiRect::all(Val::Px(8.)),
+            ..default()
+        },
+        BackgroundColor(BACKGROUND_COLOR),
+        // Purely informative, clicks go through to the board
+        Pickable::IGNORE,
+        Visibility::Hidden,
+    ));
+}
+
+fn show_tower_info<const SHOWN: bool>(
+    mut info: Single<&mut Visibility, With<TowerInfo>>,
+) {
+    **info = match SHOWN {
+        true => Visibility::Inherited,
+        false => Visibility::Hidden,
+    };
+}
+
+fn update_tower_info(
+    mut info: Single<&mut Text, With<TowerInfo>>,
+    selection: Res<SelectedTower>,
+) {
+    let stats = selection
+        .stat_lines()
+        .into_iter()
+        .map(|(label, value)| format!("{label}: {value}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    info.0 = format!("{:?}\n{stats}", selection.variant);
+}
@@ -0,0 +1,362 @@
+use bevy::{
+    input::common_conditions::input_just_pressed, prelude::*, window::PrimaryWindow,
+};
+
+use crate::{
+    Settings,
+    app_state::{GameState, TowerPlacingState, UiHoverState, escape_game},
+    game_loop::{Currency, Sandbox},
+    grid::{Grid, GridConfig, world_to_grid_coords},
+    health::Health,
+    tower::{RepairTower, SellTower, TargetingMode, Tower, UpgradeTower},
+    ui::{TEXT_COLOR, despawn_menu, helpers::ui_hover_state},
+};
+
+pub struct TowerMenuPlugin;
+
+impl Plugin for TowerMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<TowerMenu>()
+            .register_type::<RepairButton>()
+            .register_type::<TargetingButton>()
+            .register_type::<UpgradeButton>()
+            .add_systems(
+                OnEnter(TowerPlacingState::Placing),
+                despawn_menu::<TowerMenu>,
+            )
+            .add_systems(
+                Update,
+                (
+                    open_tower_menu
+                        .run_if(input_just_pressed(MouseButton::Right))
+                        .run_if(
+                            in_state(GameState::Running)
+                                .and(in_state(TowerPlacingState::None))
+                                .and(not(in_state(UiHoverState::Hovering))),
+                        ),
+                    (
+                        close_tower_menu
+                            .run_if(
+                                input_just_pressed(KeyCode::Escape)
+                                    .or(input_just_pressed(MouseButton::Left)
+                                        .and(not(in_state(UiHoverState::Hovering)))),
+                            )
+                            .before(escape_game),
+                        close_orphaned_tower_menu,
+                        update_repair_button,
+                        upgrade_menu_tower.run_if(input_just_pressed(KeyCode::KeyU)),
+                        update_upgrade_button,
+                        cycle_targeting.run_if(input_just_pressed(KeyCode::KeyT)),
+                        update_targeting_button
+                            .run_if(any_with_component::<TargetingButton>),
+                    )
+                        .run_if(any_with_component::<TowerMenu>),
+                ),
+            );
+    }
+}
+
+const MENU_WIDTH: f32 = 200.;
+const BUTTON_COLOR: Color = Color::srgb(0.3, 0.3, 0.3);
+const BUTTON_COLOR_HOVER: Color = Color::srgb(1., 0., 0.);
+const TEXT_COLOR_DISABLED: Color = Color::srgb(0.5, 0.5, 0.5);
+
+/// Context menu of a single tower, opened by right-clicking it
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct TowerMenu {
+    tower: Entity,
+}
+
+/// Its label shows the current repair cost, it is greyed out if there is nothing to repair
+/// or the player can't afford it
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct RepairButton;
+
+/// Its label shows the level and upgrade cost, it is greyed out at the max level or if the
+/// player can't afford it. Pressing U upgrades as well
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct UpgradeButton;
+
+/// Shows the targeting mode of the tower, clicking it or pressing T switches to the next one
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct TargetingButton;
+
+fn open_tower_menu(
+    mut commands: Commands,
+    window: Single<&Window, With<PrimaryWindow>>,
+    cam: Single<(&Camera, &GlobalTransform), With<Camera>>,
+    grid: Res<Grid>,
+    config: Res<GridConfig>,
+    towers: Query<&Tower>,
+    menus: Query<Entity, With<TowerMenu>>,
+    settings: Res<Settings>,
+    sandbox: Res<Sandbox>,
+) {
+    for menu in &menus {
+        commands.entity(menu).despawn();
+    }
+
+    let Some(cursor_pos) = window.cursor_position() else {
+        return;
+    };
+    let (camera, cam_transform) = *cam;
+    let Some(tower_entity) = camera
+        .viewport_to_world_2d(cam_transform, cursor_pos)
+        .ok()
+        .and_then(|pos| world_to_grid_coords(pos, &config))
+        .and_then(|pos| grid.towers.get(&pos).copied())
+    else {
+        return;
+    };
+    let Ok(tower) = towers.get(tower_entity) else {
+        return;
+    };
+
+    commands
+        .spawn((
+            Name::new("Tower menu"),
+            TowerMenu {
+                tower: tower_entity,
+            },
+            Node {
+                position_type: PositionType::Absolute,
+                left: Val::Px(cursor_pos.x),
+                top: Val::Px(cursor_pos.y),
+                width: Val::Px(MENU_WIDTH),
+                flex_direction: FlexDirection::Column,
+                padding: UiRect::all(Val::Px(5.)),
+                row_gap: Val::Px(5.),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0., 0., 0., 0.85)),
+        ))
+        .with_children(|p| {
+            p.spawn((
+                Text::new(format!("{:?}", tower.variant)),
+                TextFont {
+                    font_size: 25.,
+                    ..Default::default()
+                },
+                TextColor(TEXT_COLOR),
+                Pickable::IGNORE,
+            ));
+            menu_button(p, "Repair".to_string())
+                .insert(RepairButton)
+                .observe(
+                    |_: Trigger<Pointer<Click>>,
+                     menu: Single<&TowerMenu>,
+                     mut events: EventWriter<RepairTower>| {
+                        // Full health and missing currency are handled when repairing
+                        events.write(RepairTower(menu.tower));
+                    },
+                );
+            menu_button(p, "Upgrade".to_string())
+                .insert(UpgradeButton)
+                .observe(
+                    |_: Trigger<Pointer<Click>>,
+                     menu: Single<&TowerMenu>,
+                     mut events: EventWriter<UpgradeTower>| {
+                        // The max level and missing currency are handled when upgrading
+                        events.write(UpgradeTower(menu.tower));
+                    },
+                );
+            if tower.has_targeting() {
+                menu_button(p, targeting_label(tower.targeting))
+                    .insert(TargetingButton)
+                    .observe(
+                        |_: Trigger<Pointer<Click>>,
+                         menu: Single<&TowerMenu>,
+                         mut towers: Query<&mut Tower>| {
+                            if let Ok(mut tower) = towers.get_mut(menu.tower) {
+                                tower.targeting = tower.targeting.next();
+                            }
+                        },
+                    );
+            }
+            menu_button(
+                p,
+                format!(
+                    "Sell (+{})",
+                    sandbox.charge(tower.sell_value(settings.sell_refund))
+                ),
+            )
+            .observe(
+                |_: Trigger<Pointer<Click>>,
+                 mut commands: Commands,
+                 menu: Single<(Entity, &TowerMenu)>,
+                 mut events: EventWriter<SellTower>,
+                 mut next_state: ResMut<NextState<UiHoverState>>| {
+                    events.write(SellTower(menu.1.tower));
+                    commands.entity(menu.0).despawn();
+                    next_state.set(UiHoverState::None);
+                },
+            );
+        })
+        .observe(ui_hover_state::<Pointer<Over>, true>)
+        .observe(ui_hover_state::<Pointer<Out>, false>);
+}
+
+/// Grey button filling the width of its parent, also used by the pause menu
+pub(super) fn menu_button<'a>(
+    p: &'a mut ChildSpawnerCommands,
+    label: String,
+) -> EntityCommands<'a> {
+    let mut button = p.spawn((
+        Name::new(format!("Menu button: {label}")),
+        Node {
+            width: Val::Percent(100.),
+            padding: UiRect::all(Val::Px(5.)),
+            ..default()
+        },
+        BackgroundColor(BUTTON_COLOR),
+    ));
+    button
+        .with_child((
+            Text::new(label),
+            TextFont {
+                font_size: 20.,
+                ..Default::default()
+            },
+            TextColor(TEXT_COLOR),
+            Pickable::IGNORE,
+        ))
+        .observe(
+            |trigger: Trigger<Pointer<Over>>, mut colors: Query<&mut BackgroundColor>| {
+                if let Ok(mut color) = colors.get_mut(trigger.target()) {
+                    color.0 = BUTTON_COLOR_HOVER;
+                }
+            },
+        )
+        .observe(
+            |trigger: Trigger<Pointer<Out>>, mut colors: Query<&mut BackgroundColor>| {
+                if let Ok(mut color) = colors.get_mut(trigger.target()) {
+                    color.0 = BUTTON_COLOR;
+                }
+            },
+        );
+    button
+}
+
+fn close_tower_menu(
+    mut commands: Commands,
+    menu: Single<Entity, With<TowerMenu>>,
+    mut input: ResMut<ButtonInput<KeyCode>>,
+    mut next_state: ResMut<NextState<UiHoverState>>,
+) {
+    commands.entity(*menu).despawn();
+    // Escape should only close the menu, not pause the game as well
+    input.clear_just_pressed(KeyCode::Escape);
+    next_state.set(UiHoverState::None);
+}
+
+/// Closes the menu if its tower got destroyed in the meantime
+fn close_orphaned_tower_menu(
+    mut commands: Commands,
+    menu: Single<(Entity, &TowerMenu)>,
+    towers: Query<(), With<Tower>>,
+    mut next_state: ResMut<NextState<UiHoverState>>,
+) {
+    if !towers.contains(menu.1.tower) {
+        commands.entity(menu.0).despawn();
+        next_state.set(UiHoverState::None);
+    }
+}
+
+fn update_repair_button(
+    menu: Single<&TowerMenu>,
+    towers: Query<(&Tower, &Health)>,
+    currency: Res<Currency>,
+    sandbox: Res<Sandbox>,
+    button: Single<&Children, With<RepairButton>>,
+    mut texts: Query<(&mut Text, &mut TextColor)>,
+) {
+    let Ok((tower, health)) = towers.get(menu.tower) else {
+        return;
+    };
+    let cost = tower.repair_cost(health);
+    let label = match cost {
+        0 => "Repair".to_string(),
+        cost => format!("Repair (-{cost})"),
+    };
+    let color = match cost == 0 || !sandbox.affords(&currency, cost) {
+        true => TEXT_COLOR_DISABLED,
+        false => TEXT_COLOR,
+    };
+
+    let mut iter = texts.iter_many_mut(button.iter());
+    while let Some((mut text, mut text_color)) = iter.fetch_next() {
+        if text.0 != label {
+            text.0 = label.clone();
+        }
+        text_color.set_if_neq(TextColor(color));
+    }
+}
+
+fn upgrade_menu_tower(menu: Single<&TowerMenu>, mut events: EventWriter<UpgradeTower>) {
+    events.write(UpgradeTower(menu.tower));
+}
+
+fn update_upgrade_button(
+    menu: Single<&TowerMenu>,
+    towers: Query<&Tower>,
+    currency: Res<Currency>,
+    sandbox: Res<Sandbox>,
+    button: Single<&Children, With<UpgradeButton>>,
+    mut texts: Query<(&mut Text, &mut TextColor)>,
+) {
+    let Ok(tower) = towers.get(menu.tower) else {
+        return;
+    };
+    let cost = tower.upgrade_cost();
+    let label = match cost {
+        Some(cost) => format!("Upgrade to {} (-{cost})", tower.level + 1),
+        None => format!("Level {} (max)", tower.level),
+    };
+    let color = match cost.is_none_or(|cost| !sandbox.affords(&currency, cost)) {
+        true => TEXT_COLOR_DISABLED,
+        false => TEXT_COLOR,
+    };
+
+    let mut iter = texts.iter_many_mut(button.iter());
+    while let Some((mut text, mut text_color)) = iter.fetch_next() {
+        if text.0 != label {
+            text.0 = label.clone();
+        }
+        text_color.set_if_neq(TextColor(color));
+    }
+}
+
+fn targeting_label(mode: TargetingMode) -> String {
+    format!("Target: {mode:?}")
+}
+
+fn cycle_targeting(menu: Single<&TowerMenu>, mut towers: Query<&mut Tower>) {
+    let Ok(mut tower) = towers.get_mut(menu.tower) else {
+        return;
+    };
+    if tower.has_targeting() {
+        tower.targeting = tower.targeting.next();
+    }
+}
+
+fn update_targeting_button(
+    menu: Single<&TowerMenu>,
+    towers: Query<&Tower>,
+    button: Single<&Children, With<TargetingButton>>,
+    mut texts: Query<&mut Text>,
+) {
+    let Ok(tower) = towers.get(menu.tower) else {
+        return;
+    };
+    let label = targeting_label(tower.targeting);
+    let mut iter = texts.iter_many_mut(button.iter());
+    while let Some(mut text) = iter.fetch_next() {
+        if text.0 != label {
+            text.0 = label.clone();
+        }
+    }
+}
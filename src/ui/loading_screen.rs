@@ -0,0 +1,112 @@
+use bevy::{
+    asset::{LoadState, UntypedAssetId},
+    prelude::*,
+};
+
+use crate::{AssetLock, app_state::AppState};
+
+use super::{TEXT_COLOR, despawn_menu};
+
+pub struct LoadingScreenPlugin;
+
+impl Plugin for LoadingScreenPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<LoadingScreen>()
+            .register_type::<ProgressBar>()
+            .add_systems(OnEnter(AppState::Loading), build_ui)
+            .add_systems(OnExit(AppState::Loading), despawn_menu::<LoadingScreen>)
+            .add_systems(Update, track_loading.run_if(in_state(AppState::Loading)));
+    }
+}
+
+const BAR_WIDTH: f32 = 600.;
+const BAR_HEIGHT: f32 = 30.;
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct LoadingScreen;
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct ProgressBar;
+
+fn build_ui(mut commands: Commands) {
+    commands
+        .spawn((
+            Name::new("Loading screen"),
+            LoadingScreen,
+            Node {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(20.),
+                ..default()
+            },
+            BackgroundColor(Color::BLACK),
+        ))
+        .with_children(|p| {
+            p.spawn((
+                Text::new("Loading..."),
+                TextFont {
+                    font_size: 40.,
+                    ..Default::default()
+                },
+                TextColor(TEXT_COLOR),
+            ));
+            p.spawn((
+                Node {
+                    width: Val::Px(BAR_WIDTH),
+                    height: Val::Px(BAR_HEIGHT),
+                    ..default()
+                },
+                BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+            ))
+            .with_child((
+                ProgressBar,
+                Node {
+                    width: Val::Percent(0.),
+                    height: Val::Percent(100.),
+                    ..default()
+                },
+                BackgroundColor(TEXT_COLOR),
+            ));
+        });
+}
+
+/// Waits for every asset of the `AssetLock` before showing the menu. Failed assets count
+/// as done, bevy draws a placeholder for them instead of hanging here forever.
+fn track_loading(
+    locks: Query<&AssetLock>,
+    asset_server: Res<AssetServer>,
+    mut bar: Single<&mut Node, With<ProgressBar>>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut reported: Local<Vec<UntypedAssetId>>,
+) {
+    let handles: Vec<&UntypedHandle> = locks.iter().flat_map(|lock| &lock.0).collect();
+    let mut done = 0;
+    for handle in &handles {
+        match asset_server.get_load_state(handle.id()) {
+            Some(LoadState::Loaded) => done += 1,
+            Some(LoadState::Failed(e)) => {
+                done += 1;
+                if !reported.contains(&handle.id()) {
+                    reported.push(handle.id());
+                    error!("Failed to load {:?}: {e}", handle.path());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let progress = match handles.len() {
+        0 => 1.,
+        total => done as f32 / total as f32,
+    };
+    bar.width = Val::Percent(progress * 100.);
+
+    if done == handles.len() {
+        next_state.set(AppState::Menu);
+    }
+}
@@ -1,6 +1,10 @@
 use bevy::prelude::*;
 
-use crate::app_state::{AppState, MenuState};
+use crate::{
+    app_state::{AppState, MenuState},
+    game_loop::Difficulty,
+    save::LoadGame,
+};
 
 use super::{despawn_menu, helpers::build_menu};
 pub struct MainMenuPlugin;
@@ -8,6 +12,7 @@ pub struct MainMenuPlugin;
 impl Plugin for MainMenuPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<MainMenuMarker>()
+            .register_type::<DifficultyMarker>()
             .add_systems(OnEnter(MenuState::MainMenu), build_ui)
             .add_systems(OnExit(MenuState::MainMenu), despawn_menu::<MainMenuMarker>);
     }
@@ -17,15 +22,29 @@ impl Plugin for MainMenuPlugin {
 #[reflect(Component)]
 struct MainMenuMarker;
 
-const MENU_BUTTONS: &[&str] = &["Play", "Settings", "Quit"];
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+struct DifficultyMarker;
+
 const BUTTON_WIDTH: f32 = 450.;
 const BUTTON_HEIGHT: f32 = 60.;
 const BUTTON_GAP: f32 = 50.;
 
-fn build_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let buttons = MENU_BUTTONS
+fn build_ui(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    difficulty: Res<Difficulty>,
+) {
+    let buttons = ["Play", difficulty.label(), "Load", "Settings", "Quit"]
         .iter()
-        .map(|button| (*button, action(button), None))
+        .map(|button| {
+            let marker = Difficulty::VARIANTS.contains(button).then_some(
+                (|cmds: &mut EntityCommands| {
+                    cmds.insert(DifficultyMarker);
+                }) as fn(&mut EntityCommands),
+            );
+            (*button, action(button), marker)
+        })
         .collect();
     build_menu(
         &mut commands,
@@ -49,6 +68,23 @@ fn action(button: &str) -> fn(&mut EntityCommands) {
                 },
             );
         },
+        v if Difficulty::VARIANTS.contains(&v) => |cmds: &mut EntityCommands| {
+            cmds.observe(
+                |_: Trigger<Pointer<Click>>,
+                 mut difficulty: ResMut<Difficulty>,
+                 mut text: Single<&mut Text2d, With<DifficultyMarker>>| {
+                    *difficulty = difficulty.next();
+                    text.0 = difficulty.label().to_string();
+                },
+            );
+        },
+        "Load" => |cmds: &mut EntityCommands| {
+            cmds.observe(
+                |_: Trigger<Pointer<Click>>, mut events: EventWriter<LoadGame>| {
+                    events.write(LoadGame);
+                },
+            );
+        },
         "Settings" => |cmds: &mut EntityCommands| {
             cmds.observe(
                 |_: Trigger<Pointer<Click>>,
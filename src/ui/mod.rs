@@ -1,11 +1,13 @@
 use bevy::prelude::*;
 use bevy_lunex::{Dimension, UiLunexPlugins, UiTextSize};
 use hud::HUDPlugin;
+use loading_screen::LoadingScreenPlugin;
 use main_menu::MainMenuPlugin;
 use settings::SettingsMenuPlugin;
 
 pub mod helpers;
 mod hud;
+mod loading_screen;
 mod main_menu;
 mod settings;
 
@@ -18,7 +20,12 @@ impl Plugin for UIPlugin {
         app.register_type::<Dimension>()
             .register_type::<UiTextSize>()
             .add_plugins(UiLunexPlugins)
-            .add_plugins((MainMenuPlugin, HUDPlugin, SettingsMenuPlugin));
+            .add_plugins((
+                LoadingScreenPlugin,
+                MainMenuPlugin,
+                HUDPlugin,
+                SettingsMenuPlugin,
+            ));
     }
 }
 
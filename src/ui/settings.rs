@@ -1,6 +1,8 @@
 use bevy::prelude::*;
 
-use crate::{Settings, app_state::MenuState, soundtrack::SoundtrackToggled};
+use crate::{
+    Settings, app_state::MenuState, soundtrack::SoundtrackToggled, theme::Theme,
+};
 
 use super::{despawn_menu, helpers::build_menu};
 pub struct SettingsMenuPlugin;
@@ -10,6 +12,11 @@ impl Plugin for SettingsMenuPlugin {
         app.register_type::<SettingsMarker>()
             .register_type::<SfxMarker>()
             .register_type::<SoundtrackMarker>()
+            .register_type::<ThemeMarker>()
+            .register_type::<ReducedMotionMarker>()
+            .register_type::<KeyboardPlacementMarker>()
+            .register_type::<SfxVolumeMarker>()
+            .register_type::<MusicVolumeMarker>()
             .add_systems(OnEnter(MenuState::Settings), build_ui)
             .add_systems(OnExit(MenuState::Settings), despawn_menu::<SettingsMarker>);
     }
@@ -25,38 +32,74 @@ struct SfxMarker;
 #[derive(Component, Reflect, Default)]
 #[reflect(Component)]
 struct SoundtrackMarker;
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+struct ThemeMarker;
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+struct ReducedMotionMarker;
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+struct KeyboardPlacementMarker;
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+struct SfxVolumeMarker;
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+struct MusicVolumeMarker;
 
 const BUTTON_WIDTH: f32 = 750.;
 const BUTTON_HEIGHT: f32 = 60.;
-const BUTTON_GAP: f32 = 50.;
+const BUTTON_GAP: f32 = 40.;
 
 fn build_ui(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     settings: Res<Settings>,
+    theme: Res<Theme>,
 ) {
-    let buttons = [settings.sfx_label(), settings.soundtrack_label(), "Return"]
-        .iter()
-        .map(|button| {
-            (
-                *button,
-                action(button),
-                match *button {
-                    v if v == Settings::SFX_VARIANTS[0]
-                        || v == Settings::SFX_VARIANTS[1] =>
-                    {
-                        Some(insert_marker::<SfxMarker>())
-                    }
-                    v if v == Settings::SOUNDTRACK_VARIANTS[0]
-                        || v == Settings::SOUNDTRACK_VARIANTS[1] =>
-                    {
-                        Some(insert_marker::<SoundtrackMarker>())
-                    }
-                    _ => None,
-                },
-            )
-        })
-        .collect();
+    let buttons = [
+        settings.sfx_label(),
+        settings.sfx_volume_label(),
+        settings.soundtrack_label(),
+        settings.music_volume_label(),
+        theme.label(),
+        settings.reduced_motion_label(),
+        settings.keyboard_placement_label(),
+        "Return",
+    ]
+    .iter()
+    .map(|button| {
+        (
+            *button,
+            action(button),
+            match *button {
+                v if v == Settings::SFX_VARIANTS[0] || v == Settings::SFX_VARIANTS[1] => {
+                    Some(insert_marker::<SfxMarker>())
+                }
+                v if v == Settings::SOUNDTRACK_VARIANTS[0]
+                    || v == Settings::SOUNDTRACK_VARIANTS[1] =>
+                {
+                    Some(insert_marker::<SoundtrackMarker>())
+                }
+                v if Theme::VARIANTS.contains(&v) => Some(insert_marker::<ThemeMarker>()),
+                v if Settings::REDUCED_MOTION_VARIANTS.contains(&v) => {
+                    Some(insert_marker::<ReducedMotionMarker>())
+                }
+                v if Settings::KEYBOARD_PLACEMENT_VARIANTS.contains(&v) => {
+                    Some(insert_marker::<KeyboardPlacementMarker>())
+                }
+                v if Settings::SFX_VOLUME_VARIANTS.contains(&v) => {
+                    Some(insert_marker::<SfxVolumeMarker>())
+                }
+                v if Settings::MUSIC_VOLUME_VARIANTS.contains(&v) => {
+                    Some(insert_marker::<MusicVolumeMarker>())
+                }
+                _ => None,
+            },
+        )
+    })
+    .collect();
     build_menu(
         &mut commands,
         &asset_server,
@@ -100,6 +143,66 @@ fn action(button: &str) -> fn(&mut EntityCommands) {
                 );
             }
         }
+        v if Theme::VARIANTS.contains(&v) => |cmds: &mut EntityCommands| {
+            cmds.observe(
+                |_: Trigger<Pointer<Click>>,
+                 mut theme: ResMut<Theme>,
+                 mut text: Single<&mut Text2d, With<ThemeMarker>>| {
+                    *theme = theme.next();
+                    text.0 = theme.label().to_string();
+                },
+            );
+        },
+        v if Settings::REDUCED_MOTION_VARIANTS.contains(&v) => {
+            |cmds: &mut EntityCommands| {
+                cmds.observe(
+                    |_: Trigger<Pointer<Click>>,
+                     mut settings: ResMut<Settings>,
+                     mut text: Single<&mut Text2d, With<ReducedMotionMarker>>| {
+                        settings.reduced_motion = !settings.reduced_motion;
+                        text.0 = settings.reduced_motion_label().to_string();
+                    },
+                );
+            }
+        }
+        v if Settings::KEYBOARD_PLACEMENT_VARIANTS.contains(&v) => {
+            |cmds: &mut EntityCommands| {
+                cmds.observe(
+                    |_: Trigger<Pointer<Click>>,
+                     mut settings: ResMut<Settings>,
+                     mut text: Single<&mut Text2d, With<KeyboardPlacementMarker>>| {
+                        settings.keyboard_placement = !settings.keyboard_placement;
+                        text.0 = settings.keyboard_placement_label().to_string();
+                    },
+                );
+            }
+        }
+        // Left click turns the volume up, right click turns it down
+        v if Settings::SFX_VOLUME_VARIANTS.contains(&v) => |cmds: &mut EntityCommands| {
+            cmds.observe(
+                |trigger: Trigger<Pointer<Click>>,
+                 mut settings: ResMut<Settings>,
+                 mut text: Single<&mut Text2d, With<SfxVolumeMarker>>| {
+                    let up = trigger.event().button == PointerButton::Primary;
+                    settings.sfx_volume = Settings::step_volume(settings.sfx_volume, up);
+                    text.0 = settings.sfx_volume_label().to_string();
+                },
+            );
+        },
+        v if Settings::MUSIC_VOLUME_VARIANTS.contains(&v) => {
+            |cmds: &mut EntityCommands| {
+                cmds.observe(
+                    |trigger: Trigger<Pointer<Click>>,
+                     mut settings: ResMut<Settings>,
+                     mut text: Single<&mut Text2d, With<MusicVolumeMarker>>| {
+                        let up = trigger.event().button == PointerButton::Primary;
+                        settings.music_volume =
+                            Settings::step_volume(settings.music_volume, up);
+                        text.0 = settings.music_volume_label().to_string();
+                    },
+                );
+            }
+        }
         "Return" => |cmds: &mut EntityCommands| {
             cmds.observe(
                 |_: Trigger<Pointer<Click>>,